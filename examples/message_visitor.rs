@@ -0,0 +1,78 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::ops::ControlFlow;
+
+use mail_parser::*;
+
+#[derive(Default)]
+struct AttachmentCounter {
+    attachments: usize,
+    bytes: usize,
+    is_attachment: bool,
+}
+
+impl<'x> MessageVisitor<'x> for AttachmentCounter {
+    fn on_header(&mut self, _: MessagePartId, header: &Header<'x>) -> ControlFlow<()> {
+        if let (HeaderName::ContentDisposition, Some(disposition)) =
+            (&header.name, header.value.as_content_type())
+        {
+            self.is_attachment = disposition.is_attachment();
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn on_part_start(&mut self, _: MessagePartId, _: Option<&ContentType<'x>>) -> ControlFlow<()> {
+        if self.is_attachment {
+            self.attachments += 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn on_body_chunk(&mut self, _: MessagePartId, chunk: &[u8]) -> ControlFlow<()> {
+        if std::mem::take(&mut self.is_attachment) {
+            self.bytes += chunk.len();
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn main() {
+    let input = br#"From: Art Vandelay <art@vandelay.com>
+Subject: Quarterly figures
+Content-Type: multipart/mixed; boundary="festivus"
+
+--festivus
+Content-Type: text/plain
+
+Please find the figures attached.
+--festivus
+Content-Type: text/csv
+Content-Disposition: attachment; filename="imports.csv"
+
+latex,1000
+--festivus
+Content-Type: application/octet-stream
+Content-Disposition: attachment; filename="exports.bin"
+Content-Transfer-Encoding: base64
+
+AAECAwQFBgcICQ==
+--festivus--
+"#;
+
+    let mut counter = AttachmentCounter::default();
+    let _ = MessageParser::default().parse_with_visitor(&input[..], &mut counter);
+
+    println!(
+        "Found {} attachments, {} bytes in total.",
+        counter.attachments, counter.bytes
+    );
+}
@@ -9,8 +9,16 @@
  * except according to those terms.
  */
 
+use std::{
+    borrow::Cow,
+    fmt::{self, Display, Write},
+};
+
 use crate::{Addr, Address, Group};
 
+#[cfg(feature = "serde_support")]
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
 impl<'x> Address<'x> {
     /// Returns the first address in the list, or the first address in the first group.
     pub fn first(&self) -> Option<&Addr<'x>> {
@@ -88,18 +96,40 @@ impl<'x> Address<'x> {
             Address::List(list) => list.iter().any(|a| {
                 a.address
                     .as_ref()
-                    .map_or(false, |a| a.eq_ignore_ascii_case(addr))
+                    .is_some_and(|a| a.eq_ignore_ascii_case(addr))
             }),
             Address::Group(group) => group.iter().any(|group| {
                 group.addresses.iter().any(|a| {
                     a.address
                         .as_ref()
-                        .map_or(false, |a| a.eq_ignore_ascii_case(addr))
+                        .is_some_and(|a| a.eq_ignore_ascii_case(addr))
                 })
             }),
         }
     }
 
+    /// Appends the comments of each address to its name, as done by
+    /// default when parsing messages.
+    pub(crate) fn merge_comments(&mut self) {
+        let addresses: Box<dyn Iterator<Item = &mut Addr<'x>>> = match self {
+            Address::List(list) => Box::new(list.iter_mut()),
+            Address::Group(group) => Box::new(
+                group
+                    .iter_mut()
+                    .flat_map(|group| group.addresses.iter_mut()),
+            ),
+        };
+
+        for addr in addresses {
+            if let Some(comment) = addr.comment.take() {
+                addr.name = Some(match addr.name.take() {
+                    Some(name) => format!("{name} ({comment})").into(),
+                    None => comment,
+                });
+            }
+        }
+    }
+
     pub fn into_owned(self) -> Address<'static> {
         match self {
             Address::List(list) => {
@@ -133,6 +163,7 @@ impl<'x> Addr<'x> {
         Self {
             name: name.map(|name| name.into()),
             address: Some(address.into()),
+            comment: None,
         }
     }
 
@@ -140,6 +171,7 @@ impl<'x> Addr<'x> {
         Addr {
             name: self.name.map(|s| s.into_owned().into()),
             address: self.address.map(|s| s.into_owned().into()),
+            comment: self.comment.map(|s| s.into_owned().into()),
         }
     }
 
@@ -150,4 +182,174 @@ impl<'x> Addr<'x> {
     pub fn address(&self) -> Option<&str> {
         self.address.as_deref()
     }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Splits a sub-address such as `user+tag@example.org` into its base
+    /// local part and its tag, `("user", "tag")`. Local parts with several
+    /// `+` are split on the first one. Returns `None` when the local part has
+    /// no `+` or starts with it.
+    pub fn subaddress(&self) -> Option<(&str, &str)> {
+        let (local, _) = self.address.as_deref()?.rsplit_once('@')?;
+        local.split_once('+').filter(|(base, _)| !base.is_empty())
+    }
+
+    /// Returns the address without its sub-address tag, `user@example.org`
+    /// for `user+tag@example.org`. Addresses without a tag are returned
+    /// unchanged, see `subaddress`.
+    pub fn base_address(&self) -> Option<Cow<'_, str>> {
+        let address = self.address.as_deref()?;
+        Some(match self.subaddress() {
+            Some((base, _)) => {
+                let (_, domain) = address.rsplit_once('@')?;
+                format!("{base}@{domain}").into()
+            }
+            None => address.into(),
+        })
+    }
+
+    /// Returns the original sender of an address rewritten with the Sender
+    /// Rewriting Scheme by a forwarder, or `None` when the local part does
+    /// not start with `SRS0=` or `SRS1=`. For `SRS0=HHH=TT=domain=local@fwd`
+    /// the original sender is `local@domain`. `SRS1` addresses, produced
+    /// when an already rewritten address is forwarded again, embed the
+    /// `SRS0` address of the first forwarder as in
+    /// `SRS1=HHH=fwd1.example==HHH=TT=domain=local@fwd2`. The hash and
+    /// timestamp are not verified.
+    pub fn srs_decode(&self) -> Option<Cow<'_, str>> {
+        let (local, _) = self.address.as_deref()?.trim().rsplit_once('@')?;
+        let prefix = local.get(..5)?;
+        let srs0 = if prefix.eq_ignore_ascii_case("SRS0=") {
+            &local[5..]
+        } else if prefix.eq_ignore_ascii_case("SRS1=") {
+            // Skip the hash and the first forwarder, the rest is the SRS0
+            // address without its "SRS0" prefix
+            let mut parts = local[5..].splitn(3, '=');
+            parts.next()?;
+            parts.next().filter(|forwarder| !forwarder.is_empty())?;
+            parts
+                .next()?
+                .strip_prefix(['=', '+', '-'])
+                .filter(|srs0| !srs0.is_empty())?
+        } else {
+            return None;
+        };
+
+        let mut parts = srs0.splitn(4, '=');
+        let _hash = parts.next()?;
+        let _timestamp = parts.next()?;
+        let domain = parts.next().filter(|domain| !domain.is_empty())?;
+        let local = parts.next().filter(|local| !local.is_empty())?;
+
+        Some(format!("{local}@{domain}").into())
+    }
+}
+
+impl Display for Addr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.name.as_deref(), self.address.as_deref()) {
+            (Some(name), Some(address)) => {
+                write_phrase(f, name)?;
+                write!(f, " <{address}>")?;
+            }
+            (Some(name), None) => write_phrase(f, name)?,
+            (None, Some(address)) => f.write_str(address)?,
+            (None, None) => f.write_str("<>")?,
+        }
+        if let Some(comment) = self.comment.as_deref() {
+            f.write_str(" (")?;
+            write_escaped(f, comment, |ch| matches!(ch, '(' | ')' | '\\'))?;
+            f.write_char(')')?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Group<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = self.name.as_deref() {
+            write_phrase(f, name)?;
+            f.write_char(':')?;
+            if !self.addresses.is_empty() {
+                f.write_char(' ')?;
+                write_list(f, &self.addresses)?;
+            }
+            f.write_char(';')
+        } else {
+            write_list(f, &self.addresses)
+        }
+    }
+}
+
+impl Display for Address<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::List(list) => write_list(f, list),
+            Address::Group(groups) => write_list(f, groups),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, items: &[impl Display]) -> fmt::Result {
+    for (pos, item) in items.iter().enumerate() {
+        if pos > 0 {
+            f.write_str(", ")?;
+        }
+        item.fmt(f)?;
+    }
+    Ok(())
+}
+
+/// Writes a display name as a sequence of atoms when possible, otherwise
+/// as a quoted string with `"` and `\` escaped (RFC 5322 section 3.2.4).
+fn write_phrase(f: &mut fmt::Formatter<'_>, phrase: &str) -> fmt::Result {
+    if !phrase.is_empty()
+        && phrase
+            .split(' ')
+            .all(|word| !word.is_empty() && word.chars().all(is_atext))
+    {
+        f.write_str(phrase)
+    } else {
+        f.write_char('"')?;
+        write_escaped(f, phrase, |ch| matches!(ch, '"' | '\\'))?;
+        f.write_char('"')
+    }
+}
+
+fn write_escaped(
+    f: &mut fmt::Formatter<'_>,
+    text: &str,
+    needs_escape: impl Fn(char) -> bool,
+) -> fmt::Result {
+    for ch in text.chars() {
+        if needs_escape(ch) {
+            f.write_char('\\')?;
+        }
+        f.write_char(ch)?;
+    }
+    Ok(())
+}
+
+fn is_atext(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || !ch.is_ascii() || "!#$%&'*+-/=?^_`{|}~".contains(ch)
+}
+
+// The comment is left out of human readable formats when empty, binary
+// formats such as bincode need every field to be present.
+#[cfg(feature = "serde_support")]
+impl Serialize for Addr<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let has_comment = self.comment.is_some() || !serializer.is_human_readable();
+        let mut addr = serializer.serialize_struct("Addr", 2 + usize::from(has_comment))?;
+        addr.serialize_field("name", &self.name)?;
+        addr.serialize_field("address", &self.address)?;
+        if has_comment {
+            addr.serialize_field("comment", &self.comment)?;
+        } else {
+            addr.skip_field("comment")?;
+        }
+        addr.end()
+    }
 }
@@ -9,7 +9,10 @@
  * except according to those terms.
  */
 
-use crate::{HeaderName, HeaderValue, MessageParser};
+use crate::{
+    decoders::charsets::map::charset_decoder, parsers::header::parser_for, DuplicateHeaderPolicy,
+    HeaderName, HeaderValue, LineEnding, MessageParser,
+};
 
 impl MessageParser {
     /// Create a new builder for a message parser using the default settings.
@@ -23,6 +26,24 @@ impl MessageParser {
         Self {
             header_map: Default::default(),
             def_hdr_parse_fnc: |s| s.parse_raw(),
+            address_comments: false,
+            lowercase_other_headers: false,
+            duplicate_header_policy: DuplicateHeaderPolicy::Last,
+            line_ending: LineEnding::Preserve,
+            mbox_from_line: false,
+            flatten_address_groups: false,
+            recurse_content_types: Vec::new(),
+            decode_composite_cte: false,
+            raw_dkim_arc: false,
+            autodetect_cte: false,
+            tolerant_headers: false,
+            collapse_header_whitespace: false,
+            semicolon_address_separator: false,
+            case_insensitive_boundaries: false,
+            generate_text_from_html: false,
+            max_parts: usize::MAX,
+            max_decompressed_size: 50 * 1024 * 1024,
+            unknown_charset_fallback: None,
         }
     }
 
@@ -33,6 +54,8 @@ impl MessageParser {
     /// * `Content-Id`
     /// * `Content-Description`
     /// * `Content-Location`
+    /// * `Content-Base`
+    /// * `Content-Duration`
     /// * `Content-Transfer-Encoding`
     ///
     /// Adding these MIME headers is required in order to parse message bodies.
@@ -43,6 +66,8 @@ impl MessageParser {
             .header_id(HeaderName::ContentId)
             .header_text(HeaderName::ContentDescription)
             .header_text(HeaderName::ContentLocation)
+            .header_text(HeaderName::ContentBase)
+            .header_text(HeaderName::ContentDuration)
             .header_text(HeaderName::ContentTransferEncoding)
     }
 
@@ -118,6 +143,22 @@ impl MessageParser {
             .header_address(HeaderName::Bcc)
     }
 
+    /// Parse only the given headers, all other headers are stored as raw text.
+    ///
+    /// The MIME headers listed in `with_mime_headers` are always parsed, as
+    /// they are needed to split the message into its parts.
+    pub fn with_header_allowlist(self, headers: &[HeaderName<'_>]) -> Self {
+        headers.iter().fold(
+            self.with_mime_headers().default_header_raw(),
+            |mut parser, header| {
+                parser
+                    .header_map
+                    .insert(header.to_owned(), parser_for(header));
+                parser
+            },
+        )
+    }
+
     /// Remove a custom header parser.
     pub fn without_header(mut self, header: impl Into<HeaderName<'static>>) -> Self {
         self.header_map.remove(&header.into());
@@ -139,7 +180,8 @@ impl MessageParser {
 
     /// Parse a header as an address.
     pub fn header_address(mut self, header: impl Into<HeaderName<'static>>) -> Self {
-        self.header_map.insert(header.into(), |s| s.parse_address());
+        self.header_map
+            .insert(header.into(), |s| s.parse_address_with_comments());
         self
     }
 
@@ -205,6 +247,181 @@ impl MessageParser {
         };
         self
     }
+
+    /// Store `DKIM-Signature` and `ARC-*` headers as raw text regardless of
+    /// the parser configured for other headers, avoiding the cost of
+    /// RFC 2047 decoding their large base64 values.
+    pub fn without_dkim_arc(mut self) -> Self {
+        self.raw_dkim_arc = true;
+        self
+    }
+
+    /// Store the comments found in address headers in `Addr::comment`
+    /// instead of appending them to the address name, for example
+    /// `"John Doe" <jdoe@example.com> (Sales)` is parsed as the name
+    /// `John Doe` and the comment `Sales`.
+    pub fn with_address_comments(mut self, enable: bool) -> Self {
+        self.address_comments = enable;
+        self
+    }
+
+    /// Choose which occurrence of a repeated `Content-Type`,
+    /// `Content-Transfer-Encoding` or `Content-Disposition` header is used to
+    /// structure and decode a message part. Defaults to the last one. All
+    /// occurrences are kept in the part headers and the header accessors
+    /// always return the last one.
+    ///
+    /// Duplicate MIME headers are malformed and may be used to make different
+    /// parsers disagree on the structure of a message, scanners should pick the
+    /// same policy as the software they are protecting.
+    pub fn with_duplicate_header_policy(mut self, policy: DuplicateHeaderPolicy) -> Self {
+        self.duplicate_header_policy = policy;
+        self
+    }
+
+    /// Normalize the line endings of decoded text and HTML parts. The raw
+    /// message and part offsets are not affected.
+    pub fn with_normalize_eol(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Store the names of non-standard headers (`HeaderName::Other`) in
+    /// lowercase, which simplifies using them as map keys.
+    ///
+    /// The original casing is lost, so this option should not be enabled
+    /// when the parsed headers are used for DKIM verification.
+    pub fn with_lowercase_other_headers(mut self, enable: bool) -> Self {
+        self.lowercase_other_headers = enable;
+        self
+    }
+
+    /// Promote the members of address groups into a flat `Address::List`,
+    /// discarding the group names. By default groups are preserved as
+    /// `Address::Group`.
+    pub fn with_flatten_address_groups(mut self, enable: bool) -> Self {
+        self.flatten_address_groups = enable;
+        self
+    }
+
+    /// Skip a leading mbox `From sender date` separator line before parsing,
+    /// so that messages read from mbox files need not be stripped first.
+    /// The line is not part of the returned message's raw contents.
+    pub fn with_mbox_from_line(mut self, enable: bool) -> Self {
+        self.mbox_from_line = enable;
+        self
+    }
+
+    /// Decode `multipart/*` parts declaring a base64 or quoted-printable
+    /// Content-Transfer-Encoding before splitting them into their parts.
+    /// RFC 2045 does not allow encoding composite types, but some broken
+    /// gateways do it anyway. The decoded parts have no raw representation,
+    /// their offsets and those of their headers point to the encoded body of
+    /// the multipart.
+    pub fn with_decode_composite_cte(mut self, enable: bool) -> Self {
+        self.decode_composite_cte = enable;
+        self
+    }
+
+    /// Decode leaf parts declared as `7bit` or `8bit` whose contents are
+    /// clearly base64 or quoted-printable, as produced by some broken
+    /// senders. Parts decoded this way are flagged with `is_encoding_problem`
+    /// and their `encoding` is set to the detected one.
+    pub fn with_autodetect_cte(mut self, enable: bool) -> Self {
+        self.autodetect_cte = enable;
+        self
+    }
+
+    /// Accept `;` as the separator between a header name and its value, as
+    /// in `Subject; Hello`, a defect of some broken senders. The `;` is only
+    /// treated as a separator when it is the first `:` or `;` on the line and
+    /// the text before it is a known header name (any `HeaderName` variant
+    /// other than `Other`), so values such as `X-Foo: a;b` are not affected.
+    pub fn with_tolerant_headers(mut self, enable: bool) -> Self {
+        self.tolerant_headers = enable;
+        self
+    }
+
+    /// Accept `;` as a separator between addresses, as in `a@x; b@y` which
+    /// Outlook produces when users separate recipients with semicolons. A
+    /// `;` only separates addresses outside of a group, it still ends a
+    /// group started with `name:`. By default `;` always ends a group, as
+    /// defined by RFC 5322, so such lists are parsed as unnamed groups.
+    pub fn with_semicolon_address_separator(mut self, enable: bool) -> Self {
+        self.semicolon_address_separator = enable;
+        self
+    }
+
+    /// Match MIME boundary delimiters ignoring ASCII case, to recover
+    /// messages from broken senders that declare `boundary=ABC` but delimit
+    /// their parts with `--abc`. Boundaries are case-sensitive (RFC 2046),
+    /// so this is disabled by default.
+    pub fn with_case_insensitive_boundaries(mut self, enable: bool) -> Self {
+        self.case_insensitive_boundaries = enable;
+        self
+    }
+
+    /// Collapse runs of spaces and tabs into a single space when decoding
+    /// unstructured headers such as `Subject`, the way most mail clients
+    /// display them. Disabled by default so that values are kept exactly as
+    /// they were sent.
+    pub fn with_collapse_header_whitespace(mut self, enable: bool) -> Self {
+        self.collapse_header_whitespace = enable;
+        self
+    }
+
+    /// Convert the HTML bodies of messages without a plain text alternative
+    /// into text parts. By default `text_body` lists the HTML parts of these
+    /// messages; with this option they are replaced by text parts appended
+    /// to `parts` and flagged with `is_synthetic`, which are not part of the
+    /// MIME structure and point to the offsets of the HTML part.
+    pub fn with_generate_text_from_html(mut self, enable: bool) -> Self {
+        self.generate_text_from_html = enable;
+        self
+    }
+
+    /// Stop parsing once a message has `max_parts` parts, including the parts
+    /// of nested messages, and flag it with `is_truncated`, along with the
+    /// nested messages that were being parsed. This bounds the memory used
+    /// by messages made of huge amounts of tiny parts. Nested messages that
+    /// are base64 or quoted-printable encoded count towards the same limit.
+    /// There is no limit by default.
+    pub fn with_max_parts(mut self, max_parts: usize) -> Self {
+        self.max_parts = max_parts;
+        self
+    }
+
+    /// Maximum size in bytes of a part body once decompressed, 50 MiB by
+    /// default. With the `compression` feature, bodies with a
+    /// `Content-Encoding` that decompress to more than this are kept
+    /// compressed and flagged with `is_encoding_problem`.
+    pub fn with_max_decompressed_size(mut self, max_size: usize) -> Self {
+        self.max_decompressed_size = max_size;
+        self
+    }
+
+    /// Decode text parts that are not valid UTF-8 with the given charset, for
+    /// example `windows-1252`, when their charset is missing, unsupported or
+    /// `us-ascii`. By default, and when `charset` is not a supported charset,
+    /// these parts are decoded as UTF-8 with invalid sequences replaced.
+    /// Parts declared as `utf-8` are always decoded as UTF-8.
+    pub fn with_unknown_charset_fallback(mut self, charset: &str) -> Self {
+        self.unknown_charset_fallback = charset_decoder(charset.trim().as_bytes());
+        self
+    }
+
+    /// Parse parts with any of the given `(type, subtype)` content types as
+    /// nested messages, in addition to `message/rfc822` and `message/global`.
+    /// Useful when a sender is known to attach e-mails as
+    /// `application/octet-stream` or `application/mbox`.
+    pub fn with_recurse_content_types(mut self, content_types: &[(&str, &str)]) -> Self {
+        self.recurse_content_types.extend(
+            content_types
+                .iter()
+                .map(|(ctype, subtype)| (ctype.to_ascii_lowercase(), subtype.to_ascii_lowercase())),
+        );
+        self
+    }
 }
 
 impl Default for MessageParser {
@@ -212,3 +429,155 @@ impl Default for MessageParser {
         Self::new()
     }
 }
+
+// Parser functions are compared by address, the same function may have
+// different addresses in different codegen units so equal parsers can
+// compare as different, but never the other way around.
+impl PartialEq for MessageParser {
+    fn eq(&self, other: &Self) -> bool {
+        let MessageParser {
+            header_map,
+            def_hdr_parse_fnc,
+            address_comments,
+            lowercase_other_headers,
+            duplicate_header_policy,
+            line_ending,
+            mbox_from_line,
+            flatten_address_groups,
+            recurse_content_types,
+            decode_composite_cte,
+            raw_dkim_arc,
+            autodetect_cte,
+            tolerant_headers,
+            collapse_header_whitespace,
+            semicolon_address_separator,
+            case_insensitive_boundaries,
+            generate_text_from_html,
+            max_parts,
+            max_decompressed_size,
+            unknown_charset_fallback,
+        } = self;
+
+        header_map.len() == other.header_map.len()
+            && header_map.iter().all(|(name, fnc)| {
+                other
+                    .header_map
+                    .get(name)
+                    .is_some_and(|other_fnc| *fnc as usize == *other_fnc as usize)
+            })
+            && *def_hdr_parse_fnc as usize == other.def_hdr_parse_fnc as usize
+            && unknown_charset_fallback.map(|fnc| fnc as usize)
+                == other.unknown_charset_fallback.map(|fnc| fnc as usize)
+            && *address_comments == other.address_comments
+            && *lowercase_other_headers == other.lowercase_other_headers
+            && *duplicate_header_policy == other.duplicate_header_policy
+            && *line_ending == other.line_ending
+            && *mbox_from_line == other.mbox_from_line
+            && *flatten_address_groups == other.flatten_address_groups
+            && *recurse_content_types == other.recurse_content_types
+            && *decode_composite_cte == other.decode_composite_cte
+            && *raw_dkim_arc == other.raw_dkim_arc
+            && *autodetect_cte == other.autodetect_cte
+            && *tolerant_headers == other.tolerant_headers
+            && *collapse_header_whitespace == other.collapse_header_whitespace
+            && *semicolon_address_separator == other.semicolon_address_separator
+            && *case_insensitive_boundaries == other.case_insensitive_boundaries
+            && *generate_text_from_html == other.generate_text_from_html
+            && *max_parts == other.max_parts
+            && *max_decompressed_size == other.max_decompressed_size
+    }
+}
+
+impl Eq for MessageParser {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{HeaderName, HeaderValue, MessageParser, MimeHeaders};
+
+    #[test]
+    fn parser_equality() {
+        assert_eq!(MessageParser::default(), MessageParser::default());
+        assert_eq!(
+            MessageParser::new().with_max_parts(10),
+            MessageParser::new().with_max_parts(10)
+        );
+        assert_ne!(
+            MessageParser::default(),
+            MessageParser::new().with_minimal_headers()
+        );
+        assert_ne!(
+            MessageParser::new().with_max_parts(10),
+            MessageParser::new().with_max_parts(20)
+        );
+        assert_ne!(
+            MessageParser::new().default_header_text(),
+            MessageParser::new().default_header_raw()
+        );
+    }
+
+    #[test]
+    fn header_allowlist() {
+        let input = concat!(
+            "From: John Doe <jdoe@example.com>\n",
+            "To: Jane <jane@example.com>, bob@example.com\n",
+            "Subject: =?utf-8?q?caf=C3=A9?=\n",
+            "Date: Tue, 1 Jul 2003 10:52:37 +0200\n",
+            "Message-ID: <1234@example.com>\n",
+            "References: <1233@example.com>\n",
+            "Received: from mx.example.com by example.org; Tue, 1 Jul 2003 10:52:37 +0200\n",
+            "MIME-Version: 1.0\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "Hello\n",
+            "--b\n",
+            "Content-Type: application/octet-stream\n",
+            "Content-Disposition: attachment; filename=\"file.bin\"\n",
+            "\n",
+            "data\n",
+            "--b--\n"
+        );
+        let structured = |parser: MessageParser| {
+            parser
+                .parse(input)
+                .unwrap()
+                .headers()
+                .iter()
+                .filter(|h| !matches!(h.value, HeaderValue::Text(_) | HeaderValue::Empty))
+                .count()
+        };
+
+        assert_eq!(structured(MessageParser::default()), 5);
+        assert_eq!(
+            structured(
+                MessageParser::default()
+                    .with_header_allowlist(&[HeaderName::From, HeaderName::Subject])
+            ),
+            2
+        );
+
+        let message = MessageParser::default()
+            .with_header_allowlist(&[HeaderName::From, HeaderName::Subject])
+            .parse(input)
+            .unwrap();
+        assert_eq!(
+            message.from().unwrap().first().unwrap().address(),
+            Some("jdoe@example.com")
+        );
+        assert_eq!(message.subject(), Some("café"));
+        assert_eq!(message.to(), None);
+        assert_eq!(message.date(), None);
+        assert_eq!(
+            message.header("Date").and_then(|h| h.as_text()),
+            Some("Tue, 1 Jul 2003 10:52:37 +0200")
+        );
+        assert_eq!(message.parts.len(), 3);
+        assert_eq!(
+            message.attachment(0).unwrap().attachment_name(),
+            Some("file.bin")
+        );
+        assert_eq!(message.body_text(0).unwrap(), "Hello");
+    }
+}
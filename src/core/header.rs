@@ -12,11 +12,12 @@
 use core::fmt;
 use std::hash::Hash;
 use std::net::IpAddr;
-use std::{borrow::Cow, fmt::Display};
+use std::{borrow::Cow, fmt::Display, ops::Range};
 
 use crate::{
-    Address, ContentType, DateTime, GetHeader, Greeting, Header, HeaderName, HeaderValue, Host,
-    Message, MessagePart, MessagePartId, MimeHeaders, PartType, Protocol, Received, TlsVersion,
+    decoders::charsets::map::charset_name, Address, ContentType, DateTime, GetHeader, Greeting,
+    Header, HeaderName, HeaderValue, Host, Message, MessagePart, MessagePartId, MessageSecurity,
+    MimeHeaders, PartType, PartialInfo, Protocol, Received, TlsVersion,
 };
 
 impl<'x> Header<'x> {
@@ -45,6 +46,52 @@ impl<'x> Header<'x> {
         self.offset_field
     }
 
+    /// Returns the header field in the DKIM "relaxed" canonical form
+    /// (RFC 6376, section 3.4.2), read from the raw message the header was
+    /// parsed from: the name is lowercased, the value unfolded, sequences of
+    /// whitespace are reduced to a single space, whitespace around the colon
+    /// and at the end of the value is removed and the field is terminated
+    /// with CRLF. CRLF and bare LF line endings produce the same result.
+    pub fn canonical_relaxed(&self, message: &Message<'_>) -> String {
+        let raw_message = message.raw_bytes();
+        let name = raw_message
+            .get(self.offset_field..self.offset_start.saturating_sub(1))
+            .unwrap_or_default();
+        let value = raw_message
+            .get(self.offset_start..self.offset_end)
+            .unwrap_or_default();
+
+        let mut result = Vec::with_capacity(name.len() + value.len() + 3);
+        result.extend(
+            name.iter()
+                .filter(|ch| !ch.is_ascii_whitespace())
+                .map(u8::to_ascii_lowercase),
+        );
+        result.push(b':');
+
+        let mut is_value_start = true;
+        let mut has_space = false;
+        for (pos, &ch) in value.iter().enumerate() {
+            match ch {
+                b' ' | b'\t' => has_space = true,
+                b'\n' => (),
+                b'\r' if value.get(pos + 1) == Some(&b'\n') => (),
+                _ => {
+                    if has_space && !is_value_start {
+                        result.push(b' ');
+                    }
+                    result.push(ch);
+                    has_space = false;
+                    is_value_start = false;
+                }
+            }
+        }
+        result.extend_from_slice(b"\r\n");
+
+        String::from_utf8(result)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+    }
+
     /// Returns an owned version of the header
     pub fn into_owned(self) -> Header<'static> {
         Header {
@@ -156,6 +203,47 @@ impl<'x> HeaderValue<'x> {
         }
     }
 
+    /// Interprets a text value as a floating point number, such as the
+    /// score in `X-Spam-Score: 4.2`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            HeaderValue::Text(ref s) => s.trim().parse().ok().filter(|n: &f64| n.is_finite()),
+            _ => None,
+        }
+    }
+
+    /// Interprets a text value as an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            HeaderValue::Text(ref s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Interprets a text value as a boolean, accepting `yes`, `true` and `1`
+    /// or `no`, `false` and `0` in any case.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            HeaderValue::Text(ref s) => {
+                let s = s.trim();
+                if ["yes", "true", "1"]
+                    .iter()
+                    .any(|v| s.eq_ignore_ascii_case(v))
+                {
+                    Some(true)
+                } else if ["no", "false", "0"]
+                    .iter()
+                    .any(|v| s.eq_ignore_ascii_case(v))
+                {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
     pub fn as_text_list(&self) -> Option<Vec<&str>> {
         match *self {
             HeaderValue::Text(ref s) => Some(vec![s.as_ref()]),
@@ -321,6 +409,17 @@ impl HeaderName<'_> {
             HeaderName::ListPost => HeaderName::ListPost,
             HeaderName::ListSubscribe => HeaderName::ListSubscribe,
             HeaderName::ListUnsubscribe => HeaderName::ListUnsubscribe,
+            HeaderName::ContentDuration => HeaderName::ContentDuration,
+            HeaderName::DispositionNotificationTo => HeaderName::DispositionNotificationTo,
+            HeaderName::ReturnReceiptTo => HeaderName::ReturnReceiptTo,
+            HeaderName::Organization => HeaderName::Organization,
+            HeaderName::UserAgent => HeaderName::UserAgent,
+            HeaderName::XMailer => HeaderName::XMailer,
+            HeaderName::ContentBase => HeaderName::ContentBase,
+            HeaderName::DeliveredTo => HeaderName::DeliveredTo,
+            HeaderName::XOriginalTo => HeaderName::XOriginalTo,
+            HeaderName::Face => HeaderName::Face,
+            HeaderName::XFace => HeaderName::XFace,
         }
     }
 
@@ -364,6 +463,17 @@ impl HeaderName<'_> {
             HeaderName::ListPost => HeaderName::ListPost,
             HeaderName::ListSubscribe => HeaderName::ListSubscribe,
             HeaderName::ListUnsubscribe => HeaderName::ListUnsubscribe,
+            HeaderName::ContentDuration => HeaderName::ContentDuration,
+            HeaderName::DispositionNotificationTo => HeaderName::DispositionNotificationTo,
+            HeaderName::ReturnReceiptTo => HeaderName::ReturnReceiptTo,
+            HeaderName::Organization => HeaderName::Organization,
+            HeaderName::UserAgent => HeaderName::UserAgent,
+            HeaderName::XMailer => HeaderName::XMailer,
+            HeaderName::ContentBase => HeaderName::ContentBase,
+            HeaderName::DeliveredTo => HeaderName::DeliveredTo,
+            HeaderName::XOriginalTo => HeaderName::XOriginalTo,
+            HeaderName::Face => HeaderName::Face,
+            HeaderName::XFace => HeaderName::XFace,
         }
     }
 
@@ -420,6 +530,17 @@ impl HeaderName<'_> {
             HeaderName::ListPost => "List-Post",
             HeaderName::ListSubscribe => "List-Subscribe",
             HeaderName::ListUnsubscribe => "List-Unsubscribe",
+            HeaderName::ContentDuration => "Content-Duration",
+            HeaderName::DispositionNotificationTo => "Disposition-Notification-To",
+            HeaderName::ReturnReceiptTo => "Return-Receipt-To",
+            HeaderName::Organization => "Organization",
+            HeaderName::UserAgent => "User-Agent",
+            HeaderName::XMailer => "X-Mailer",
+            HeaderName::ContentBase => "Content-Base",
+            HeaderName::DeliveredTo => "Delivered-To",
+            HeaderName::XOriginalTo => "X-Original-To",
+            HeaderName::Face => "Face",
+            HeaderName::XFace => "X-Face",
             HeaderName::Other(_) => "",
         }
     }
@@ -463,6 +584,17 @@ impl HeaderName<'_> {
             HeaderName::ListPost => "List-Post".len(),
             HeaderName::ListSubscribe => "List-Subscribe".len(),
             HeaderName::ListUnsubscribe => "List-Unsubscribe".len(),
+            HeaderName::ContentDuration => "Content-Duration".len(),
+            HeaderName::DispositionNotificationTo => "Disposition-Notification-To".len(),
+            HeaderName::ReturnReceiptTo => "Return-Receipt-To".len(),
+            HeaderName::Organization => "Organization".len(),
+            HeaderName::UserAgent => "User-Agent".len(),
+            HeaderName::XMailer => "X-Mailer".len(),
+            HeaderName::ContentBase => "Content-Base".len(),
+            HeaderName::DeliveredTo => "Delivered-To".len(),
+            HeaderName::XOriginalTo => "X-Original-To".len(),
+            HeaderName::Face => "Face".len(),
+            HeaderName::XFace => "X-Face".len(),
             HeaderName::Other(other) => other.len(),
         }
     }
@@ -478,6 +610,8 @@ impl HeaderName<'_> {
                 | HeaderName::ContentTransferEncoding
                 | HeaderName::ContentType
                 | HeaderName::ContentDisposition
+                | HeaderName::ContentDuration
+                | HeaderName::ContentBase
         )
     }
 
@@ -530,6 +664,17 @@ impl HeaderName<'_> {
             HeaderName::ListSubscribe => 35,
             HeaderName::ListUnsubscribe => 36,
             HeaderName::Other(_) => 37,
+            HeaderName::ContentDuration => 38,
+            HeaderName::DispositionNotificationTo => 39,
+            HeaderName::ReturnReceiptTo => 40,
+            HeaderName::Organization => 41,
+            HeaderName::UserAgent => 42,
+            HeaderName::XMailer => 43,
+            HeaderName::ContentBase => 44,
+            HeaderName::DeliveredTo => 45,
+            HeaderName::XOriginalTo => 46,
+            HeaderName::Face => 47,
+            HeaderName::XFace => 48,
         }
     }
 }
@@ -586,7 +731,23 @@ impl<'x> MimeHeaders<'x> for Message<'x> {
 }
 
 impl<'x> MessagePart<'x> {
-    /// Returns the body part's contents as a `u8` slice
+    /// Returns the RFC 2424 Content-Duration field in seconds
+    pub fn content_duration(&self) -> Option<u32> {
+        let value = self
+            .headers
+            .header_value(&HeaderName::ContentDuration)?
+            .as_text()?
+            .trim_start();
+        let digits = value
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(value.len());
+        value[..digits].parse().ok()
+    }
+
+    /// Returns the body part's contents as a `u8` slice. For nested messages
+    /// this is the raw message, see `decoded_bytes` to only obtain the
+    /// payload of leaf parts. For parts flagged with `is_undecoded` this is
+    /// the raw, still transfer encoded body.
     pub fn contents(&self) -> &[u8] {
         match &self.body {
             PartType::Text(text) | PartType::Html(text) => text.as_bytes(),
@@ -596,8 +757,27 @@ impl<'x> MessagePart<'x> {
         }
     }
 
-    /// Returns the body part's contents as a `str`
+    /// Returns the decoded payload of a leaf part, that is, the text after
+    /// charset conversion or the binary contents after removing the transfer
+    /// encoding. Unlike `contents`, `None` is returned for multipart and
+    /// nested message parts, and for the undecoded parts of a `LazyMessage`.
+    pub fn decoded_bytes(&self) -> Option<Cow<'_, [u8]>> {
+        if self.is_undecoded {
+            return None;
+        }
+        match &self.body {
+            PartType::Text(text) | PartType::Html(text) => Some(Cow::Borrowed(text.as_bytes())),
+            PartType::Binary(bin) | PartType::InlineBinary(bin) => Some(Cow::Borrowed(bin)),
+            PartType::Message(_) | PartType::Multipart(_) => None,
+        }
+    }
+
+    /// Returns the body part's contents as a `str`, or `None` for the
+    /// undecoded parts of a `LazyMessage`.
     pub fn text_contents(&self) -> Option<&str> {
+        if self.is_undecoded {
+            return None;
+        }
         match &self.body {
             PartType::Text(text) | PartType::Html(text) => text.as_ref().into(),
             PartType::Binary(bin) | PartType::InlineBinary(bin) => {
@@ -626,7 +806,9 @@ impl<'x> MessagePart<'x> {
         }
     }
 
-    /// Returns the body part's length
+    /// Returns the body part's decoded length, or the raw length of the
+    /// nested message for `message/rfc822` parts. Use [`MessagePart::raw_len`]
+    /// for the size of the part as it appeared in the raw message.
     pub fn len(&self) -> usize {
         match &self.body {
             PartType::Text(text) | PartType::Html(text) => text.len(),
@@ -636,6 +818,61 @@ impl<'x> MessagePart<'x> {
         }
     }
 
+    /// Returns the contents of a plain text part up to its signature
+    /// delimiter, a line containing exactly `-- `. Delimiters inside quoted
+    /// replies (lines starting with `>`) are not matched, and the whole body
+    /// is returned when no delimiter is present.
+    pub fn body_without_signature(&self) -> Option<Cow<'_, str>> {
+        let PartType::Text(text) = &self.body else {
+            return None;
+        };
+
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            if matches!(line, "-- " | "-- \n" | "-- \r\n") {
+                return Some(Cow::Borrowed(&text[..offset]));
+            }
+            offset += line.len();
+        }
+
+        Some(Cow::Borrowed(text.as_ref()))
+    }
+
+    /// Returns the lines of a plain text part together with their quote
+    /// depth, the number of leading `>` markers, which may be separated by a
+    /// single space as in `> > text`. The markers and the space following the
+    /// last one are stripped from the returned content. Blank lines are kept,
+    /// and an empty list is returned for parts that are not plain text.
+    pub fn quoted_lines(&self) -> Vec<(u8, &str)> {
+        let PartType::Text(text) = &self.body else {
+            return Vec::new();
+        };
+
+        text.lines()
+            .map(|line| {
+                let mut depth: u8 = 0;
+                let mut rest = line;
+                loop {
+                    if let Some(next) = rest.strip_prefix('>') {
+                        depth = depth.saturating_add(1);
+                        rest = next;
+                    } else if let Some(next) = rest
+                        .strip_prefix(' ')
+                        .filter(|next| depth > 0 && next.starts_with('>'))
+                    {
+                        rest = next;
+                    } else {
+                        break;
+                    }
+                }
+                if depth > 0 {
+                    rest = rest.strip_prefix(' ').unwrap_or(rest);
+                }
+                (depth, rest)
+            })
+            .collect()
+    }
+
     /// Returns `true` when the body part MIME type is text/*
     pub fn is_text(&self) -> bool {
         matches!(self.body, PartType::Text(_) | PartType::Html(_))
@@ -646,6 +883,21 @@ impl<'x> MessagePart<'x> {
         matches!(self.body, PartType::Html(_))
     }
 
+    /// Returns the charset declared by a text part, as it appears in the
+    /// Content-Type header
+    pub fn charset(&self) -> Option<&str> {
+        if self.is_text() {
+            self.content_type()?.attribute("charset")
+        } else {
+            None
+        }
+    }
+
+    /// Returns the canonical IANA name of the charset declared by a text part
+    pub fn normalized_charset(&self) -> Option<&'static str> {
+        charset_name(self.charset()?.trim().as_bytes())
+    }
+
     /// Returns `true` when the part is binary
     pub fn is_binary(&self) -> bool {
         matches!(self.body, PartType::Binary(_) | PartType::InlineBinary(_))
@@ -661,6 +913,70 @@ impl<'x> MessagePart<'x> {
         matches!(self.body, PartType::Message(_))
     }
 
+    /// Returns `true` when the part is the `application/applefile` header of
+    /// a `multipart/appledouble` attachment, which only holds Mac metadata.
+    /// The file contents are stored in the following part.
+    pub fn is_appledouble_metadata(&self) -> bool {
+        self.content_type()
+            .is_some_and(|ct| ct.ctype() == "application" && ct.subtype() == Some("applefile"))
+    }
+
+    /// Returns `true` when the part is a Microsoft TNEF attachment, that is
+    /// when its Content-Type is `application/ms-tnef` or
+    /// `application/vnd.ms-tnef`, or when it is named `winmail.dat`. The
+    /// contents are not decoded, they have to be handed to a TNEF decoder.
+    pub fn is_tnef(&self) -> bool {
+        (!self.is_multipart() && !self.is_message())
+            && (self.is_content_type("application", "ms-tnef")
+                || self.is_content_type("application", "vnd.ms-tnef")
+                || self
+                    .attachment_name()
+                    .is_some_and(|name| name.trim().eq_ignore_ascii_case("winmail.dat")))
+    }
+
+    /// Returns `true` when the attachment is likely dangerous, that is when
+    /// the extension of its `attachment_name` is in `DANGEROUS_EXTENSIONS`
+    /// (which also catches double extensions such as `invoice.pdf.exe`), or
+    /// when the extension is a well-known one whose media type differs from
+    /// the declared Content-Type (such as an `image/jpeg` part named
+    /// `report.pdf`). Parts declared as `application/octet-stream` are only
+    /// checked against the list. This is a heuristic, see
+    /// `is_suspicious_attachment_with` to use a different list.
+    pub fn is_suspicious_attachment(&self) -> bool {
+        self.is_suspicious_attachment_with(DANGEROUS_EXTENSIONS)
+    }
+
+    /// Same as `is_suspicious_attachment`, using `dangerous_extensions`
+    /// (lowercase, without the leading dot) instead of the built-in list.
+    pub fn is_suspicious_attachment_with(&self, dangerous_extensions: &[&str]) -> bool {
+        let Some(extension) = self
+            .attachment_name()
+            .and_then(|name| name.trim().rsplit_once('.'))
+            .map(|(_, extension)| extension.trim().to_ascii_lowercase())
+        else {
+            return false;
+        };
+
+        if dangerous_extensions.contains(&extension.as_str()) {
+            return true;
+        }
+
+        match (
+            self.content_type(),
+            EXTENSION_TYPES
+                .iter()
+                .find(|(extensions, _)| extensions.contains(&extension.as_str())),
+        ) {
+            (Some(content_type), Some((_, expected_type))) => {
+                !content_type.ctype().eq_ignore_ascii_case(expected_type)
+                    && !content_type
+                        .subtype()
+                        .is_some_and(|subtype| subtype.eq_ignore_ascii_case("octet-stream"))
+            }
+            _ => false,
+        }
+    }
+
     /// Returns `true` when the body part is empty
     pub fn is_empty(&self) -> bool {
         self.len() == 0
@@ -671,11 +987,67 @@ impl<'x> MessagePart<'x> {
         &self.headers
     }
 
-    /// Returns the body raw length
+    /// Returns the raw length of the part, including its headers
     pub fn raw_len(&self) -> usize {
         self.offset_end.saturating_sub(self.offset_header)
     }
 
+    /// Returns the URL that relative references in this part should be
+    /// resolved against, from the Content-Base header (RFC 2110) or, if
+    /// missing, the Content-Location header (RFC 2557). Surrounding quotes
+    /// are removed.
+    pub fn base_url(&self) -> Option<&str> {
+        self.headers
+            .header_value(&HeaderName::ContentBase)
+            .and_then(|header| header.as_text())
+            .or_else(|| self.content_location())
+            .map(|url| url.trim().trim_matches('"'))
+    }
+
+    /// Returns the raw value of the Content-Type header field used to build
+    /// `content_type`, read from `message`, the message this part belongs
+    /// to. The value is returned as found after the colon, with its
+    /// parameter order, spacing, folding and line ending, so that
+    /// `Content-Type:` followed by it reproduces the original field.
+    pub fn content_type_raw<'a>(&self, message: &'a Message<'_>) -> Option<&'a str> {
+        let header = self.headers.header(HeaderName::ContentType)?;
+        std::str::from_utf8(
+            message
+                .raw_message
+                .get(header.offset_start..header.offset_end)?,
+        )
+        .ok()
+    }
+
+    /// Returns the non-standard Content-Encoding field, used by some
+    /// HTTP-based senders to declare a compressed body. With the
+    /// `compression` feature enabled, `gzip` and `deflate` bodies are
+    /// decompressed while parsing.
+    pub fn content_encoding(&self) -> Option<&str> {
+        self.headers
+            .header_value(&HeaderName::Other("Content-Encoding".into()))
+            .and_then(|header| header.as_text())
+            .map(|encoding| encoding.trim())
+    }
+
+    /// Returns the fragment parameters of a `message/partial` part. These
+    /// parts are not parsed as nested messages, the fragments have to be
+    /// reassembled first.
+    pub fn message_partial(&self) -> Option<PartialInfo<'_>> {
+        let content_type = self.content_type()?;
+        if content_type.ctype() != "message" || content_type.subtype() != Some("partial") {
+            return None;
+        }
+
+        Some(PartialInfo {
+            id: content_type.attribute("id")?.into(),
+            number: content_type.attribute("number")?.trim().parse().ok()?,
+            total: content_type
+                .attribute("total")
+                .and_then(|total| total.trim().parse().ok()),
+        })
+    }
+
     /// Get the raw header offset of this part
     pub fn raw_header_offset(&self) -> usize {
         self.offset_header
@@ -691,11 +1063,76 @@ impl<'x> MessagePart<'x> {
         self.offset_end
     }
 
+    /// Returns the byte range within `message.raw_message` covered by the
+    /// signed content of a `multipart/signed` part: the first body part,
+    /// including its headers and excluding the line break that precedes the
+    /// next boundary delimiter (RFC 1847, section 2.1).
+    pub fn signed_content_range(&self, message: &Message<'_>) -> Option<Range<usize>> {
+        if !self.is_content_type("multipart", "signed") {
+            return None;
+        }
+        let boundary = self.content_type()?.attribute("boundary")?.as_bytes();
+        let start = message
+            .parts
+            .get(*self.sub_parts()?.first()?)?
+            .offset_header;
+        let raw = message.raw_message.get(start..self.offset_end)?;
+
+        let mut end = start
+            + raw.windows(boundary.len() + 3).position(|delimiter| {
+                delimiter.starts_with(b"\n--") && &delimiter[3..] == boundary
+            })?;
+        if end > start && message.raw_message[end - 1] == b'\r' {
+            end -= 1;
+        }
+
+        Some(start..end)
+    }
+
+    /// Returns the root part of a `multipart/related` part (RFC 2387): the
+    /// subpart whose Content-ID matches the `start` parameter or, when the
+    /// parameter is missing, the first subpart. Returns `None` for other
+    /// content types or when `start` does not match any subpart.
+    pub fn related_root<'a>(&self, message: &'a Message<'x>) -> Option<&'a MessagePart<'x>> {
+        if !self.is_content_type("multipart", "related") {
+            return None;
+        }
+        let mut sub_parts = self
+            .sub_parts()?
+            .iter()
+            .filter_map(|&part_id| message.parts.get(part_id));
+
+        match self.content_type()?.attribute("start") {
+            Some(start) => {
+                let start = start.trim();
+                let start = start
+                    .strip_prefix('<')
+                    .and_then(|start| start.strip_suffix('>'))
+                    .unwrap_or(start);
+                sub_parts.find(|part| part.content_id() == Some(start))
+            }
+            None => sub_parts.next(),
+        }
+    }
+
+    /// Returns the `type` parameter of a `multipart/related` part, the
+    /// media type of its root part.
+    pub fn related_type(&self) -> Option<&str> {
+        if self.is_content_type("multipart", "related") {
+            self.content_type()?.attribute("type")
+        } else {
+            None
+        }
+    }
+
     /// Returns an owned version of the this part
     pub fn into_owned(self) -> MessagePart<'static> {
         MessagePart {
             headers: self.headers.into_iter().map(|h| h.into_owned()).collect(),
             is_encoding_problem: self.is_encoding_problem,
+            is_synthetic: self.is_synthetic,
+            is_incomplete: self.is_incomplete,
+            is_undecoded: self.is_undecoded,
             body: match self.body {
                 PartType::Text(v) => PartType::Text(v.into_owned().into()),
                 PartType::Html(v) => PartType::Html(v.into_owned().into()),
@@ -774,24 +1211,24 @@ impl<'x> ContentType<'x> {
         self.c_subtype.as_ref()?.as_ref().into()
     }
 
-    /// Returns an attribute by name
+    /// Returns an attribute by name, attribute names are case-insensitive
     pub fn attribute(&self, name: &str) -> Option<&str> {
         self.attributes
             .as_ref()?
             .iter()
-            .find(|(key, _)| key == name)?
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))?
             .1
             .as_ref()
             .into()
     }
 
-    /// Removes an attribute by name
+    /// Removes an attribute by name, attribute names are case-insensitive
     pub fn remove_attribute(&mut self, name: &str) -> Option<Cow<'x, str>> {
         let attributes = self.attributes.as_mut()?;
 
         attributes
             .iter()
-            .position(|(key, _)| key == name)
+            .position(|(key, _)| key.eq_ignore_ascii_case(name))
             .map(|pos| attributes.swap_remove(pos).1)
     }
 
@@ -800,11 +1237,12 @@ impl<'x> ContentType<'x> {
         self.attributes.as_deref()
     }
 
-    /// Returns `true` when the provided attribute name is present
+    /// Returns `true` when the provided attribute name is present,
+    /// attribute names are case-insensitive
     pub fn has_attribute(&self, name: &str) -> bool {
         self.attributes
             .as_ref()
-            .map_or(false, |attr| attr.iter().any(|(key, _)| key == name))
+            .is_some_and(|attr| attr.iter().any(|(key, _)| key.eq_ignore_ascii_case(name)))
     }
 
     /// Returns ```true``` if the Content-Disposition type is "attachment"
@@ -827,6 +1265,11 @@ impl<'x> Received<'x> {
             from_iprev: self.from_iprev.map(|s| s.into_owned().into()),
             by: self.by.map(|s| s.into_owned()),
             for_: self.for_.map(|s| s.into_owned().into()),
+            for_list: self
+                .for_list
+                .into_iter()
+                .map(|s| s.into_owned().into())
+                .collect(),
             with: self.with,
             tls_version: self.tls_version,
             tls_cipher: self.tls_cipher.map(|s| s.into_owned().into()),
@@ -864,6 +1307,11 @@ impl<'x> Received<'x> {
         self.for_.as_ref().map(|s| s.as_ref())
     }
 
+    /// Returns all the email addresses listed in the `for` clause
+    pub fn for_list(&self) -> &[Cow<'x, str>] {
+        &self.for_list
+    }
+
     /// Returns the protocol that was used to receive the message
     pub fn with(&self) -> Option<Protocol> {
         self.with
@@ -908,8 +1356,107 @@ impl<'x> Received<'x> {
     pub fn date(&self) -> Option<DateTime> {
         self.date
     }
+
+    /// Formats this trace record as a `Received` header value (without the
+    /// field name), folding each clause onto its own line. Clauses whose
+    /// fields are `None` are omitted.
+    pub fn to_header_string(&self) -> String {
+        let mut clauses = Vec::new();
+
+        let name = self
+            .from
+            .as_ref()
+            .or(self.helo.as_ref())
+            .map(|host| host.to_string());
+        let ip_literal = self.from_ip.map(|ip| match ip {
+            IpAddr::V4(ip) => format!("[{ip}]"),
+            IpAddr::V6(ip) => format!("[IPv6:{ip}]"),
+        });
+        if let Some(host) = name.as_ref().or(ip_literal.as_ref()) {
+            let mut clause = format!("from {host}");
+            if let (Some(helo), Some(_)) = (&self.helo, &self.from) {
+                let cmd = self.helo_cmd.unwrap_or(Greeting::Ehlo);
+                clause.push_str(&format!(" ({cmd} {helo})"));
+            }
+            let mut info = Vec::new();
+            if let Some(iprev) = &self.from_iprev {
+                info.push(iprev.to_string());
+            }
+            if let Some(ip_literal) = ip_literal.as_ref().filter(|_| name.is_some()) {
+                info.push(ip_literal.clone());
+            }
+            if let Some(ident) = &self.ident {
+                info.push(format!("ident={ident}"));
+            }
+            if !info.is_empty() {
+                clause.push_str(&format!(" ({})", info.join(" ")));
+            }
+            clauses.push(clause);
+        }
+
+        let mut clause = String::new();
+        if let Some(by) = &self.by {
+            clause.push_str(&format!("by {by}"));
+        }
+        if let Some(via) = &self.via {
+            clause.push_str(&format!(" via {via}"));
+        }
+        if let Some(with) = self.with {
+            clause.push_str(&format!(" with {with}"));
+        }
+        match (self.tls_version, &self.tls_cipher) {
+            (Some(version), Some(cipher)) => {
+                clause.push_str(&format!(" ({version} cipher {cipher})"))
+            }
+            (Some(version), None) => clause.push_str(&format!(" ({version})")),
+            (None, Some(cipher)) => clause.push_str(&format!(" (cipher {cipher})")),
+            (None, None) => (),
+        }
+        if !clause.is_empty() {
+            clauses.push(clause.trim_start().to_string());
+        }
+
+        if let Some(id) = &self.id {
+            clauses.push(format!("id {id}"));
+        }
+        if !self.for_list.is_empty() {
+            clauses.push(format!("for <{}>", self.for_list.join(">, <")));
+        } else if let Some(for_) = &self.for_ {
+            clauses.push(format!("for <{for_}>"));
+        }
+
+        let mut header = clauses.join("\r\n\t");
+        if let Some(date) = &self.date {
+            header.push_str(";\r\n\t");
+            header.push_str(&date.to_rfc822());
+        }
+        header
+    }
 }
 
+/// File extensions of executables, scripts and disk images flagged by
+/// `MessagePart::is_suspicious_attachment`.
+pub static DANGEROUS_EXTENSIONS: &[&str] = &[
+    "ade", "adp", "app", "appx", "bat", "cab", "chm", "cmd", "com", "cpl", "dll", "dmg", "exe",
+    "hta", "img", "inf", "ins", "iso", "isp", "jar", "js", "jse", "lnk", "msc", "msi", "msix",
+    "msp", "mst", "pif", "ps1", "reg", "scr", "sct", "shb", "sys", "vb", "vbe", "vbs", "vhd",
+    "vhdx", "vxd", "wsc", "wsf", "wsh",
+];
+
+// Media type expected for common file extensions
+static EXTENSION_TYPES: &[(&[&str], &str)] = &[
+    (
+        &["jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff"],
+        "image",
+    ),
+    (&["mp3", "wav", "ogg", "m4a", "flac"], "audio"),
+    (&["mp4", "mov", "avi", "mkv", "webm"], "video"),
+    (
+        &["pdf", "zip", "doc", "docx", "xls", "xlsx", "ppt", "pptx"],
+        "application",
+    ),
+];
+
 /// A hostname or IP address.
 impl Host<'_> {
     pub fn into_owned(self) -> Host<'static> {
@@ -920,6 +1467,26 @@ impl Host<'_> {
     }
 }
 
+impl MessageSecurity<'_> {
+    /// Returns true if the message is signed
+    pub fn is_signed(&self) -> bool {
+        self.signed.is_some()
+    }
+
+    /// Returns true if the message is encrypted
+    pub fn is_encrypted(&self) -> bool {
+        self.encrypted.is_some()
+    }
+
+    pub fn into_owned(self) -> MessageSecurity<'static> {
+        MessageSecurity {
+            signed: self.signed,
+            encrypted: self.encrypted,
+            micalg: self.micalg.map(|s| s.into_owned().into()),
+        }
+    }
+}
+
 impl<'x> GetHeader<'x> for Vec<Header<'x>> {
     fn header_value(&self, name: &HeaderName<'_>) -> Option<&HeaderValue<'x>> {
         self.iter()
@@ -1007,6 +1574,17 @@ impl From<u8> for HeaderName<'_> {
             34 => HeaderName::ListPost,
             35 => HeaderName::ListSubscribe,
             36 => HeaderName::ListUnsubscribe,
+            38 => HeaderName::ContentDuration,
+            39 => HeaderName::DispositionNotificationTo,
+            40 => HeaderName::ReturnReceiptTo,
+            41 => HeaderName::Organization,
+            42 => HeaderName::UserAgent,
+            43 => HeaderName::XMailer,
+            44 => HeaderName::ContentBase,
+            45 => HeaderName::DeliveredTo,
+            46 => HeaderName::XOriginalTo,
+            47 => HeaderName::Face,
+            48 => HeaderName::XFace,
             _ => HeaderName::Other("".into()),
         }
     }
@@ -1106,3 +1684,644 @@ impl Display for HeaderName<'_> {
         write!(f, "{}", self.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{HeaderName, MessageParser, MimeHeaders, PartType, PartialInfo};
+
+    #[test]
+    fn header_name_ids() {
+        // Ids are stored by applications, new variants are numbered after
+        // the id of HeaderName::Other
+        assert_eq!(HeaderName::Other("X-Test".into()).id(), 37);
+        assert_eq!(HeaderName::from(37), HeaderName::Other("".into()));
+        assert_eq!(HeaderName::ListUnsubscribe.id(), 36);
+        for id in 0..=48 {
+            assert_eq!(HeaderName::from(id).id(), id);
+        }
+        assert_eq!(HeaderName::from(49).id(), 37);
+    }
+
+    #[test]
+    fn signed_content_range() {
+        let signed_content = concat!(
+            "Content-Type: multipart/alternative; boundary=\"alt\"\r\n",
+            "\r\n",
+            "--alt\r\n",
+            "Content-Type: text/plain; charset=\"us-ascii\"\r\n",
+            "\r\n",
+            "This is a signed message.\r\n",
+            "--alt\r\n",
+            "Content-Type: text/html; charset=\"us-ascii\"\r\n",
+            "\r\n",
+            "<p>This is a signed message.</p>\r\n",
+            "--alt--"
+        );
+        let signature = concat!(
+            "\r\n",
+            "--sig\r\n",
+            "Content-Type: application/pkcs7-signature; name=\"smime.p7s\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "Content-Disposition: attachment; filename=\"smime.p7s\"\r\n",
+            "\r\n",
+            "MIAGCSqGSIb3DQEHAqCAMIACAQExDzANBglghkgBZQMEAgEFADCABgkqhkiG9w0BBwEAAKCA\r\n",
+            "--sig--\r\n"
+        );
+
+        for separator in ["\r\n", ""] {
+            let input = format!(
+                concat!(
+                    "From: sender@example.com\r\n",
+                    "Subject: Signed\r\n",
+                    "MIME-Version: 1.0\r\n",
+                    "Content-Type: multipart/signed; protocol=\"application/pkcs7-signature\";\r\n",
+                    "\tmicalg=sha-256; boundary=\"sig\"\r\n",
+                    "\r\n",
+                    "This is an S/MIME signed message\r\n",
+                    "\r\n",
+                    "--sig\r\n",
+                    "{}{}{}"
+                ),
+                signed_content, separator, signature
+            );
+            let message = MessageParser::default().parse(&input).unwrap();
+            let range = message.root_part().signed_content_range(&message).unwrap();
+
+            assert_eq!(
+                std::str::from_utf8(&message.raw_message[range]).unwrap(),
+                format!("{signed_content}{separator}")
+            );
+            assert_eq!(message.parts[1].signed_content_range(&message), None);
+        }
+    }
+
+    #[test]
+    fn body_without_signature() {
+        for (body, expected) in [
+            ("Hello\n\n-- \nJohn Doe\n", "Hello\n\n"),
+            ("Hello\r\n-- \r\nJohn Doe\r\n", "Hello\r\n"),
+            ("-- \nJohn Doe\n", ""),
+            (
+                "Hello\n--\nnot a signature\n",
+                "Hello\n--\nnot a signature\n",
+            ),
+            ("Hello\n-- trailing text\n", "Hello\n-- trailing text\n"),
+            (
+                "On Monday Jane wrote:\n> Hi\n> -- \n> Jane\n\nHello\n-- \nJohn\n",
+                "On Monday Jane wrote:\n> Hi\n> -- \n> Jane\n\nHello\n",
+            ),
+            ("Hello\n> -- \n> Jane\n", "Hello\n> -- \n> Jane\n"),
+        ] {
+            let input = format!("Subject: test\n\n{body}");
+            let message = MessageParser::default().parse(&input).unwrap();
+            assert_eq!(
+                message.parts[0].body_without_signature().unwrap(),
+                expected,
+                "failed for {body:?}"
+            );
+        }
+
+        let message = MessageParser::default()
+            .parse("Content-Type: text/html\n\n<p>Hello</p>\n-- \nJohn\n")
+            .unwrap();
+        assert_eq!(message.parts[0].body_without_signature(), None);
+    }
+
+    #[test]
+    fn content_duration() {
+        for (value, expected) in [
+            ("33", Some(33)),
+            (" 120 (two minutes)", Some(120)),
+            ("45s", Some(45)),
+            ("unknown", None),
+            ("", None),
+        ] {
+            let input = format!("Content-Type: audio/basic\nContent-Duration: {value}\n\ndata");
+            let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+            assert_eq!(
+                message.root_part().content_duration(),
+                expected,
+                "{value:?}"
+            );
+        }
+
+        let message = MessageParser::default()
+            .parse(b"Content-Type: audio/basic\n\ndata")
+            .unwrap();
+        assert_eq!(message.content_duration(), None);
+    }
+
+    #[test]
+    fn message_partial() {
+        let input = concat!(
+            "Content-Type: multipart/mixed; boundary=b\n",
+            "\n",
+            "--b\n",
+            "Content-Type: message/partial; id=\"ABC@host.com\"; number=2; total=3\n",
+            "\n",
+            "Subject: fragment\n",
+            "\n",
+            "Second fragment\n",
+            "--b\n",
+            "Content-Type: message/partial; id=\"ABC@host.com\"; number=1\n",
+            "\n",
+            "First fragment\n",
+            "--b\n",
+            "Content-Type: message/partial; id=\"ABC@host.com\"\n",
+            "\n",
+            "Missing number\n",
+            "--b\n",
+            "Content-Type: message/global\n",
+            "\n",
+            "Subject: =?utf-8?q?caf=C3=A9?= caf\u{e9}\n",
+            "\n",
+            "Global message\n",
+            "--b--\n"
+        );
+        let message = MessageParser::default().parse(input).unwrap();
+
+        assert_eq!(
+            message.parts[1].message_partial(),
+            Some(PartialInfo {
+                id: "ABC@host.com".into(),
+                number: 2,
+                total: Some(3),
+            })
+        );
+        assert!(matches!(message.parts[1].body, PartType::Binary(_)));
+        assert_eq!(
+            message.parts[2].message_partial(),
+            Some(PartialInfo {
+                id: "ABC@host.com".into(),
+                number: 1,
+                total: None,
+            })
+        );
+        assert_eq!(message.parts[3].message_partial(), None);
+
+        assert_eq!(message.parts[4].message_partial(), None);
+        let nested = message.parts[4].message().unwrap();
+        assert_eq!(nested.subject(), Some("café café"));
+    }
+
+    #[test]
+    fn header_value_coercion() {
+        let input = concat!(
+            "X-Spam-Score: 4.2\n",
+            "X-Spam-Flag: YES\n",
+            "X-Spam-Level: 3 \n",
+            "X-Spam-Negative: -1.5\n",
+            "X-Virus-Scanned: no\n",
+            "X-Spam-Status: Yes, score=4.2\n",
+            "X-Spam-Infinity: inf\n",
+            "Date: Tue, 1 Jul 2003 10:52:37 +0200\n",
+            "\n",
+            "body"
+        );
+        let message = MessageParser::default().parse(input).unwrap();
+        let header = |name: &'static str| message.header(name).unwrap();
+
+        assert_eq!(header("X-Spam-Score").as_f64(), Some(4.2));
+        assert_eq!(header("X-Spam-Score").as_i64(), None);
+        assert_eq!(header("X-Spam-Score").as_bool(), None);
+        assert_eq!(header("X-Spam-Flag").as_bool(), Some(true));
+        assert_eq!(header("X-Spam-Flag").as_f64(), None);
+        assert_eq!(header("X-Spam-Level").as_i64(), Some(3));
+        assert_eq!(header("X-Spam-Level").as_f64(), Some(3.0));
+        assert_eq!(header("X-Spam-Negative").as_f64(), Some(-1.5));
+        assert_eq!(header("X-Virus-Scanned").as_bool(), Some(false));
+        assert_eq!(header("X-Spam-Status").as_bool(), None);
+        assert_eq!(header("X-Spam-Infinity").as_f64(), None);
+        assert_eq!(header("Date").as_i64(), None);
+    }
+
+    #[test]
+    fn appledouble() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"outer\"\n",
+                "\n",
+                "--outer\n",
+                "Content-Type: text/plain\n",
+                "\n",
+                "Report attached\n",
+                "--outer\n",
+                "Content-Type: multipart/appledouble; boundary=\"inner\"\n",
+                "\n",
+                "--inner\n",
+                "Content-Type: application/applefile; name=\"report.pdf\"\n",
+                "Content-Transfer-Encoding: base64\n",
+                "\n",
+                "AAUWBwACAAA=\n",
+                "--inner\n",
+                "Content-Type: application/pdf; name=\"report.pdf\"\n",
+                "Content-Disposition: attachment; filename=\"report.pdf\"\n",
+                "\n",
+                "%PDF-1.4\n",
+                "--inner--\n",
+                "--outer--\n"
+            ))
+            .unwrap();
+
+        assert_eq!(message.parts.len(), 5);
+        assert!(message.parts[3].is_appledouble_metadata());
+        assert!(!message.parts[4].is_appledouble_metadata());
+        assert_eq!(message.attachment_count(), 1);
+        let attachment = message.attachment(0).unwrap();
+        assert_eq!(attachment.attachment_name(), Some("report.pdf"));
+        assert_eq!(attachment.contents(), b"%PDF-1.4");
+        assert_eq!(
+            message
+                .attachments()
+                .map(|part| part.content_type().unwrap().subtype().unwrap())
+                .collect::<Vec<_>>(),
+            ["pdf"]
+        );
+    }
+
+    #[test]
+    fn decoded_bytes() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"b\"\n",
+                "\n",
+                "--b\n",
+                "Content-Type: text/plain; charset=iso-8859-1\n",
+                "Content-Transfer-Encoding: quoted-printable\n",
+                "\n",
+                "Caf=E9\n",
+                "--b\n",
+                "Content-Type: text/html\n",
+                "\n",
+                "<p>Hi</p>\n",
+                "--b\n",
+                "Content-Type: application/octet-stream\n",
+                "Content-Transfer-Encoding: base64\n",
+                "\n",
+                "AAEC\n",
+                "--b\n",
+                "Content-Type: image/png\n",
+                "Content-Transfer-Encoding: base64\n",
+                "\n",
+                "iVBO\n",
+                "--b\n",
+                "Content-Type: message/rfc822\n",
+                "\n",
+                "Subject: nested\n",
+                "\n",
+                "Nested body\n",
+                "--b--\n"
+            ))
+            .unwrap();
+
+        assert!(matches!(message.parts[0].body, PartType::Multipart(_)));
+        assert_eq!(message.parts[0].decoded_bytes(), None);
+        assert!(matches!(message.parts[1].body, PartType::Text(_)));
+        assert_eq!(
+            message.parts[1].decoded_bytes().as_deref(),
+            Some("Café".as_bytes())
+        );
+        assert!(matches!(message.parts[2].body, PartType::Html(_)));
+        assert_eq!(
+            message.parts[2].decoded_bytes().as_deref(),
+            Some(&b"<p>Hi</p>"[..])
+        );
+        assert!(matches!(message.parts[3].body, PartType::Binary(_)));
+        assert_eq!(
+            message.parts[3].decoded_bytes().as_deref(),
+            Some(&[0u8, 1, 2][..])
+        );
+        assert!(matches!(message.parts[4].body, PartType::InlineBinary(_)));
+        assert_eq!(
+            message.parts[4].decoded_bytes().as_deref(),
+            Some(&[0x89u8, b'P', b'N'][..])
+        );
+        assert!(matches!(message.parts[5].body, PartType::Message(_)));
+        assert_eq!(message.parts[5].decoded_bytes(), None);
+        assert!(!message.parts[5].contents().is_empty());
+    }
+
+    #[test]
+    fn base_url() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/related; boundary=\"b\"\n",
+                "\n",
+                "--b\n",
+                "Content-Type: text/html\n",
+                "Content-Base: \"http://www.example.com/docs/\"\n",
+                "Content-Location: index.html\n",
+                "\n",
+                "<a href=\"page.html\">Next</a>\n",
+                "--b\n",
+                "Content-Type: image/png\n",
+                "Content-Location: http://www.example.com/images/logo.png\n",
+                "\n",
+                "PNG\n",
+                "--b\n",
+                "Content-Type: text/plain\n",
+                "\n",
+                "No base\n",
+                "--b--\n"
+            ))
+            .unwrap();
+
+        let html = &message.parts[1];
+        assert_eq!(html.content_location(), Some("index.html"));
+        let base_url = html.base_url().unwrap();
+        assert_eq!(base_url, "http://www.example.com/docs/");
+        let href = html
+            .text_contents()
+            .unwrap()
+            .split_once("href=\"")
+            .and_then(|(_, href)| href.split_once('"'))
+            .map(|(href, _)| href)
+            .unwrap();
+        assert_eq!(
+            format!("{base_url}{href}"),
+            "http://www.example.com/docs/page.html"
+        );
+
+        assert_eq!(
+            message.parts[2].base_url(),
+            Some("http://www.example.com/images/logo.png")
+        );
+        assert_eq!(message.parts[3].base_url(), None);
+    }
+
+    #[test]
+    fn suspicious_attachments() {
+        let input = concat!(
+            "Content-Type: multipart/mixed; boundary=b\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: application/octet-stream; name=\"invoice.pdf.exe\"\r\n",
+            "\r\n",
+            "MZ\r\n",
+            "--b\r\n",
+            "Content-Type: image/jpeg; name=\"photo.jpg\"\r\n",
+            "\r\n",
+            "JPEG\r\n",
+            "--b\r\n",
+            "Content-Type: image/jpeg; name=\"report.PDF\"\r\n",
+            "\r\n",
+            "PDF\r\n",
+            "--b\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Disposition: attachment; filename=\"scan.pdf\"\r\n",
+            "\r\n",
+            "PDF\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain; name=\"notes.txt\"\r\n",
+            "\r\n",
+            "Notes\r\n",
+            "--b\r\n",
+            "Content-Type: application/x-sh; name=\"setup.sh\"\r\n",
+            "\r\n",
+            "echo\r\n",
+            "--b--\r\n",
+        );
+        let message = MessageParser::default().parse(input).unwrap();
+
+        assert_eq!(
+            message
+                .attachments()
+                .map(|part| (
+                    part.attachment_name().unwrap(),
+                    part.is_suspicious_attachment()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                ("invoice.pdf.exe", true),
+                ("photo.jpg", false),
+                ("report.PDF", true),
+                ("scan.pdf", false),
+                ("notes.txt", false),
+                ("setup.sh", false),
+            ]
+        );
+        assert!(message
+            .attachments()
+            .last()
+            .unwrap()
+            .is_suspicious_attachment_with(&["sh"]));
+        assert!(!message.parts[0].is_suspicious_attachment());
+    }
+
+    #[test]
+    fn related_root() {
+        let input = concat!(
+            "Content-Type: multipart/related; boundary=r;\r\n",
+            " type=\"text/html\"; start=\"<html@x>\"\r\n",
+            "\r\n",
+            "--r\r\n",
+            "Content-Type: image/png\r\n",
+            "Content-ID: <logo@x>\r\n",
+            "\r\n",
+            "PNG\r\n",
+            "--r\r\n",
+            "Content-Type: text/html\r\n",
+            "Content-ID: <html@x>\r\n",
+            "\r\n",
+            "<img src=\"cid:logo@x\">\r\n",
+            "--r--\r\n",
+        );
+        let message = MessageParser::default().parse(input).unwrap();
+        let related = &message.parts[0];
+        assert_eq!(related.related_type(), Some("text/html"));
+        let root = related.related_root(&message).unwrap();
+        assert_eq!(root.content_id(), Some("html@x"));
+        assert!(root.is_text_html());
+
+        let input = input.replace(" start=\"<html@x>\"", "");
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert_eq!(
+            message.parts[0]
+                .related_root(&message)
+                .unwrap()
+                .content_id(),
+            Some("logo@x")
+        );
+        let input = input.replace("type=", "start=\"<missing@x>\"; type=");
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert_eq!(message.parts[0].related_root(&message), None);
+        assert_eq!(message.parts[1].related_root(&message), None);
+        assert_eq!(message.parts[1].related_type(), None);
+    }
+
+    #[test]
+    fn canonical_relaxed() {
+        // RFC 6376, section 3.4.5
+        for raw_message in [
+            "A: X\r\nB : Y\t\r\n\tZ  \r\n\r\n C \r\nD \t E\r\n",
+            "A: X\nB : Y\t\n\tZ  \n\n C \nD \t E\n",
+        ] {
+            let message = MessageParser::default().parse(raw_message).unwrap();
+            assert_eq!(
+                message
+                    .headers()
+                    .iter()
+                    .map(|header| header.canonical_relaxed(&message))
+                    .collect::<Vec<_>>(),
+                vec!["a:X\r\n", "b:Y Z\r\n"],
+                "failed for {raw_message:?}"
+            );
+        }
+
+        let raw_message = concat!(
+            "DKIM-Signature: v=1; a=rsa-sha256; d=example.net; s=brisbane;\r\n",
+            "      c=relaxed/relaxed;  h=From:To:Subject;\r\n",
+            "      b=dzdVyOfAKCdLXdJOc9G2q8LoXSlEniSbav+yuU4zGeeruD00lszZ\r\n",
+            "        VoG4ZHRNiYzR\r\n",
+            "SUBJECT:   Is   dinner ready?   \r\n",
+            "\r\n",
+            "Hi.\r\n"
+        );
+        let message = MessageParser::default().parse(raw_message).unwrap();
+        assert_eq!(
+            message
+                .headers()
+                .iter()
+                .map(|header| header.canonical_relaxed(&message))
+                .collect::<Vec<_>>(),
+            vec![
+                concat!(
+                    "dkim-signature:v=1; a=rsa-sha256; d=example.net; s=brisbane; ",
+                    "c=relaxed/relaxed; h=From:To:Subject; ",
+                    "b=dzdVyOfAKCdLXdJOc9G2q8LoXSlEniSbav+yuU4zGeeruD00lszZ VoG4ZHRNiYzR\r\n"
+                ),
+                "subject:Is dinner ready?\r\n"
+            ]
+        );
+
+        // Header offsets of nested messages refer to the enclosing buffer
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Subject: outer\r\n",
+                "Content-Type: message/rfc822\r\n",
+                "\r\n",
+                "Subject:  inner \r\n",
+                "\r\n",
+                "Body\r\n"
+            ))
+            .unwrap();
+        let nested = message.parts[0].message().unwrap();
+        assert_eq!(
+            nested
+                .headers()
+                .iter()
+                .map(|header| header.canonical_relaxed(nested))
+                .collect::<Vec<_>>(),
+            vec!["subject:inner\r\n"]
+        );
+    }
+
+    #[test]
+    fn quoted_lines() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "From: jane@example.org\r\n",
+                "Subject: Re: Re: Lunch\r\n",
+                "\r\n",
+                "Sounds good.\r\n",
+                "\r\n",
+                "On Monday, John wrote:\r\n",
+                "> Noon works for me.\r\n",
+                ">\r\n",
+                "> On Sunday, Jane wrote:\r\n",
+                "> > Shall we meet at noon?\r\n",
+                ">>   Indented\r\n",
+                "> >> Third level\r\n",
+                "\r\n",
+                "Jane\r\n"
+            ))
+            .unwrap();
+        assert_eq!(
+            message.parts[0].quoted_lines(),
+            vec![
+                (0, "Sounds good."),
+                (0, ""),
+                (0, "On Monday, John wrote:"),
+                (1, "Noon works for me."),
+                (1, ""),
+                (1, "On Sunday, Jane wrote:"),
+                (2, "Shall we meet at noon?"),
+                (2, "  Indented"),
+                (3, "Third level"),
+                (0, ""),
+                (0, "Jane"),
+            ]
+        );
+
+        let message = MessageParser::default()
+            .parse("Content-Type: text/html\r\n\r\n<p>&gt; Hi</p>\r\n")
+            .unwrap();
+        assert!(message.parts[0].quoted_lines().is_empty());
+    }
+
+    #[test]
+    fn content_type_raw() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "From: john@example.org\r\n",
+                "Content-Type: Multipart/Mixed;\r\n",
+                "  boundary=\"b\" ; charset = utf-8\r\n",
+                "\r\n",
+                "--b\r\n",
+                "Content-Type: text/plain;format=flowed;  CHARSET=\"ISO-8859-1\"\r\n",
+                "\r\n",
+                "Hello\r\n",
+                "--b\r\n",
+                "\r\n",
+                "No Content-Type\r\n",
+                "--b--\r\n"
+            ))
+            .unwrap();
+
+        let root = &message.parts[0];
+        assert_eq!(
+            root.content_type_raw(&message),
+            Some(" Multipart/Mixed;\r\n  boundary=\"b\" ; charset = utf-8\r\n")
+        );
+        let content_type = root.content_type().unwrap();
+        assert_eq!(content_type.ctype(), "multipart");
+        assert_eq!(content_type.subtype(), Some("mixed"));
+        assert_eq!(content_type.attribute("boundary"), Some("b"));
+
+        let text = &message.parts[1];
+        assert_eq!(
+            text.content_type_raw(&message),
+            Some(" text/plain;format=flowed;  CHARSET=\"ISO-8859-1\"\r\n")
+        );
+        assert_eq!(
+            text.content_type().unwrap().attributes().unwrap(),
+            [
+                ("format".into(), "flowed".into()),
+                ("charset".into(), "ISO-8859-1".into())
+            ]
+        );
+
+        assert_eq!(message.parts[2].content_type_raw(&message), None);
+
+        // Parts of nested messages
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+                "\r\n",
+                "--b\r\n",
+                "Content-Type: message/rfc822\r\n",
+                "\r\n",
+                "Subject: Nested\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "\r\n",
+                "Hello\r\n",
+                "--b--\r\n"
+            ))
+            .unwrap();
+        let nested = message.parts[1].message().unwrap();
+        assert_eq!(
+            nested.parts[0].content_type_raw(nested),
+            Some(" text/plain; charset=utf-8\r\n")
+        );
+    }
+}
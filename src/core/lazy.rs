@@ -0,0 +1,204 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::borrow::Cow;
+
+use crate::{
+    decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode},
+    parsers::message::decode_text,
+    Encoding, LazyMessage, Message, MessagePart, MessagePartId, MimeHeaders, PartType,
+};
+
+#[cfg(feature = "compression")]
+use crate::{decoders::compression::decompress, GetHeader, HeaderName};
+
+impl<'x> LazyMessage<'x> {
+    /// Returns the parsed message. The bodies of its leaf parts, including
+    /// those of nested messages, are kept raw and have to be decoded with
+    /// `contents` or `text_contents`.
+    pub fn message(&self) -> &Message<'x> {
+        &self.message
+    }
+
+    /// Returns the parsed message, see `LazyMessage::message`.
+    pub fn into_message(self) -> Message<'x> {
+        self.message
+    }
+
+    /// Returns a top-level part by its id
+    pub fn part(&self, part_id: MessagePartId) -> Option<&MessagePart<'x>> {
+        self.message.parts.get(part_id)
+    }
+
+    /// Decodes the body of a leaf part of this message or of one of its
+    /// nested messages, removing the transfer encoding. Bodies that fail to
+    /// decode are returned raw. `None` is returned for multipart and nested
+    /// message parts.
+    ///
+    /// Decoding is done on every call and the result is not cached, the
+    /// returned value borrows from the part when no decoding was needed.
+    /// Bodies that exceed `MessageParser::with_max_decompressed_size` once
+    /// decompressed are returned compressed.
+    pub fn contents<'a>(&self, part: &'a MessagePart<'x>) -> Option<Cow<'a, [u8]>> {
+        let raw = match &part.body {
+            PartType::Binary(bytes) | PartType::InlineBinary(bytes) => bytes.as_ref(),
+            PartType::Text(text) | PartType::Html(text) => {
+                return Some(Cow::Borrowed(text.as_bytes()))
+            }
+            PartType::Message(_) | PartType::Multipart(_) => return None,
+        };
+
+        let bytes = match part.encoding {
+            Encoding::Base64 => base64_decode(raw).map_or(Cow::Borrowed(raw), Cow::Owned),
+            Encoding::QuotedPrintable => {
+                quoted_printable_decode(raw).map_or(Cow::Borrowed(raw), Cow::Owned)
+            }
+            Encoding::None => Cow::Borrowed(raw),
+        };
+
+        #[cfg(feature = "compression")]
+        if let Some(Ok(decompressed)) = part
+            .headers
+            .header_value(&HeaderName::Other("Content-Encoding".into()))
+            .and_then(|value| value.as_text())
+            .and_then(|value| decompress(value, &bytes, self.max_decompressed_size))
+        {
+            return Some(Cow::Owned(decompressed));
+        }
+
+        Some(bytes)
+    }
+
+    /// Decodes the body of a leaf part as text, removing the transfer encoding
+    /// and converting it from its declared charset with the settings of the
+    /// `MessageParser` that parsed the message. `None` is returned for
+    /// multipart and nested message parts.
+    pub fn text_contents<'a>(&self, part: &'a MessagePart<'x>) -> Option<Cow<'a, str>> {
+        match &part.body {
+            PartType::Text(text) | PartType::Html(text) => Some(Cow::Borrowed(text.as_ref())),
+            _ => self.contents(part).map(|bytes| {
+                decode_text(
+                    bytes,
+                    part.content_type().and_then(|ct| ct.attribute("charset")),
+                    self.unknown_charset_fallback,
+                    self.line_ending,
+                )
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::{MessageParser, PartType};
+
+    #[test]
+    fn lazy_bodies() {
+        let input = concat!(
+            "From: john@example.org\r\n",
+            "Subject: Lazy\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain; charset=iso-8859-1\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "Caf=E9 cr=E8me\r\n",
+            "--b\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "SGVsbG8g\r\n",
+            "d29ybGQ=\r\n",
+            "--b\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "\r\n",
+            "Subject: Nested\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "TmVzdGVk\r\n",
+            "--b\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "U3ViamVjdDogRW5jb2RlZA0KQ29udGVudC1UeXBlOiB0ZXh0L3BsYWluOyBjaGFyc2V0PXV0Zi04\r\n",
+            "DQpDb250ZW50LVRyYW5zZmVyLUVuY29kaW5nOiBxdW90ZWQtcHJpbnRhYmxlDQoNCkNhZj1DMz1B\r\n",
+            "OQ0K\r\n",
+            "--b--\r\n"
+        );
+        let lazy = MessageParser::default().parse_lazy(input).unwrap();
+        let message = lazy.message();
+        assert_eq!(message.subject(), Some("Lazy"));
+        assert_eq!(message.text_body, vec![1]);
+        assert_eq!(message.attachments, vec![2, 3, 4]);
+
+        // Bodies are borrowed from the input, nothing was decoded
+        for (part_id, raw) in [(1, "Caf=E9 cr=E8me"), (2, "SGVsbG8g\r\nd29ybGQ=")] {
+            assert_eq!(
+                lazy.part(part_id).unwrap().body,
+                PartType::Binary(Cow::Borrowed(raw.as_bytes()))
+            );
+            assert!(matches!(
+                lazy.part(part_id).unwrap().body,
+                PartType::Binary(Cow::Borrowed(_))
+            ));
+        }
+
+        let text = lazy.part(1).unwrap();
+        assert_eq!(lazy.text_contents(text).as_deref(), Some("Café crème"));
+
+        // The eager accessors do not return undecoded bodies
+        assert!(text.is_undecoded);
+        assert_eq!(text.text_contents(), None);
+        assert_eq!(text.decoded_bytes(), None);
+        assert_eq!(message.body_text(0), None);
+        assert!(!lazy.part(0).unwrap().is_undecoded);
+        let binary = lazy.part(2).unwrap();
+        assert!(matches!(lazy.contents(binary), Some(Cow::Owned(_))));
+        assert_eq!(lazy.contents(binary).as_deref(), Some(&b"Hello world"[..]));
+
+        let nested = lazy.part(3).unwrap().message().unwrap();
+        assert_eq!(nested.subject(), Some("Nested"));
+        assert_eq!(
+            nested.parts[0].body,
+            PartType::Binary(Cow::Borrowed(b"TmVzdGVk"))
+        );
+        assert_eq!(
+            lazy.text_contents(&nested.parts[0]).as_deref(),
+            Some("Nested")
+        );
+        assert_eq!(lazy.contents(lazy.part(0).unwrap()), None);
+
+        // Encoded nested messages are parsed, their parts decoded on request
+        let nested = lazy.part(4).unwrap().message().unwrap();
+        assert_eq!(nested.subject(), Some("Encoded"));
+        assert!(nested.parts[0].is_undecoded);
+        assert_eq!(nested.parts[0].contents(), b"Caf=C3=A9\r\n");
+        assert_eq!(
+            lazy.text_contents(&nested.parts[0]).as_deref(),
+            Some("Café\r\n")
+        );
+
+        // Same structure as an eager parse
+        let eager = MessageParser::default().parse(input).unwrap();
+        assert_eq!(eager.text_body, message.text_body);
+        assert_eq!(eager.html_body, message.html_body);
+        assert_eq!(eager.attachments, message.attachments);
+        assert_eq!(eager.parts.len(), message.parts.len());
+        for (eager_part, lazy_part) in eager.parts.iter().zip(message.parts.iter()) {
+            assert_eq!(eager_part.headers, lazy_part.headers);
+            assert_eq!(eager_part.offset_body, lazy_part.offset_body);
+            assert_eq!(eager_part.offset_end, lazy_part.offset_end);
+        }
+    }
+}
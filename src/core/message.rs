@@ -9,17 +9,22 @@
  * except according to those terms.
  */
 
-use std::{borrow::Cow, convert::TryInto};
+use std::{borrow::Cow, convert::TryInto, net::IpAddr};
 
 use crate::{
-    decoders::html::{html_to_text, text_to_html},
+    decoders::{
+        base64::base64_decode,
+        hex::decode_hex,
+        html::{html_to_text, text_to_html},
+    },
     parsers::{
-        fields::thread::thread_name,
-        preview::{preview_html, preview_text},
+        fields::{id::is_valid_msg_id, thread::thread_name},
+        preview::{preview_html, preview_html_bytes, preview_text, preview_text_bytes},
         MessageStream,
     },
-    Address, AttachmentIterator, BodyPartIterator, DateTime, GetHeader, Header, HeaderForm,
-    HeaderName, HeaderValue, Message, MessageParser, MessagePart, PartType, Received,
+    Addr, Address, AttachmentIterator, BodyPartIterator, DateTime, GetHeader, Header, HeaderForm,
+    HeaderName, HeaderValue, InlineImage, ListId, Message, MessageParser, MessagePart,
+    MessageSecurity, MimeHeaders, PartType, Priority, Received, SecurityProtocol, ThreadIndex,
 };
 
 impl<'x> Message<'x> {
@@ -51,6 +56,20 @@ impl<'x> Message<'x> {
             .and_then(|h| std::str::from_utf8(&self.raw_message[h.offset_start..h.offset_end]).ok())
     }
 
+    /// Returns the text value of the first header field named `name`, which
+    /// is matched case-insensitively and may be a known header or an `Other`
+    /// one. `None` is returned when the header is missing or its parsed value
+    /// is not text, such as an address or a date.
+    pub fn header_text(&self, name: &str) -> Option<&str> {
+        let name = HeaderName::from(name);
+        self.parts[0]
+            .headers
+            .iter()
+            .find(|header| header.name == name)?
+            .value
+            .as_text()
+    }
+
     // Parse a header as a specific type.
     pub fn header_as(
         &self,
@@ -104,6 +123,29 @@ impl<'x> Message<'x> {
         })
     }
 
+    /// Returns the name and decoded value of all header fields, in the
+    /// order they appear in the message. Structured values are rendered as
+    /// follows:
+    ///
+    /// * Addresses as an RFC 5322 address list (see the `Display`
+    ///   implementation of `Address`).
+    /// * Dates in RFC 3339 format.
+    /// * Message ids enclosed in `<>` and separated by a space, other
+    ///   multi-valued headers such as Keywords separated by `", "`.
+    /// * Content-Type and Content-Disposition as `type/subtype` followed by
+    ///   their `; name="value"` attributes.
+    /// * Received headers on a single line.
+    ///
+    /// Headers that appear several times are returned once per occurrence.
+    pub fn display_headers(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.parts[0].headers.iter().map(|header| {
+            (
+                header.name.as_str().to_string(),
+                display_value(&header.name, &header.value),
+            )
+        })
+    }
+
     /// Returns all headers in raw format
     pub fn headers_raw(&self) -> impl Iterator<Item = (&str, &str)> {
         self.parts[0].headers.iter().filter_map(move |header| {
@@ -123,6 +165,63 @@ impl<'x> Message<'x> {
             .unwrap_or_default()
     }
 
+    /// Returns the buffer that the `offset_*` fields of headers and parts
+    /// refer to. For top-level messages this is the raw message, while
+    /// nested messages may share the buffer of their enclosing message.
+    pub fn raw_bytes(&self) -> &[u8] {
+        &self.raw_message
+    }
+
+    /// Returns the length in bytes of the raw message as it appeared on the
+    /// wire. For messages nested inside another message this is the length
+    /// of the nested message's own range, not of the enclosing buffer.
+    pub fn raw_len(&self) -> usize {
+        self.raw_message().len()
+    }
+
+    /// Returns the sum of the decoded lengths of all body parts, recursing
+    /// into nested messages. Unlike [`MessagePart::len`], which reports the
+    /// decoded length of a single part (or the raw length of a nested
+    /// message), this excludes headers, MIME boundaries and transfer
+    /// encoding overhead.
+    pub fn decoded_size(&self) -> usize {
+        self.parts
+            .iter()
+            .map(|part| match &part.body {
+                PartType::Message(message) => message.decoded_size(),
+                _ => part.len(),
+            })
+            .sum()
+    }
+
+    /// Returns a 64-bit FNV-1a hash of the decoded contents of all body
+    /// parts, in the order they appear in the message and recursing into
+    /// nested messages. Headers and transfer encodings are not part of the
+    /// hash, and text parts are hashed as UTF-8 after charset decoding, so
+    /// the same body sent with different encodings produces the same
+    /// fingerprint.
+    pub fn body_fingerprint(&self) -> u64 {
+        self.body_fingerprint_(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn body_fingerprint_(&self, mut hash: u64) -> u64 {
+        for part in &self.parts {
+            match &part.body {
+                PartType::Multipart(_) => {}
+                PartType::Message(message) => {
+                    hash = message.body_fingerprint_(hash);
+                }
+                _ => {
+                    let contents = part.contents();
+                    for &ch in (contents.len() as u64).to_le_bytes().iter().chain(contents) {
+                        hash = (hash ^ ch as u64).wrapping_mul(0x0100_0000_01b3);
+                    }
+                }
+            }
+        }
+        hash
+    }
+
     /// Returns the BCC header field
     pub fn bcc(&self) -> Option<&Address<'x>> {
         self.parts[0]
@@ -139,20 +238,34 @@ impl<'x> Message<'x> {
             .and_then(|a| a.as_address())
     }
 
-    /// Returns all Comments header fields
-    pub fn comments(&self) -> &HeaderValue<'x> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::Comments)
-            .unwrap_or(&HeaderValue::Empty)
+    /// Returns the decoded values of all Comments header fields, in the
+    /// order they appear in the message
+    pub fn comments(&self) -> Vec<&str> {
+        self.header_values(HeaderName::Comments)
+            .filter_map(|value| value.as_text())
+            .collect()
     }
 
-    /// Returns the Date header field
+    /// Returns the first Date header field containing a valid date
     pub fn date(&self) -> Option<&DateTime> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::Date)
-            .and_then(|header| header.as_datetime())
+        self.header_values(HeaderName::Date)
+            .filter_map(|header| header.as_datetime())
+            .find(|date| date.is_valid())
+    }
+
+    /// Returns the date the message was sent, as declared by its Date header field
+    pub fn sent_date(&self) -> Option<&DateTime> {
+        self.date()
+    }
+
+    /// Returns the timestamp of the first Received header field, which is the
+    /// one added by the host closest to the final delivery
+    pub fn received_date(&self) -> Option<&DateTime> {
+        self.header_values(HeaderName::Received)
+            .next()
+            .and_then(|header| header.as_received())
+            .and_then(|received| received.date.as_ref())
+            .filter(|date| date.is_valid())
     }
 
     /// Returns the From header field
@@ -163,20 +276,118 @@ impl<'x> Message<'x> {
             .and_then(|a| a.as_address())
     }
 
-    /// Returns all In-Reply-To header fields
-    pub fn in_reply_to(&self) -> &HeaderValue<'x> {
+    /// Returns every address found in the originator and destination header
+    /// fields (`From`, `Sender`, `Reply-To`, `To`, `Cc`, `Bcc` and their
+    /// `Resent-*` counterparts) in the order they appear, together with the
+    /// name of the header they were found in. Groups are flattened.
+    pub fn all_addresses(&self) -> impl Iterator<Item = (&HeaderName<'x>, &Addr<'x>)> {
         self.parts[0]
             .headers
-            .header_value(&HeaderName::InReplyTo)
-            .unwrap_or(&HeaderValue::Empty)
+            .iter()
+            .filter(|header| {
+                matches!(
+                    header.name,
+                    HeaderName::From
+                        | HeaderName::Sender
+                        | HeaderName::ReplyTo
+                        | HeaderName::To
+                        | HeaderName::Cc
+                        | HeaderName::Bcc
+                        | HeaderName::ResentFrom
+                        | HeaderName::ResentSender
+                        | HeaderName::ResentTo
+                        | HeaderName::ResentCc
+                        | HeaderName::ResentBcc
+                )
+            })
+            .filter_map(|header| Some((&header.name, header.value.as_address()?)))
+            .flat_map(|(name, address)| address.iter().map(move |addr| (name, addr)))
+    }
+
+    /// Returns all the mailboxes listed in the From header field. RFC 5322
+    /// allows a message to have several authors, in which case the Sender
+    /// header field must also be present to identify the actual sender.
+    pub fn authors(&self) -> impl Iterator<Item = &Addr<'x>> {
+        self.from().into_iter().flat_map(|address| address.iter())
     }
 
-    /// Returns all Keywords header fields
-    pub fn keywords(&self) -> &HeaderValue<'x> {
+    /// Returns the name to display for the author of the message: the
+    /// display name of the first From address, falling back to the local
+    /// part of its address and then to the full address.
+    pub fn from_display_name(&self) -> Option<Cow<'_, str>> {
+        let addr = self.from()?.first()?;
+
+        addr.name()
+            .map(|name| {
+                let name = name.trim();
+                name.strip_prefix('"')
+                    .and_then(|name| name.strip_suffix('"'))
+                    .unwrap_or(name)
+                    .trim()
+            })
+            .filter(|name| !name.is_empty())
+            .or_else(|| {
+                let address = addr.address()?.trim();
+                address
+                    .rsplit_once('@')
+                    .map(|(local, _)| local)
+                    .filter(|local| !local.is_empty())
+                    .or(Some(address))
+                    .filter(|address| !address.is_empty())
+            })
+            .map(Cow::Borrowed)
+    }
+
+    /// Returns the lowercased domain of the first From address, the anchor
+    /// of DMARC alignment checks (RFC 7489). When From lists several
+    /// mailboxes only the first one is used, although DMARC verifiers
+    /// usually reject such messages. The domain is only borrowed when it is
+    /// already lowercase.
+    pub fn from_domain(&self) -> Option<Cow<'_, str>> {
+        let (_, domain) = self.from()?.first()?.address()?.trim().rsplit_once('@')?;
+        let domain = domain.trim();
+
+        if domain.is_empty() {
+            None
+        } else if domain.bytes().any(|ch| ch.is_ascii_uppercase()) {
+            Some(domain.to_ascii_lowercase().into())
+        } else {
+            Some(domain.into())
+        }
+    }
+
+    /// Returns the message ids listed in the In-Reply-To header field.
+    /// Quoted strings and comments around the ids, which some clients use
+    /// to describe the message being replied to, are ignored.
+    pub fn in_reply_to(&self) -> Vec<&str> {
         self.parts[0]
             .headers
-            .header_value(&HeaderName::Keywords)
-            .unwrap_or(&HeaderValue::Empty)
+            .header_value(&HeaderName::InReplyTo)
+            .and_then(|header| header.as_text_list())
+            .unwrap_or_default()
+    }
+
+    /// Returns the keywords listed in all Keywords header fields, in order
+    /// of appearance and without empty or duplicate (case-insensitive) entries
+    pub fn keywords(&self) -> Vec<&str> {
+        let mut keywords: Vec<&str> = Vec::new();
+        for keyword in self
+            .header_values(HeaderName::Keywords)
+            .filter_map(|value| value.as_text_list())
+            .flatten()
+        {
+            let keyword = keyword.trim();
+            if !keyword.is_empty()
+                && !keywords.iter().any(|k| {
+                    k.chars()
+                        .flat_map(char::to_lowercase)
+                        .eq(keyword.chars().flat_map(char::to_lowercase))
+                })
+            {
+                keywords.push(keyword);
+            }
+        }
+        keywords
     }
 
     /// Returns the List-Archive header field
@@ -195,12 +406,33 @@ impl<'x> Message<'x> {
             .unwrap_or(&HeaderValue::Empty)
     }
 
-    /// Returns the List-ID header field
-    pub fn list_id(&self) -> &HeaderValue<'x> {
-        self.parts[0]
+    /// Returns the List-ID header field split into its optional
+    /// description and its list identifier, as in
+    /// `"Human Name" <list.id.domain>` (RFC 2919).
+    pub fn list_id(&self) -> Option<ListId<'_>> {
+        let addr = self.parts[0]
             .headers
-            .header_value(&HeaderName::ListId)
-            .unwrap_or(&HeaderValue::Empty)
+            .header_value(&HeaderName::ListId)?
+            .as_address()?
+            .first()?;
+        let id = addr.address()?.trim();
+        let id = id
+            .strip_prefix('<')
+            .and_then(|id| id.strip_suffix('>'))
+            .unwrap_or(id)
+            .trim();
+
+        if !id.is_empty() {
+            Some(ListId {
+                description: addr
+                    .name()
+                    .map(|name| name.trim())
+                    .filter(|name| !name.is_empty()),
+                id,
+            })
+        } else {
+            None
+        }
     }
 
     /// Returns the List-Owner header field
@@ -235,12 +467,23 @@ impl<'x> Message<'x> {
             .unwrap_or(&HeaderValue::Empty)
     }
 
-    /// Returns the Message-ID header field
+    /// Returns the Message-ID header field, if multiple ids are present
+    /// the first one is returned
     pub fn message_id(&self) -> Option<&str> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::MessageId)
-            .and_then(|header| header.as_text())
+        match self.parts[0].headers.header_value(&HeaderName::MessageId)? {
+            HeaderValue::Text(id) => Some(id.as_ref()),
+            HeaderValue::TextList(ids) => ids.first().map(|id| id.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` when the raw Message-ID header field is a well-formed
+    /// RFC 5322 `msg-id`. The parser is lenient and will still extract an id
+    /// from malformed fields, this can be used to decide whether that id is
+    /// reliable enough for threading.
+    pub fn message_id_valid(&self) -> bool {
+        self.header_raw(HeaderName::MessageId)
+            .is_some_and(is_valid_msg_id)
     }
 
     /// Returns the MIME-Version header field
@@ -251,6 +494,74 @@ impl<'x> Message<'x> {
             .unwrap_or(&HeaderValue::Empty)
     }
 
+    /// Returns the Organization header field
+    pub fn organization(&self) -> Option<&str> {
+        self.parts[0]
+            .headers
+            .header_value(&HeaderName::Organization)
+            .and_then(|header| header.as_text())
+    }
+
+    /// Returns the priority of the message from the `X-Priority`,
+    /// `Importance` or `Priority` header fields. When more than one is
+    /// present they are checked in that order and the first one with a
+    /// recognized value wins, as `X-Priority` is the header most clients
+    /// actually display.
+    pub fn priority(&self) -> Option<Priority> {
+        let value = |name: &'static str| {
+            self.header(name)
+                .and_then(|value| value.as_text())
+                .map(|value| value.trim())
+        };
+
+        value("X-Priority")
+            .and_then(|value| match value.as_bytes().first()? {
+                b'1' | b'2' => Some(Priority::High),
+                b'3' => Some(Priority::Normal),
+                b'4' | b'5' => Some(Priority::Low),
+                _ => None,
+            })
+            .or_else(|| {
+                let value = value("Importance")?;
+                if value.eq_ignore_ascii_case("high") {
+                    Some(Priority::High)
+                } else if value.eq_ignore_ascii_case("normal") {
+                    Some(Priority::Normal)
+                } else if value.eq_ignore_ascii_case("low") {
+                    Some(Priority::Low)
+                } else {
+                    None
+                }
+            })
+            .or_else(|| {
+                let value = value("Priority")?;
+                if value.eq_ignore_ascii_case("urgent") {
+                    Some(Priority::High)
+                } else if value.eq_ignore_ascii_case("normal") {
+                    Some(Priority::Normal)
+                } else if value.eq_ignore_ascii_case("non-urgent") {
+                    Some(Priority::Low)
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Returns the IP address that most likely injected the message: the
+    /// `from` IP of the earliest (bottom-most) Received header field that is
+    /// not accepted by `is_trusted`. The closure is used to exclude internal
+    /// relays, for example by checking the address against a list of
+    /// trusted networks.
+    pub fn origin_ip(&self, is_trusted: impl Fn(&IpAddr) -> bool) -> Option<IpAddr> {
+        self.parts[0]
+            .headers
+            .iter()
+            .rev()
+            .filter(|header| header.name == HeaderName::Received)
+            .filter_map(|header| header.value.as_received()?.from_ip())
+            .find(|ip| !is_trusted(ip))
+    }
+
     /// Returns the first Received header field
     pub fn received(&self) -> Option<&Received<'x>> {
         self.parts[0]
@@ -259,12 +570,13 @@ impl<'x> Message<'x> {
             .and_then(|header| header.as_received())
     }
 
-    /// Returns all References header fields
-    pub fn references(&self) -> &HeaderValue<'x> {
+    /// Returns the message ids listed in the References header field, in order
+    pub fn references(&self) -> Vec<&str> {
         self.parts[0]
             .headers
             .header_value(&HeaderName::References)
-            .unwrap_or(&HeaderValue::Empty)
+            .and_then(|header| header.as_text_list())
+            .unwrap_or_default()
     }
 
     /// Returns the Reply-To header field
@@ -275,59 +587,131 @@ impl<'x> Message<'x> {
             .and_then(|a| a.as_address())
     }
 
-    /// Returns the Resent-BCC header field
+    /// Returns the Resent-BCC header field of the most recent resent block,
+    /// see `Message::resent_date`
     pub fn resent_bcc(&self) -> Option<&Address<'x>> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentBcc)
+        self.resent_header_value(HeaderName::ResentBcc)
             .and_then(|a| a.as_address())
     }
 
-    /// Returns the Resent-CC header field
+    /// Returns the Resent-CC header field of the most recent resent block,
+    /// see `Message::resent_date`
     pub fn resent_cc(&self) -> Option<&Address<'x>> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentTo)
+        self.resent_header_value(HeaderName::ResentCc)
             .and_then(|a| a.as_address())
     }
 
-    /// Returns all Resent-Date header fields
-    pub fn resent_date(&self) -> &HeaderValue<'x> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentDate)
-            .unwrap_or(&HeaderValue::Empty)
+    /// Returns the Resent-Date header field of the most recent resent block.
+    /// Each time a message is reintroduced into the transport system a new
+    /// block of `Resent-*` fields is added at the top of the header, so the
+    /// most recent block is the first one in document order (RFC 5322,
+    /// section 3.6.6).
+    pub fn resent_date(&self) -> Option<&DateTime> {
+        self.resent_header_value(HeaderName::ResentDate)
+            .and_then(|date| date.as_datetime())
     }
 
-    /// Returns the Resent-From header field
+    /// Returns the Resent-From header field of the most recent resent block,
+    /// see `Message::resent_date`
     pub fn resent_from(&self) -> Option<&Address<'x>> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentFrom)
+        self.resent_header_value(HeaderName::ResentFrom)
             .and_then(|a| a.as_address())
     }
 
-    /// Returns all Resent-Message-ID header fields
+    /// Returns the Resent-Message-ID header field of the most recent resent
+    /// block, see `Message::resent_date`
     pub fn resent_message_id(&self) -> &HeaderValue<'x> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentMessageId)
+        self.resent_header_value(HeaderName::ResentMessageId)
             .unwrap_or(&HeaderValue::Empty)
     }
 
-    /// Returns the Sender header field
+    // Returns the value of a field from the first block of Resent-* fields,
+    // which ends at the first other field or at a repeated Resent-* field.
+    fn resent_header_value(&self, name: HeaderName<'_>) -> Option<&HeaderValue<'x>> {
+        let headers = &self.parts[0].headers;
+        let start = headers
+            .iter()
+            .position(|header| is_resent_header(&header.name))?;
+        let mut end = start;
+        while headers.get(end).is_some_and(|header| {
+            is_resent_header(&header.name)
+                && !headers[start..end]
+                    .iter()
+                    .any(|prev| prev.name == header.name)
+        }) {
+            end += 1;
+        }
+        headers[start..end]
+            .iter()
+            .find(|header| header.name == name)
+            .map(|header| &header.value)
+    }
+
+    /// Returns whether the message is signed or encrypted with S/MIME or PGP,
+    /// based on the content types of its parts.
+    pub fn security(&self) -> MessageSecurity<'_> {
+        let mut security = MessageSecurity::default();
+
+        for part in &self.parts {
+            let Some(ct) = part.content_type() else {
+                continue;
+            };
+            let protocol = ct.attribute("protocol").unwrap_or_default();
+
+            if ct.ctype().eq_ignore_ascii_case("multipart") {
+                match ct.subtype() {
+                    Some(st) if st.eq_ignore_ascii_case("signed") => {
+                        let signed = if protocol.eq_ignore_ascii_case("application/pgp-signature") {
+                            SecurityProtocol::Pgp
+                        } else if protocol.eq_ignore_ascii_case("application/pkcs7-signature")
+                            || protocol.eq_ignore_ascii_case("application/x-pkcs7-signature")
+                        {
+                            SecurityProtocol::SMime
+                        } else {
+                            continue;
+                        };
+                        if security.signed.is_none() {
+                            security.signed = Some(signed);
+                            security.micalg = ct.attribute("micalg").map(Cow::Borrowed);
+                        }
+                    }
+                    Some(st)
+                        if st.eq_ignore_ascii_case("encrypted")
+                            && protocol.eq_ignore_ascii_case("application/pgp-encrypted") =>
+                    {
+                        security.encrypted.get_or_insert(SecurityProtocol::Pgp);
+                    }
+                    _ => (),
+                }
+            } else if part.is_content_type("application", "pkcs7-mime")
+                || part.is_content_type("application", "x-pkcs7-mime")
+            {
+                match ct.attribute("smime-type") {
+                    Some(st) if st.eq_ignore_ascii_case("signed-data") => {
+                        security.signed.get_or_insert(SecurityProtocol::SMime);
+                    }
+                    Some(st) if st.eq_ignore_ascii_case("compressed-data") => (),
+                    _ => {
+                        security.encrypted.get_or_insert(SecurityProtocol::SMime);
+                    }
+                }
+            }
+        }
+
+        security
+    }
+
+    /// Returns the Resent-Sender header field of the most recent resent
+    /// block, see `Message::resent_date`
     pub fn resent_sender(&self) -> Option<&Address<'x>> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentSender)
+        self.resent_header_value(HeaderName::ResentSender)
             .and_then(|a| a.as_address())
     }
 
-    /// Returns the Resent-To header field
+    /// Returns the Resent-To header field of the most recent resent block,
+    /// see `Message::resent_date`
     pub fn resent_to(&self) -> Option<&Address<'x>> {
-        self.parts[0]
-            .headers
-            .header_value(&HeaderName::ResentTo)
+        self.resent_header_value(HeaderName::ResentTo)
             .and_then(|a| a.as_address())
     }
 
@@ -352,6 +736,35 @@ impl<'x> Message<'x> {
         }
     }
 
+    /// Returns the address read receipts should be sent to, taken from the
+    /// Disposition-Notification-To header or, if missing, from the legacy
+    /// Return-Receipt-To header. When several addresses are listed only the
+    /// first one is returned, use `header` to obtain all of them.
+    pub fn read_receipt_to(&self) -> Option<&Addr<'x>> {
+        [
+            HeaderName::DispositionNotificationTo,
+            HeaderName::ReturnReceiptTo,
+        ]
+        .iter()
+        .find_map(|name| {
+            self.parts[0]
+                .headers
+                .header_value(name)
+                .and_then(|a| a.as_address())
+                .and_then(|a| a.first())
+        })
+    }
+
+    /// Returns the addresses of all the Delivered-To header fields, in the
+    /// order they appear in the message. Each delivery agent that handles
+    /// the message prepends one, so a repeated address denotes a mail loop.
+    pub fn delivered_to(&self) -> Vec<&Addr<'x>> {
+        self.header_values(HeaderName::DeliveredTo)
+            .filter_map(|value| value.as_address())
+            .flat_map(|address| address.iter())
+            .collect()
+    }
+
     /// Returns the Sender header field
     pub fn sender(&self) -> Option<&Address<'x>> {
         self.parts[0]
@@ -360,6 +773,64 @@ impl<'x> Message<'x> {
             .and_then(|a| a.as_address())
     }
 
+    /// Returns the software used to compose the message, taken from the
+    /// User-Agent header field or, if missing, from X-Mailer.
+    pub fn user_agent(&self) -> Option<&str> {
+        [HeaderName::UserAgent, HeaderName::XMailer]
+            .iter()
+            .find_map(|name| {
+                self.parts[0]
+                    .headers
+                    .header_value(name)
+                    .and_then(|header| header.as_text())
+            })
+    }
+
+    /// Returns the image of the non-standard Face header field, a base64
+    /// encoded PNG of the sender's face of at most 48x48 pixels. Returns
+    /// `None` when the header is missing, is not valid base64 or does not
+    /// contain a PNG image.
+    pub fn face_png(&self) -> Option<Vec<u8>> {
+        base64_decode(
+            self.parts[0]
+                .headers
+                .header_value(&HeaderName::Face)?
+                .as_text()?
+                .as_bytes(),
+        )
+        .filter(|png| png.starts_with(b"\x89PNG\r\n\x1a\n"))
+    }
+
+    /// Returns the undecoded value of the non-standard X-Face header field,
+    /// a compressed 48x48 monochrome bitmap. Whitespace and line breaks in
+    /// the value have to be ignored when decoding it.
+    pub fn x_face(&self) -> Option<&str> {
+        self.parts[0]
+            .headers
+            .header_value(&HeaderName::XFace)
+            .and_then(|header| header.as_text())
+    }
+
+    /// Returns the RFC 2424 Content-Duration field of the root part in seconds
+    pub fn content_duration(&self) -> Option<u32> {
+        self.parts[0].content_duration()
+    }
+
+    /// Returns the BCP 47 language tag of the message, which is the first
+    /// tag of the Content-Language header field or, if missing, the tag of
+    /// the language detected in the first text body. Detected languages are
+    /// returned as ISO 639-1 codes (`"en"`, `"fr"`), falling back to ISO
+    /// 639-3 for languages without a two letter code. Returns `None` when
+    /// the language of the body cannot be reliably detected.
+    #[cfg(feature = "lang_detect")]
+    pub fn detect_language(&self) -> Option<&str> {
+        if let Some(tag) = self.content_language_tags().first() {
+            return Some(tag);
+        }
+        let info = whatlang::detect(self.body_text(0)?.as_ref())?;
+        info.is_reliable().then(|| language_tag(info.lang()))
+    }
+
     /// Returns the Subject header field
     pub fn subject(&self) -> Option<&str> {
         self.parts[0]
@@ -368,12 +839,44 @@ impl<'x> Message<'x> {
             .and_then(|header| header.as_text())
     }
 
+    /// Returns the Subject header field as it appears in the message,
+    /// without decoding RFC 2047 encoded words or unfolding. Leading
+    /// whitespace and the final line ending are removed.
+    pub fn raw_subject(&self) -> Option<&[u8]> {
+        let header = self.parts[0].headers.header(HeaderName::Subject)?;
+        let raw = self
+            .raw_message
+            .get(header.offset_start..header.offset_end)?;
+        let start = raw
+            .iter()
+            .position(|ch| !matches!(ch, b' ' | b'\t'))
+            .unwrap_or(raw.len());
+        let end = raw
+            .iter()
+            .rposition(|ch| !matches!(ch, b'\r' | b'\n'))
+            .map_or(start, |pos| pos + 1)
+            .max(start);
+        Some(&raw[start..end])
+    }
+
     /// Returns the message thread name or 'base subject' as defined in
     /// [RFC 5957 - Internet Message Access Protocol - SORT and THREAD Extensions (Section 2.1)](https://datatracker.ietf.org/doc/html/rfc5256#section-2.1)
     pub fn thread_name(&self) -> Option<&str> {
         thread_name(self.subject()?).into()
     }
 
+    /// Returns the decoded Microsoft Exchange Thread-Index header field
+    pub fn thread_index(&self) -> Option<ThreadIndex> {
+        ThreadIndex::parse(self.header_raw("Thread-Index")?.trim().as_bytes())
+    }
+
+    /// Returns the Microsoft Exchange Thread-Topic header field
+    pub fn thread_topic(&self) -> Option<Cow<'_, str>> {
+        MessageStream::new(self.header_raw("Thread-Topic")?.as_bytes())
+            .parse_unstructured()
+            .into_text()
+    }
+
     /// Returns the To header field
     pub fn to(&self) -> Option<&Address<'x>> {
         self.parts[0]
@@ -382,7 +885,7 @@ impl<'x> Message<'x> {
             .and_then(|a| a.as_address())
     }
 
-    /// Returns a preview of the message body
+    /// Returns a preview of the message body of at most `preview_len` characters
     pub fn body_preview(&self, preview_len: usize) -> Option<Cow<'x, str>> {
         if !self.text_body.is_empty() {
             preview_text(self.body_text(0)?, preview_len).into()
@@ -393,6 +896,17 @@ impl<'x> Message<'x> {
         }
     }
 
+    /// Returns a preview of the message body of at most `max_bytes` bytes
+    pub fn body_preview_bytes(&self, max_bytes: usize) -> Option<Cow<'x, str>> {
+        if !self.text_body.is_empty() {
+            preview_text_bytes(self.body_text(0)?, max_bytes).into()
+        } else if !self.html_body.is_empty() {
+            preview_html_bytes(self.body_html(0)?, max_bytes).into()
+        } else {
+            None
+        }
+    }
+
     /// Returns a message body part as text/plain
     pub fn body_html(&'x self, pos: usize) -> Option<Cow<'x, str>> {
         let part = self.parts.get(*self.html_body.get(pos)?)?;
@@ -433,6 +947,77 @@ impl<'x> Message<'x> {
         self.parts.get(*self.attachments.get(pos)?)
     }
 
+    /// Returns the first attachment whose name, as returned by
+    /// `attachment_name`, matches `name` case-insensitively. When several
+    /// attachments share a name, the first one in the message is returned.
+    pub fn attachment_by_name(&self, name: &str) -> Option<&MessagePart<'x>> {
+        self.attachments
+            .iter()
+            .filter_map(|&part_id| self.parts.get(part_id))
+            .find(|part| {
+                part.attachment_name()
+                    .is_some_and(|part_name| part_name.eq_ignore_ascii_case(name))
+            })
+    }
+
+    /// Returns an iterator over the parts whose Content-Type matches `type_`
+    /// and `subtype` case-insensitively. A `subtype` of `None` matches any
+    /// subtype, so `parts_by_type("image", None)` selects all `image/*`
+    /// parts.
+    pub fn parts_by_type<'a>(
+        &'a self,
+        type_: &'a str,
+        subtype: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a MessagePart<'x>> + 'a {
+        self.parts.iter().filter(move |part| match subtype {
+            Some(subtype) => part.is_content_type(type_, subtype),
+            None => part
+                .content_type()
+                .is_some_and(|ct| ct.ctype().eq_ignore_ascii_case(type_)),
+        })
+    }
+
+    /// Returns an iterator over the `text/calendar` parts of the message,
+    /// together with the iCalendar method (e.g. `REQUEST`, `REPLY` or
+    /// `CANCEL`) declared in their Content-Type.
+    pub fn calendar_parts(&self) -> impl Iterator<Item = (&MessagePart<'x>, Option<&str>)> {
+        self.parts
+            .iter()
+            .filter(|part| part.is_content_type("text", "calendar"))
+            .map(|part| {
+                (
+                    part,
+                    part.content_type().and_then(|ct| ct.attribute("method")),
+                )
+            })
+    }
+
+    /// Returns the first Microsoft TNEF (`winmail.dat`) part of the message,
+    /// see `MessagePart::is_tnef`
+    pub fn tnef_part(&self) -> Option<&MessagePart<'x>> {
+        self.parts.iter().find(|part| part.is_tnef())
+    }
+
+    /// Returns `true` when the message has a Microsoft TNEF (`winmail.dat`)
+    /// part
+    pub fn has_tnef(&self) -> bool {
+        self.tnef_part().is_some()
+    }
+
+    /// Returns `true` when any text, HTML, binary or nested message part has
+    /// non-empty contents
+    pub fn has_body(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| !part.is_multipart() && !part.is_empty())
+    }
+
+    /// Returns `true` when the message consists of a header block only, with
+    /// no MIME parts and an empty body
+    pub fn is_headers_only(&self) -> bool {
+        self.parts.len() <= 1 && !self.has_body()
+    }
+
     /// Returns the number of plain text body parts
     pub fn text_body_count(&self) -> usize {
         self.text_body.len()
@@ -463,7 +1048,124 @@ impl<'x> Message<'x> {
         AttachmentIterator::new(self)
     }
 
-    /// Returns an owned version of the message
+    /// Returns the sum of the decoded lengths of the attachments, as returned
+    /// by `MessagePart::len`. Attached messages count with their raw length.
+    pub fn attachments_total_size(&self) -> usize {
+        self.attachments
+            .iter()
+            .filter_map(|&part_id| self.parts.get(part_id))
+            .map(|part| part.len())
+            .sum()
+    }
+
+    /// Returns the attachment with the largest decoded length, the first one
+    /// in the message when several have the same length.
+    pub fn largest_attachment(&self) -> Option<&MessagePart<'x>> {
+        largest_part(
+            self.attachments
+                .iter()
+                .filter_map(|&part_id| self.parts.get(part_id)),
+        )
+    }
+
+    /// Same as `attachments_total_size`, but attached messages are replaced
+    /// by their own attachments, at any depth.
+    pub fn attachments_total_size_recursive(&self) -> usize {
+        let mut parts = Vec::new();
+        self.collect_attachments_recursive(&mut parts);
+        parts.into_iter().map(|part| part.len()).sum()
+    }
+
+    /// Same as `largest_attachment`, but attached messages are replaced by
+    /// their own attachments, at any depth.
+    pub fn largest_attachment_recursive(&self) -> Option<&MessagePart<'x>> {
+        let mut parts = Vec::new();
+        self.collect_attachments_recursive(&mut parts);
+        largest_part(parts.into_iter())
+    }
+
+    fn collect_attachments_recursive<'a>(&'a self, parts: &mut Vec<&'a MessagePart<'x>>) {
+        for part in self
+            .attachments
+            .iter()
+            .filter_map(|&part_id| self.parts.get(part_id))
+        {
+            if let PartType::Message(message) = &part.body {
+                message.collect_attachments_recursive(parts);
+            } else {
+                parts.push(part);
+            }
+        }
+    }
+
+    /// Returns the original message of a chain of forwards, descending
+    /// through the `message/rfc822` parts of messages that only wrap a
+    /// nested message. A message is a wrapper when the nested message is its
+    /// only part with content, other than multipart containers and blank
+    /// text parts. Returns `self` when the message is not a wrapper.
+    pub fn innermost_message(&self) -> &Message<'x> {
+        let mut message = self;
+        while let Some(nested) = message.wrapped_message() {
+            message = nested;
+        }
+        message
+    }
+
+    fn wrapped_message(&self) -> Option<&Message<'x>> {
+        let mut nested = None;
+        for part in &self.parts {
+            match &part.body {
+                PartType::Message(message) if nested.is_none() => nested = Some(message),
+                PartType::Text(text) | PartType::Html(text) if text.trim().is_empty() => (),
+                PartType::Binary(bytes) | PartType::InlineBinary(bytes) if bytes.is_empty() => (),
+                PartType::Multipart(_) => (),
+                _ => return None,
+            }
+        }
+        nested
+    }
+
+    /// Returns the `image/*` parts with a Content-ID that are displayed inline,
+    /// either because their Content-Disposition is `inline` or because an
+    /// HTML part references them with a `cid:` URL (RFC 2392).
+    pub fn inline_images(&self) -> Vec<InlineImage<'_>> {
+        let mut references = Vec::new();
+        for part in &self.parts {
+            if let PartType::Html(html) = &part.body {
+                cid_references(html, &mut references);
+            }
+        }
+
+        self.parts
+            .iter()
+            .enumerate()
+            .filter_map(|(part_id, part)| {
+                let content_type = part
+                    .content_type()
+                    .filter(|ct| ct.ctype().eq_ignore_ascii_case("image"))?;
+                let cid = part.content_id()?;
+                if part.content_disposition().is_some_and(|cd| cd.is_inline())
+                    || references.iter().any(|reference| reference == cid)
+                {
+                    Some(InlineImage {
+                        part_id,
+                        cid,
+                        content_type: match content_type.subtype() {
+                            Some(subtype) => format!("{}/{}", content_type.ctype(), subtype),
+                            None => content_type.ctype().to_string(),
+                        },
+                        contents: part.contents(),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns an owned version of the message. The whole raw buffer is
+    /// copied, so header and part offsets remain valid against
+    /// `raw_bytes()`.
     pub fn into_owned(self) -> Message<'static> {
         Message {
             html_body: self.html_body,
@@ -471,10 +1173,177 @@ impl<'x> Message<'x> {
             attachments: self.attachments,
             parts: self.parts.into_iter().map(|p| p.into_owned()).collect(),
             raw_message: self.raw_message.into_owned().into(),
+            is_truncated: self.is_truncated,
+            is_incomplete: self.is_incomplete,
+        }
+    }
+}
+
+fn display_value(name: &HeaderName<'_>, value: &HeaderValue<'_>) -> String {
+    match value {
+        HeaderValue::Address(address) => address.to_string(),
+        HeaderValue::Text(text) if is_id_header(name) => format!("<{text}>"),
+        HeaderValue::Text(text) => text.to_string(),
+        HeaderValue::TextList(list) if is_id_header(name) => list
+            .iter()
+            .map(|id| format!("<{id}>"))
+            .collect::<Vec<_>>()
+            .join(" "),
+        HeaderValue::TextList(list) => list.join(", "),
+        HeaderValue::DateTime(date) => date.to_rfc3339(),
+        HeaderValue::ContentType(content_type) => {
+            let mut result = content_type.ctype().to_string();
+            if let Some(subtype) = content_type.subtype() {
+                result.push('/');
+                result.push_str(subtype);
+            }
+            for (name, value) in content_type.attributes().unwrap_or_default() {
+                result.push_str(&format!(
+                    "; {name}=\"{}\"",
+                    value.replace('\\', "\\\\").replace('"', "\\\"")
+                ));
+            }
+            result
+        }
+        HeaderValue::Received(received) => received.to_header_string().replace("\r\n\t", " "),
+        HeaderValue::Empty => String::new(),
+    }
+}
+
+fn is_resent_header(name: &HeaderName<'_>) -> bool {
+    matches!(
+        name,
+        HeaderName::ResentDate
+            | HeaderName::ResentFrom
+            | HeaderName::ResentSender
+            | HeaderName::ResentTo
+            | HeaderName::ResentCc
+            | HeaderName::ResentBcc
+            | HeaderName::ResentMessageId
+    )
+}
+
+fn is_id_header(name: &HeaderName<'_>) -> bool {
+    matches!(
+        name,
+        HeaderName::MessageId
+            | HeaderName::InReplyTo
+            | HeaderName::References
+            | HeaderName::ResentMessageId
+            | HeaderName::ContentId
+    )
+}
+
+// Collects the Content-IDs referenced by "cid:" URLs in an HTML body
+fn cid_references(html: &str, references: &mut Vec<String>) {
+    let lowercase_html = html.to_ascii_lowercase();
+    for (pos, _) in lowercase_html.match_indices("cid:") {
+        let url = &html[pos + 4..];
+        let url = &url[..url
+            .find(|ch: char| matches!(ch, '"' | '\'' | '<' | '>' | '(' | ')') || ch.is_whitespace())
+            .unwrap_or(url.len())];
+        if !url.is_empty() {
+            references.push(match decode_hex(url.as_bytes()) {
+                (true, bytes) => String::from_utf8(bytes).unwrap_or_else(|_| url.to_string()),
+                (false, _) => url.to_string(),
+            });
         }
     }
 }
 
+// Maps the ISO 639-3 codes used by whatlang to their ISO 639-1 equivalent,
+// as BCP 47 requires the shortest available code
+#[cfg(feature = "lang_detect")]
+fn language_tag(lang: whatlang::Lang) -> &'static str {
+    use whatlang::Lang;
+
+    match lang {
+        Lang::Afr => "af",
+        Lang::Aka => "ak",
+        Lang::Amh => "am",
+        Lang::Ara => "ar",
+        Lang::Aze => "az",
+        Lang::Bel => "be",
+        Lang::Ben => "bn",
+        Lang::Bul => "bg",
+        Lang::Cat => "ca",
+        Lang::Ces => "cs",
+        Lang::Cmn => "zh",
+        Lang::Dan => "da",
+        Lang::Deu => "de",
+        Lang::Ell => "el",
+        Lang::Eng => "en",
+        Lang::Epo => "eo",
+        Lang::Est => "et",
+        Lang::Fin => "fi",
+        Lang::Fra => "fr",
+        Lang::Guj => "gu",
+        Lang::Heb => "he",
+        Lang::Hin => "hi",
+        Lang::Hrv => "hr",
+        Lang::Hun => "hu",
+        Lang::Hye => "hy",
+        Lang::Ind => "id",
+        Lang::Ita => "it",
+        Lang::Jav => "jv",
+        Lang::Jpn => "ja",
+        Lang::Kan => "kn",
+        Lang::Kat => "ka",
+        Lang::Khm => "km",
+        Lang::Kor => "ko",
+        Lang::Lat => "la",
+        Lang::Lav => "lv",
+        Lang::Lit => "lt",
+        Lang::Mal => "ml",
+        Lang::Mar => "mr",
+        Lang::Mkd => "mk",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Nld => "nl",
+        Lang::Nob => "nb",
+        Lang::Ori => "or",
+        Lang::Pan => "pa",
+        Lang::Pes => "fa",
+        Lang::Pol => "pl",
+        Lang::Por => "pt",
+        Lang::Ron => "ro",
+        Lang::Rus => "ru",
+        Lang::Sin => "si",
+        Lang::Slk => "sk",
+        Lang::Slv => "sl",
+        Lang::Sna => "sn",
+        Lang::Spa => "es",
+        Lang::Srp => "sr",
+        Lang::Swe => "sv",
+        Lang::Tam => "ta",
+        Lang::Tel => "te",
+        Lang::Tgl => "tl",
+        Lang::Tha => "th",
+        Lang::Tuk => "tk",
+        Lang::Tur => "tr",
+        Lang::Ukr => "uk",
+        Lang::Urd => "ur",
+        Lang::Uzb => "uz",
+        Lang::Vie => "vi",
+        Lang::Yid => "yi",
+        Lang::Zul => "zu",
+        #[allow(unreachable_patterns)]
+        lang => lang.code(),
+    }
+}
+
+fn largest_part<'a, 'x>(
+    parts: impl Iterator<Item = &'a MessagePart<'x>>,
+) -> Option<&'a MessagePart<'x>> {
+    parts.reduce(|largest, part| {
+        if part.len() > largest.len() {
+            part
+        } else {
+            largest
+        }
+    })
+}
+
 impl<'x> TryInto<Message<'x>> for &'x [u8] {
     type Error = ();
 
@@ -13,4 +13,6 @@ pub mod address;
 pub mod body;
 pub mod builder;
 pub mod header;
+pub mod lazy;
 pub mod message;
+pub mod partial;
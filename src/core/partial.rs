@@ -0,0 +1,251 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use crate::{Header, HeaderName, Message, MessageParser, PartialReassembler};
+
+// Fragment numbers and totals are read from the message, anything above this
+// is rejected to bound the list of missing fragments.
+const MAX_FRAGMENTS: u32 = 10_000;
+
+impl PartialReassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a message containing a `message/partial` fragment. Returns `false`
+    /// if the message is not a fragment, belongs to a different message than
+    /// the fragments added so far or declares more than 10,000 fragments.
+    pub fn add(&mut self, message: &Message<'_>) -> bool {
+        let Some((part, partial)) = message
+            .parts
+            .iter()
+            .find_map(|part| part.message_partial().map(|partial| (part, partial)))
+        else {
+            return false;
+        };
+        if partial.number == 0
+            || partial.number > MAX_FRAGMENTS
+            || partial.total.is_some_and(|total| total > MAX_FRAGMENTS)
+        {
+            return false;
+        }
+
+        match &self.id {
+            Some(id) if id != partial.id.as_ref() => return false,
+            Some(_) => (),
+            None => self.id = Some(partial.id.to_string()),
+        }
+        if partial.total.is_some() {
+            self.total = partial.total;
+        }
+
+        if partial.number == 1 {
+            // Keep the enclosing headers of the first fragment, except those
+            // that are replaced by the enclosed message's headers.
+            self.headers.clear();
+            for header in &part.headers {
+                if !is_enclosed_header(&header.name) {
+                    self.headers
+                        .extend_from_slice(raw_header(message.raw_message.as_ref(), header));
+                }
+            }
+        }
+        self.fragments
+            .insert(partial.number, part.contents().to_vec());
+
+        true
+    }
+
+    /// Returns the identifier of the fragmented message
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the fragment numbers that have not been added yet. Until the
+    /// fragment declaring the total is received, only the gaps below the
+    /// highest fragment number can be reported.
+    pub fn missing(&self) -> Vec<u32> {
+        let last = self
+            .total
+            .or_else(|| self.fragments.keys().next_back().copied())
+            .unwrap_or(0);
+        (1..=last)
+            .filter(|number| !self.fragments.contains_key(number))
+            .collect()
+    }
+
+    /// Returns `true` when all the fragments have been added
+    pub fn is_complete(&self) -> bool {
+        self.total.is_some_and(|total| {
+            self.fragments.len() == total as usize
+                && self.fragments.keys().next_back() == Some(&total)
+        })
+    }
+
+    /// Returns the raw bytes of the original message, or `None` if fragments
+    /// are missing.
+    ///
+    /// The headers of the first enclosing message are merged with the
+    /// enclosed headers: `Content-*`, `Subject`, `Message-ID`, `Encrypted`
+    /// and `MIME-Version` are taken from the enclosed message, all other
+    /// enclosed headers are dropped.
+    pub fn reassemble(&self) -> Option<Vec<u8>> {
+        if !self.is_complete() {
+            return None;
+        }
+
+        let mut fragments = self.fragments.values();
+        let first = fragments.next()?;
+        let enclosed = MessageParser::new().parse_headers(first.as_slice());
+        let (enclosed_headers, offset_body) = enclosed.as_ref().map_or((&[][..], 0), |message| {
+            (
+                message.parts[0].headers.as_slice(),
+                message.parts[0].offset_body,
+            )
+        });
+
+        let mut raw_message = Vec::with_capacity(
+            self.headers.len() + self.fragments.values().map(|f| f.len()).sum::<usize>(),
+        );
+        raw_message.extend_from_slice(&self.headers);
+        for header in enclosed_headers {
+            if is_enclosed_header(&header.name) {
+                raw_message.extend_from_slice(raw_header(first, header));
+            }
+        }
+        raw_message.extend_from_slice(
+            &first[enclosed_headers
+                .last()
+                .map_or(offset_body, |header| header.offset_end)..],
+        );
+        for fragment in fragments {
+            raw_message.extend_from_slice(fragment);
+        }
+
+        Some(raw_message)
+    }
+}
+
+fn is_enclosed_header(name: &HeaderName<'_>) -> bool {
+    match name {
+        HeaderName::Subject | HeaderName::MessageId | HeaderName::MimeVersion => true,
+        HeaderName::Other(name) => {
+            name.eq_ignore_ascii_case("Encrypted")
+                || name
+                    .get(..8)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case("Content-"))
+        }
+        _ => name.is_mime_header(),
+    }
+}
+
+fn raw_header<'x>(raw_message: &'x [u8], header: &Header<'_>) -> &'x [u8] {
+    raw_message
+        .get(header.offset_field..header.offset_end)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{MessageParser, PartialReassembler};
+
+    #[test]
+    fn reassemble_partial() {
+        let original = concat!(
+            "From: sender@example.com\n",
+            "To: rcpt@example.com\n",
+            "Subject: Large message\n",
+            "Message-ID: <original@example.com>\n",
+            "MIME-Version: 1.0\n",
+            "Content-Type: text/plain; charset=us-ascii\n",
+            "\n",
+            "First line\n",
+            "Second line\n",
+            "Third line\n"
+        );
+        let fragment = |number: u32, outer_total: &str, body: &str| {
+            format!(
+                concat!(
+                    "From: sender@example.com\n",
+                    "To: rcpt@example.com\n",
+                    "Subject: Large message (part {0} of 3)\n",
+                    "Message-ID: <fragment{0}@example.com>\n",
+                    "MIME-Version: 1.0\n",
+                    "Content-Type: message/partial; id=\"abc@example.com\"; number={0}{1}\n",
+                    "\n",
+                    "{2}"
+                ),
+                number, outer_total, body
+            )
+        };
+        let fragments = [
+            fragment(
+                1,
+                "",
+                concat!(
+                    "X-Dropped: enclosed header\n",
+                    "Subject: Large message\n",
+                    "Message-ID: <original@example.com>\n",
+                    "MIME-Version: 1.0\n",
+                    "Content-Type: text/plain; charset=us-ascii\n",
+                    "\n",
+                    "First line\n"
+                ),
+            ),
+            fragment(2, "", "Second line\n"),
+            fragment(3, "; total=3", "Third line\n"),
+        ];
+        let parser = MessageParser::default();
+        let fragments = fragments
+            .iter()
+            .map(|fragment| parser.parse(fragment.as_bytes()).unwrap())
+            .collect::<Vec<_>>();
+
+        let mut reassembler = PartialReassembler::new();
+        assert!(reassembler.add(&fragments[1]));
+        assert_eq!(reassembler.id(), Some("abc@example.com"));
+        assert_eq!(reassembler.missing(), vec![1]);
+        assert!(!reassembler.is_complete());
+
+        assert!(reassembler.add(&fragments[2]));
+        assert_eq!(reassembler.missing(), vec![1]);
+        assert_eq!(reassembler.reassemble(), None);
+
+        assert!(reassembler.add(&fragments[0]));
+        assert!(reassembler.missing().is_empty());
+        assert!(reassembler.is_complete());
+
+        let raw_message = reassembler.reassemble().unwrap();
+        assert_eq!(std::str::from_utf8(&raw_message).unwrap(), original);
+        assert_eq!(
+            parser.parse(&raw_message).unwrap(),
+            parser.parse(original).unwrap()
+        );
+
+        // Fragments of other messages and regular messages are rejected
+        let other = fragment(1, "", "Other\n").replace("abc@example.com", "xyz@example.com");
+        assert!(!reassembler.add(&parser.parse(other.as_bytes()).unwrap()));
+        assert!(!reassembler.add(&parser.parse(original).unwrap()));
+
+        // Fragment numbers and totals are bounded
+        let mut reassembler = PartialReassembler::new();
+        let huge = fragment(2, "; total=4294967295", "Second line\n");
+        assert!(!reassembler.add(&parser.parse(huge.as_bytes()).unwrap()));
+        let huge = fragment(4000000000, "", "Second line\n");
+        assert!(!reassembler.add(&parser.parse(huge.as_bytes()).unwrap()));
+        assert!(reassembler.missing().is_empty());
+        assert!(!reassembler.is_complete());
+
+        assert!(reassembler.add(&fragments[2]));
+        assert_eq!(reassembler.missing(), vec![1, 2]);
+        assert!(!reassembler.is_complete());
+    }
+}
@@ -18,6 +18,63 @@ pub fn base64_decode(bytes: &[u8]) -> Option<Vec<u8>> {
     base64_decode_stream(bytes.iter(), bytes.len(), u8::MAX)
 }
 
+/// Decodes base64 skipping any characters outside the base64 alphabet
+/// instead of failing, returns the decoded bytes and the number of characters
+/// skipped. A truncated final quantum is decoded as if it was padded.
+///
+/// The message parser uses it to recover the contents of base64 parts that
+/// fail to decode, which are then flagged with `is_encoding_problem`.
+pub fn base64_decode_lenient(bytes: &[u8]) -> (Vec<u8>, usize) {
+    let mut chunk: u32 = 0;
+    let mut byte_count: u8 = 0;
+    let mut invalid_count = 0;
+
+    let mut buf = Vec::with_capacity(bytes.len() / 4 * 3);
+
+    for &ch in bytes {
+        let val = BASE64_MAP[byte_count as usize][ch as usize];
+
+        if val < 0x01ffffff {
+            byte_count = (byte_count + 1) & 3;
+
+            if byte_count == 1 {
+                chunk = val;
+            } else {
+                chunk |= val;
+
+                if byte_count == 0 {
+                    buf.extend_from_slice(&chunk.to_le_bytes()[0..3]);
+                }
+            }
+        } else {
+            match ch {
+                b'=' => match byte_count {
+                    1 | 2 => {
+                        buf.push(chunk.to_le_bytes()[0]);
+                        byte_count = 0;
+                    }
+                    3 => {
+                        buf.extend_from_slice(&chunk.to_le_bytes()[0..2]);
+                        byte_count = 0;
+                    }
+                    _ => (),
+                },
+                b' ' | b'\t' | b'\r' | b'\n' => (),
+                _ => invalid_count += 1,
+            }
+        }
+    }
+
+    // Keep the bytes of a truncated final quantum
+    match byte_count {
+        2 => buf.push(chunk.to_le_bytes()[0]),
+        3 => buf.extend_from_slice(&chunk.to_le_bytes()[0..2]),
+        _ => (),
+    }
+
+    (buf, invalid_count)
+}
+
 pub fn base64_decode_stream<'x>(
     stream: impl Iterator<Item = &'x u8>,
     stream_len: usize,
@@ -166,7 +223,7 @@ impl<'x> MessageStream<'x> {
                     b' ' | b'\t' | b'\r' => (),
                     b'-' => {
                         if last_ch == b'-' {
-                            return if !boundary.is_empty() && self.try_skip(boundary) {
+                            return if !boundary.is_empty() && self.try_skip_boundary(boundary) {
                                 buf.shrink_to_fit();
                                 (
                                     if before_last_ch == b'\n' {
@@ -195,7 +252,7 @@ impl<'x> MessageStream<'x> {
 
         buf.shrink_to_fit();
         (
-            if boundary.is_empty() {
+            if boundary.is_empty() || self.is_prefix {
                 self.offset()
             } else {
                 self.restore();
@@ -465,6 +522,27 @@ pub static BASE64_MAP: &[&[u32]] = &[
 mod tests {
     use crate::parsers::MessageStream;
 
+    #[test]
+    fn decode_base64_lenient() {
+        for (encoded_str, expected_result, expected_invalid) in [
+            ("VGVzdA==", "Test", 0),
+            ("VG!VzdA==", "Test", 1),
+            ("w6HDq!cOtw7PDug=", "áéíóú", 1),
+            ("w6HD\u{0}qcOt\u{7f}w7PD\u{1b}ug==", "áéíóú", 3),
+            ("QXJlIHlv\ndSBh*IFNo\naW1hbm8?\n", "Are you a Shimano", 2),
+            ("cmáé", "r", 4),
+            ("", "", 0),
+        ] {
+            let (decoded, invalid) = super::base64_decode_lenient(encoded_str.as_bytes());
+            assert_eq!(
+                decoded,
+                expected_result.as_bytes(),
+                "Failed for {encoded_str:?}"
+            );
+            assert_eq!(invalid, expected_invalid, "Failed for {encoded_str:?}");
+        }
+    }
+
     #[test]
     fn decode_base64() {
         for (encoded_str, expected_result) in [
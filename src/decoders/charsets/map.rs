@@ -17,6 +17,91 @@ use super::{
 };
 
 pub fn charset_decoder(charset: &[u8]) -> Option<DecoderFnc> {
+    charset_hash(charset).map(|hash| {
+        #[cfg(feature = "ludicrous_mode")]
+        unsafe {
+            *FNC_MAP.get_unchecked(hash)
+        }
+        #[cfg(not(feature = "ludicrous_mode"))]
+        FNC_MAP[hash]
+    })
+}
+
+/// Returns the canonical IANA name of a charset.
+pub fn charset_name(charset: &[u8]) -> Option<&'static str> {
+    charset_hash(charset)
+        .map(|hash| {
+            #[cfg(feature = "ludicrous_mode")]
+            unsafe {
+                *NAME_MAP.get_unchecked(hash)
+            }
+            #[cfg(not(feature = "ludicrous_mode"))]
+            NAME_MAP[hash]
+        })
+        .or_else(|| {
+            // UTF-8 and US-ASCII are decoded natively and have no decoder entry
+            let charset = charset.strip_prefix(b"cs").unwrap_or(charset);
+            if charset.eq_ignore_ascii_case(b"utf-8") || charset.eq_ignore_ascii_case(b"utf8") {
+                Some("UTF-8")
+            } else if charset.eq_ignore_ascii_case(b"us-ascii")
+                || charset.eq_ignore_ascii_case(b"ascii")
+            {
+                Some("US-ASCII")
+            } else {
+                None
+            }
+        })
+}
+
+fn charset_hash(charset: &[u8]) -> Option<usize> {
+    charset_lookup(charset).or_else(|| {
+        let mut alias = [0u8; 45];
+        let len = charset_alias(charset, &mut alias)?;
+        charset_lookup(&alias[..len])
+    })
+}
+
+/// Rewrites common mislabels such as `cp1252`, `windows1252`, `iso8859-1`
+/// or `latin-1` to a spelling present in the charset map.
+fn charset_alias(charset: &[u8], alias: &mut [u8; 45]) -> Option<usize> {
+    if !(2..=45).contains(&charset.len()) {
+        return None;
+    }
+
+    let mut name = [0u8; 45];
+    for (dst, src) in name.iter_mut().zip(charset) {
+        *dst = src.to_ascii_lowercase();
+    }
+    let name = &name[..charset.len()];
+    let skip_sep = |s: &[u8]| -> usize { usize::from(matches!(s.first(), Some(b'-' | b'_'))) };
+
+    let (prefix, number): (&[u8], &[u8]) = if let Some(rest) = name.strip_prefix(b"iso") {
+        let rest = rest[skip_sep(rest)..].strip_prefix(b"8859")?;
+        (b"iso-8859-", &rest[skip_sep(rest)..])
+    } else if let Some(rest) = name
+        .strip_prefix(b"cp")
+        .or_else(|| name.strip_prefix(b"windows"))
+    {
+        (b"windows-", &rest[skip_sep(rest)..])
+    } else if let Some(rest) = name.strip_prefix(b"latin") {
+        (b"latin", &rest[skip_sep(rest)..])
+    } else {
+        return None;
+    };
+
+    if number.is_empty()
+        || !number.iter().all(u8::is_ascii_digit)
+        || prefix.len() + number.len() > alias.len()
+    {
+        return None;
+    }
+
+    alias[..prefix.len()].copy_from_slice(prefix);
+    alias[prefix.len()..prefix.len() + number.len()].copy_from_slice(number);
+    Some(prefix.len() + number.len())
+}
+
+fn charset_lookup(charset: &[u8]) -> Option<usize> {
     if (2..=45).contains(&charset.len()) {
         let mut l_charset = [0u8; 45];
         let mut hash = charset.len();
@@ -71,14 +156,7 @@ pub fn charset_decoder(charset: &[u8]) -> Option<DecoderFnc> {
             };
 
             if l_charset[..charset.len()].eq(ch_map) {
-                return Some({
-                    #[cfg(feature = "ludicrous_mode")]
-                    unsafe {
-                        *FNC_MAP.get_unchecked(hash)
-                    }
-                    #[cfg(not(feature = "ludicrous_mode"))]
-                    FNC_MAP[hash]
-                });
+                return Some(hash);
             }
         }
     }
@@ -1204,9 +1282,557 @@ static FNC_MAP: &[for<'x> fn(&'x [u8]) -> String; 545] = &[
     decoder_ibm_850,
 ];
 
+static NAME_MAP: &[&str; 545] = &[
+    "",
+    "",
+    "ISO-8859-1",
+    "",
+    "",
+    "",
+    "ISO-8859-1",
+    "ISO-8859-14",
+    "",
+    "",
+    "",
+    "ISO-8859-14",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-4",
+    "",
+    "",
+    "GBK",
+    "ISO-8859-4",
+    "",
+    "GBK",
+    "",
+    "",
+    "",
+    "ISO-8859-9",
+    "",
+    "",
+    "",
+    "ISO-8859-9",
+    "",
+    "",
+    "",
+    "ISO-8859-7",
+    "ISO-8859-7",
+    "",
+    "",
+    "",
+    "",
+    "Big5",
+    "ISO-8859-10",
+    "",
+    "",
+    "ISO-8859-1",
+    "ISO-8859-10",
+    "",
+    "",
+    "Big5",
+    "IBM866",
+    "",
+    "",
+    "",
+    "",
+    "Shift_JIS",
+    "",
+    "ISO-8859-3",
+    "",
+    "",
+    "IBM850",
+    "ISO-8859-3",
+    "",
+    "",
+    "",
+    "ISO-8859-9",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-14",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "EUC-KR",
+    "ISO-8859-15",
+    "",
+    "",
+    "GBK",
+    "",
+    "ISO-8859-2",
+    "",
+    "Shift_JIS",
+    "ISO-8859-5",
+    "ISO-8859-2",
+    "",
+    "IBM866",
+    "",
+    "ISO-8859-2",
+    "EUC-JP",
+    "",
+    "ISO-8859-5",
+    "",
+    "",
+    "KOI8-R",
+    "",
+    "",
+    "",
+    "ISO-8859-1",
+    "TIS-620",
+    "",
+    "ISO-8859-16",
+    "",
+    "ISO-8859-8",
+    "ISO-8859-16",
+    "ISO-8859-16",
+    "IBM850",
+    "",
+    "ISO-8859-8",
+    "ISO-8859-14",
+    "TIS-620",
+    "ISO-8859-7",
+    "",
+    "ISO-8859-8",
+    "ISO-8859-14",
+    "GB18030",
+    "",
+    "",
+    "",
+    "ISO-8859-14",
+    "KOI8-R",
+    "",
+    "",
+    "ISO-8859-4",
+    "ISO-8859-15",
+    "",
+    "windows-1251",
+    "",
+    "ISO-8859-4",
+    "ISO-8859-15",
+    "EUC-KR",
+    "windows-1258",
+    "",
+    "ISO-8859-5",
+    "ISO-8859-8",
+    "",
+    "",
+    "",
+    "ISO-8859-4",
+    "ISO-8859-16",
+    "",
+    "windows-1254",
+    "EUC-KR",
+    "ISO-8859-14",
+    "ISO-8859-16",
+    "windows-874",
+    "",
+    "",
+    "ISO-8859-5",
+    "ISO-8859-10",
+    "windows-1251",
+    "windows-1255",
+    "",
+    "",
+    "ISO-8859-13",
+    "windows-1258",
+    "IBM866",
+    "",
+    "ISO-8859-3",
+    "ISO-8859-13",
+    "",
+    "",
+    "",
+    "ISO-8859-3",
+    "",
+    "windows-1254",
+    "windows-1256",
+    "",
+    "ISO-8859-9",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-9",
+    "GB18030",
+    "windows-1255",
+    "windows-1250",
+    "",
+    "ISO-8859-6",
+    "",
+    "",
+    "windows-1253",
+    "",
+    "",
+    "ISO-8859-10",
+    "",
+    "TIS-620",
+    "",
+    "ISO-8859-1",
+    "windows-874",
+    "windows-1256",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-7",
+    "GBK",
+    "windows-1250",
+    "",
+    "",
+    "ISO-8859-1",
+    "ISO-8859-1",
+    "windows-1253",
+    "windows-1252",
+    "EUC-KR",
+    "ISO-8859-3",
+    "ISO-8859-6",
+    "",
+    "ISO-8859-5",
+    "",
+    "ISO-8859-7",
+    "KOI8-U",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-4",
+    "",
+    "",
+    "GB18030",
+    "",
+    "",
+    "",
+    "windows-1257",
+    "",
+    "",
+    "ISO-8859-9",
+    "windows-1252",
+    "macintosh",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-6",
+    "ISO-8859-10",
+    "",
+    "UTF-16LE",
+    "",
+    "",
+    "",
+    "windows-1257",
+    "",
+    "UTF-16LE",
+    "",
+    "UTF-16",
+    "",
+    "UTF-16BE",
+    "",
+    "ISO-8859-2",
+    "ISO-8859-3",
+    "UTF-16",
+    "ISO-8859-7",
+    "UTF-16BE",
+    "ISO-8859-10",
+    "ISO-8859-8",
+    "",
+    "",
+    "",
+    "EUC-JP",
+    "ISO-2022-JP",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-6",
+    "",
+    "",
+    "",
+    "ISO-8859-16",
+    "UTF-7",
+    "",
+    "ISO-8859-6",
+    "",
+    "ISO-8859-2",
+    "ISO-8859-2",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "Shift_JIS",
+    "",
+    "ISO-8859-7",
+    "ISO-8859-1",
+    "",
+    "",
+    "",
+    "ISO-8859-7",
+    "IBM866",
+    "",
+    "",
+    "",
+    "GBK",
+    "macintosh",
+    "",
+    "",
+    "",
+    "UTF-7",
+    "IBM850",
+    "",
+    "",
+    "",
+    "ISO-8859-6",
+    "ISO-2022-JP",
+    "",
+    "",
+    "EUC-JP",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "KOI8-U",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "macintosh",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "ISO-8859-6",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "",
+    "IBM850",
+];
+
 #[cfg(test)]
 mod tests {
-    use super::{charset_decoder, CH_MAP};
+    use super::{charset_decoder, charset_name, CH_MAP};
 
     #[test]
     fn decoder_charset() {
@@ -1220,4 +1846,48 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn canonical_charset_name() {
+        for input in CH_MAP {
+            if !input.is_empty() {
+                assert!(
+                    charset_name(input).is_some_and(|name| !name.is_empty()),
+                    "Failed for {}",
+                    std::str::from_utf8(input).unwrap()
+                );
+            }
+        }
+
+        for (input, expected) in [
+            ("latin1", Some("ISO-8859-1")),
+            ("ISO-8859-1", Some("ISO-8859-1")),
+            ("iso_8859-15", Some("ISO-8859-15")),
+            ("csWindows1252", Some("windows-1252")),
+            ("Windows-1251", Some("windows-1251")),
+            ("koi8-r", Some("KOI8-R")),
+            ("ms_kanji", Some("Shift_JIS")),
+            ("UTF-8", Some("UTF-8")),
+            ("utf8", Some("UTF-8")),
+            ("us-ascii", Some("US-ASCII")),
+            ("cp1252", Some("windows-1252")),
+            ("CP-1251", Some("windows-1251")),
+            ("windows1252", Some("windows-1252")),
+            ("iso8859-1", Some("ISO-8859-1")),
+            ("ISO8859_15", Some("ISO-8859-15")),
+            ("iso-88592", Some("ISO-8859-2")),
+            ("latin-1", Some("ISO-8859-1")),
+            ("Latin_2", Some("ISO-8859-2")),
+            ("cp", None),
+            ("iso8859", None),
+            ("latin-x", None),
+            ("x-unknown", None),
+        ] {
+            assert_eq!(
+                charset_name(input.as_bytes()),
+                expected,
+                "Failed for {input}"
+            );
+        }
+    }
 }
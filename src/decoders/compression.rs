@@ -0,0 +1,62 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::io::{self, Read};
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+/// Decompresses a body declared with a `Content-Encoding` of `gzip` or
+/// `deflate`. Returns `None` when the encoding is not one of these, and an
+/// error when the body is not valid or decompresses to more than `max_size`
+/// bytes.
+pub fn decompress(
+    content_encoding: &str,
+    bytes: &[u8],
+    max_size: usize,
+) -> Option<io::Result<Vec<u8>>> {
+    let content_encoding = content_encoding.trim();
+    let limit = (max_size as u64).saturating_add(1);
+    let mut result = Vec::with_capacity(bytes.len().saturating_mul(2).min(max_size));
+
+    let result = if content_encoding.eq_ignore_ascii_case("gzip")
+        || content_encoding.eq_ignore_ascii_case("x-gzip")
+    {
+        GzDecoder::new(bytes)
+            .take(limit)
+            .read_to_end(&mut result)
+            .map(|_| result)
+    } else if content_encoding.eq_ignore_ascii_case("deflate") {
+        // "deflate" should be zlib-wrapped, but raw streams are common too
+        ZlibDecoder::new(bytes)
+            .take(limit)
+            .read_to_end(&mut result)
+            .or_else(|_| {
+                result.clear();
+                DeflateDecoder::new(bytes)
+                    .take(limit)
+                    .read_to_end(&mut result)
+            })
+            .map(|_| result)
+    } else {
+        return None;
+    };
+
+    Some(result.and_then(|result| {
+        if result.len() <= max_size {
+            Ok(result)
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "decompressed body exceeds the maximum size",
+            ))
+        }
+    }))
+}
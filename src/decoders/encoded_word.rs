@@ -48,10 +48,8 @@ impl MessageStream<'_> {
                         }
                         state = Rfc2047State::Encoding;
                     }
-                    b'*' => {
-                        if charset_end == charset_start {
-                            charset_end = self.offset() - 1;
-                        }
+                    b'*' if charset_end == charset_start => {
+                        charset_end = self.offset() - 1;
                     }
                     b'\n' => {
                         return None;
@@ -15,6 +15,8 @@ use crate::parsers::MessageStream;
 
 pub mod base64;
 pub mod charsets;
+#[cfg(feature = "compression")]
+pub mod compression;
 pub mod encoded_word;
 pub mod hex;
 pub mod html;
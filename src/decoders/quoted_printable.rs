@@ -164,7 +164,10 @@ impl<'x> MessageStream<'x> {
                 b'\r' => {
                     crlf = b"\r\n".as_ref();
                 }
-                b'-' if !boundary.is_empty() && last_ch == b'-' && self.try_skip(boundary) => {
+                b'-' if !boundary.is_empty()
+                    && last_ch == b'-'
+                    && self.try_skip_boundary(boundary) =>
+                {
                     if before_last_ch == b'\n' {
                         buf.truncate(buf.len() - (crlf.len() + 1));
                     } else {
@@ -222,7 +225,7 @@ impl<'x> MessageStream<'x> {
         }
 
         (
-            if boundary.is_empty() {
+            if boundary.is_empty() || self.is_prefix {
                 self.offset()
             } else {
                 self.restore();
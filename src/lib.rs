@@ -253,17 +253,60 @@ pub mod decoders;
 pub mod mailbox;
 pub mod parsers;
 
-use std::{borrow::Cow, collections::HashMap, hash::Hash, net::IpAddr};
+use std::{borrow::Cow, collections::HashMap, hash::Hash, net::IpAddr, ops::ControlFlow};
 
+use decoders::charsets::DecoderFnc;
 use parsers::MessageStream;
 #[cfg(feature = "serde_support")]
 use serde::{Deserialize, Serialize};
 
 /// RFC5322/RFC822 message parser.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Clone)]
 pub struct MessageParser {
     pub(crate) header_map: HashMap<HeaderName<'static>, HdrParseFnc>,
     pub(crate) def_hdr_parse_fnc: HdrParseFnc,
+    pub(crate) address_comments: bool,
+    pub(crate) lowercase_other_headers: bool,
+    pub(crate) duplicate_header_policy: DuplicateHeaderPolicy,
+    pub(crate) line_ending: LineEnding,
+    pub(crate) mbox_from_line: bool,
+    pub(crate) flatten_address_groups: bool,
+    pub(crate) recurse_content_types: Vec<(String, String)>,
+    pub(crate) decode_composite_cte: bool,
+    pub(crate) raw_dkim_arc: bool,
+    pub(crate) autodetect_cte: bool,
+    pub(crate) tolerant_headers: bool,
+    pub(crate) collapse_header_whitespace: bool,
+    pub(crate) semicolon_address_separator: bool,
+    pub(crate) case_insensitive_boundaries: bool,
+    pub(crate) generate_text_from_html: bool,
+    pub(crate) max_parts: usize,
+    pub(crate) max_decompressed_size: usize,
+    pub(crate) unknown_charset_fallback: Option<DecoderFnc>,
+}
+
+/// Line ending used in decoded text parts, see
+/// `MessageParser::with_normalize_eol`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// Keep the line endings found in the message (default)
+    #[default]
+    Preserve,
+    /// Convert CRLF to LF
+    Lf,
+    /// Convert bare LF to CRLF
+    CrLf,
+}
+
+/// Which occurrence of a repeated MIME header is used to structure a message
+/// part, see `MessageParser::with_duplicate_header_policy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateHeaderPolicy {
+    /// Use the first occurrence
+    First,
+    /// Use the last occurrence (default)
+    #[default]
+    Last,
 }
 
 pub(crate) type HdrParseFnc = for<'x> fn(&mut MessageStream<'x>) -> crate::HeaderValue<'x>;
@@ -285,6 +328,42 @@ pub struct Message<'x> {
 
     #[cfg_attr(feature = "serde_support", serde(skip))]
     pub raw_message: Cow<'x, [u8]>,
+
+    /// Set when parsing stopped after reaching the limit configured with
+    /// `MessageParser::with_max_parts`, the remaining parts were discarded.
+    /// Nested messages are flagged when the limit was reached while parsing
+    /// them.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    pub is_truncated: bool,
+
+    /// Set when `MessageParser::parse_prefix` was only given part of the
+    /// message, or of the part holding this nested message. The parts that
+    /// were cut off are flagged with `MessagePart::is_incomplete`.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    pub is_incomplete: bool,
+}
+
+/// A message whose part bodies are decoded on request, returned by
+/// `MessageParser::parse_lazy`.
+///
+/// The headers and MIME structure are parsed as with `MessageParser::parse`
+/// but the leaf parts of the underlying `Message` hold their raw body, still
+/// transfer encoded and without charset conversion, as a `PartType::Binary`
+/// borrowed from the input, and are flagged with `MessagePart::is_undecoded`.
+/// Use `LazyMessage::contents` and `LazyMessage::text_contents` to decode
+/// them.
+///
+/// **The body accessors of `Message` and `MessagePart` do not decode these
+/// parts:** `Message::body_text`, `Message::body_html`,
+/// `MessagePart::text_contents` and `MessagePart::decoded_bytes` return
+/// `None` for them and `MessagePart::contents` returns the raw body.
+#[derive(Debug, Clone)]
+pub struct LazyMessage<'x> {
+    pub(crate) message: Message<'x>,
+    pub(crate) line_ending: LineEnding,
+    #[cfg(feature = "compression")]
+    pub(crate) max_decompressed_size: usize,
+    pub(crate) unknown_charset_fallback: Option<DecoderFnc>,
 }
 
 /// MIME Message Part
@@ -293,12 +372,33 @@ pub struct Message<'x> {
 pub struct MessagePart<'x> {
     #[cfg_attr(feature = "serde_support", serde(default))]
     pub headers: Vec<Header<'x>>,
+    /// Set when the part could not be decoded as declared and its raw
+    /// contents were kept instead. This includes multipart parts whose
+    /// boundary never appears: rather than an empty `Multipart`, the
+    /// undelimited remainder is stored as a single text part.
     pub is_encoding_problem: bool,
     #[cfg_attr(feature = "serde_support", serde(default))]
     #[cfg_attr(feature = "serde_support", serde(borrow))]
     pub body: PartType<'x>,
     #[cfg_attr(feature = "serde_support", serde(skip))]
     pub encoding: Encoding,
+    /// Set on parts generated by the parser rather than found in the
+    /// message, such as the text bodies created with
+    /// `MessageParser::with_generate_text_from_html`.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    pub is_synthetic: bool,
+    /// Set on the parts cut off at the end of the input of
+    /// `MessageParser::parse_prefix`, that is the last part and the parts
+    /// that contain it, including the parts of nested messages.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    pub is_incomplete: bool,
+    /// Set on the leaf parts of a message returned by
+    /// `MessageParser::parse_lazy`, whose body is still transfer encoded and
+    /// in its original charset. `decoded_bytes` and `text_contents` return
+    /// `None` for them, use `LazyMessage::contents` and
+    /// `LazyMessage::text_contents` instead.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
+    pub is_undecoded: bool,
     pub offset_header: usize,
     pub offset_body: usize,
     pub offset_end: usize,
@@ -367,15 +467,21 @@ impl Default for PartType<'_> {
 
 /// An RFC5322 or RFC2369 internet address.
 #[derive(Debug, PartialEq, Eq, Clone)]
-#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_support", derive(Deserialize))]
 pub struct Addr<'x> {
-    /// The address name including comments
+    /// The address name, including comments unless the parser was built
+    /// with `MessageParser::with_address_comments`
     #[cfg_attr(feature = "serde_support", serde(default))]
     pub name: Option<Cow<'x, str>>,
 
     /// An e-mail address (RFC5322/RFC2369) or URL (RFC2369)
     #[cfg_attr(feature = "serde_support", serde(default))]
     pub address: Option<Cow<'x, str>>,
+
+    /// Comments found around the address, only set when parsing with
+    /// `MessageParser::with_address_comments`
+    #[cfg_attr(feature = "serde_support", serde(default))]
+    pub comment: Option<Cow<'x, str>>,
 }
 
 /// An RFC5322 address group.
@@ -392,13 +498,22 @@ pub struct Group<'x> {
 }
 
 /// A message header.
+///
+/// The offsets point into the raw message as it was parsed, line endings are
+/// not normalized: a message stored with bare LF line endings has offsets
+/// that do not match its CRLF canonicalized form. Use
+/// `Header::canonical_relaxed` to obtain the DKIM canonicalized header.
 #[derive(Debug, PartialEq, Eq, Clone)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
 pub struct Header<'x> {
     pub name: HeaderName<'x>,
     pub value: HeaderValue<'x>,
+    /// Offset of the first byte of the field name
     pub offset_field: usize,
+    /// Offset of the byte following the colon that ends the field name
     pub offset_start: usize,
+    /// Offset of the byte following the CRLF or LF that ends the field,
+    /// including any folded lines
     pub offset_end: usize,
 }
 
@@ -444,6 +559,17 @@ pub enum HeaderName<'x> {
     ListPost,
     ListSubscribe,
     ListUnsubscribe,
+    ContentDuration,
+    DispositionNotificationTo,
+    ReturnReceiptTo,
+    Organization,
+    UserAgent,
+    XMailer,
+    ContentBase,
+    DeliveredTo,
+    XOriginalTo,
+    Face,
+    XFace,
     Other(Cow<'x, str>),
 }
 
@@ -533,6 +659,8 @@ pub struct Received<'x> {
     #[cfg_attr(feature = "serde_support", serde(default))]
     pub for_: Option<Cow<'x, str>>,
     #[cfg_attr(feature = "serde_support", serde(default))]
+    pub for_list: Vec<Cow<'x, str>>,
+    #[cfg_attr(feature = "serde_support", serde(default))]
     pub with: Option<Protocol>,
     #[cfg_attr(feature = "serde_support", serde(default))]
     pub tls_version: Option<TlsVersion>,
@@ -554,6 +682,100 @@ pub struct Received<'x> {
     pub date: Option<DateTime>,
 }
 
+/// An image displayed inline in the HTML body of a message, as returned by
+/// `Message::inline_images`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InlineImage<'x> {
+    /// Id of the image part
+    pub part_id: MessagePartId,
+    /// Content-ID of the part, without angle brackets
+    pub cid: &'x str,
+    /// Media type of the image, such as `image/png`
+    pub content_type: String,
+    /// Decoded contents of the image
+    pub contents: &'x [u8],
+}
+
+/// A mailing list identifier from the `List-ID` header field (RFC 2919), as
+/// returned by `Message::list_id`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListId<'x> {
+    /// Decoded description phrase, such as `Human Name`
+    pub description: Option<&'x str>,
+    /// List identifier, without angle brackets
+    pub id: &'x str,
+}
+
+/// A Microsoft Exchange `Thread-Index` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct ThreadIndex {
+    /// Date the conversation was started
+    pub date: DateTime,
+    /// Conversation GUID
+    pub guid: [u8; 16],
+    /// Child blocks, one for each reply or forward in the conversation
+    #[cfg_attr(feature = "serde_support", serde(default))]
+    pub children: Vec<[u8; 5]>,
+}
+
+/// Signature and encryption indicators of a message, as returned by
+/// `Message::security`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct MessageSecurity<'x> {
+    /// Signature scheme, if the message is signed
+    #[cfg_attr(feature = "serde_support", serde(default))]
+    pub signed: Option<SecurityProtocol>,
+    /// Encryption scheme, if the message is encrypted
+    #[cfg_attr(feature = "serde_support", serde(default))]
+    pub encrypted: Option<SecurityProtocol>,
+    /// Message integrity check algorithm declared by `multipart/signed`
+    #[cfg_attr(feature = "serde_support", serde(default))]
+    pub micalg: Option<Cow<'x, str>>,
+}
+
+/// Message signature or encryption scheme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum SecurityProtocol {
+    SMime,
+    Pgp,
+}
+
+/// Fragment parameters of a `message/partial` part (RFC 2046), as returned
+/// by `MessagePart::message_partial`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct PartialInfo<'x> {
+    /// Identifier shared by all the fragments of a message
+    pub id: Cow<'x, str>,
+    /// Position of this fragment, starting at 1
+    pub number: u32,
+    /// Total number of fragments, required only on the last one
+    #[cfg_attr(feature = "serde_support", serde(default))]
+    pub total: Option<u32>,
+}
+
+/// Reassembles a message split into `message/partial` fragments
+/// (RFC 2046, section 5.2.2).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PartialReassembler {
+    id: Option<String>,
+    total: Option<u32>,
+    headers: Vec<u8>,
+    fragments: std::collections::BTreeMap<u32, Vec<u8>>,
+}
+
+/// Message priority, as returned by `Message::priority`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
 pub enum Host<'x> {
@@ -631,6 +853,16 @@ pub trait MimeHeaders<'x> {
     fn content_language(&self) -> &HeaderValue<'_>;
     /// Returns the Content-Location field
     fn content_location(&self) -> Option<&str>;
+    /// Returns the language tags listed in the Content-Language field
+    fn content_language_tags(&self) -> Vec<&str> {
+        self.content_language()
+            .as_text_list()
+            .unwrap_or_default()
+            .into_iter()
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    }
     /// Returns the attachment name, if any.
     fn attachment_name(&self) -> Option<&str> {
         self.content_disposition()
@@ -639,18 +871,52 @@ pub trait MimeHeaders<'x> {
     }
     // Returns true is the content type matches
     fn is_content_type(&self, type_: &str, subtype: &str) -> bool {
-        self.content_type().map_or(false, |ct| {
+        self.content_type().is_some_and(|ct| {
             ct.c_type.eq_ignore_ascii_case(type_)
                 && ct
                     .c_subtype
                     .as_ref()
-                    .map_or(false, |st| st.eq_ignore_ascii_case(subtype))
+                    .is_some_and(|st| st.eq_ignore_ascii_case(subtype))
         })
     }
 }
 
+/// Callbacks invoked by `MessageParser::parse_with_visitor` as a message is
+/// parsed. Part ids are assigned in the order the parts are found, so they
+/// match the indices of `Message::parts` unless the message contains nested
+/// messages, whose parts are numbered after their enclosing part.
+///
+/// Returning `ControlFlow::Break` from any callback stops the parser.
+pub trait MessageVisitor<'x> {
+    /// Called for each header of a part
+    fn on_header(&mut self, part_id: MessagePartId, header: &Header<'x>) -> ControlFlow<()> {
+        let _ = (part_id, header);
+        ControlFlow::Continue(())
+    }
+
+    /// Called once all the headers of a part have been parsed
+    fn on_part_start(
+        &mut self,
+        part_id: MessagePartId,
+        content_type: Option<&ContentType<'x>>,
+    ) -> ControlFlow<()> {
+        let _ = (part_id, content_type);
+        ControlFlow::Continue(())
+    }
+
+    /// Called with the decoded contents of a non-multipart part. Encoded
+    /// nested messages are not parsed, their decoded contents are passed
+    /// instead.
+    fn on_body_chunk(&mut self, part_id: MessagePartId, chunk: &[u8]) -> ControlFlow<()> {
+        let _ = (part_id, chunk);
+        ControlFlow::Continue(())
+    }
+}
+
 pub trait GetHeader<'x> {
+    /// Returns the value of the last header with the given name
     fn header_value(&self, name: &HeaderName<'_>) -> Option<&HeaderValue<'x>>;
+    /// Returns the last header with the given name
     fn header(&self, name: impl Into<HeaderName<'x>>) -> Option<&Header<'x>>;
 }
 
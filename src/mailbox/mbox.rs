@@ -9,7 +9,7 @@
  * except according to those terms.
  */
 
-use crate::DateTime;
+use crate::{DateTime, MessageParser};
 use std::io::{BufRead, BufReader, Read};
 
 /// Parses an Mbox mailbox from a `Read` stream, returning each message as a
@@ -222,11 +222,119 @@ impl Message {
     }
 }
 
+/// Parses an mboxrd mailbox from a byte slice, returning each entry as a
+/// parsed `Message`.
+///
+/// Entries are separated by lines starting with `From ` that follow a blank
+/// line (or start the mailbox), and `>From ` quoted lines are unescaped.
+/// Entries that do not need unescaping borrow from the mailbox contents.
+/// Entries without any headers are skipped.
+pub struct MboxReader<'x> {
+    data: &'x [u8],
+    pos: usize,
+    parser: MessageParser,
+}
+
+impl<'x> MboxReader<'x> {
+    pub fn new(data: &'x [u8]) -> MboxReader<'x> {
+        MboxReader {
+            data,
+            pos: next_separator(data, 0).unwrap_or(data.len()),
+            parser: MessageParser::default(),
+        }
+    }
+
+    /// Use a custom parser for the mailbox entries
+    pub fn with_parser(mut self, parser: MessageParser) -> Self {
+        self.parser = parser;
+        self
+    }
+}
+
+impl<'x> Iterator for MboxReader<'x> {
+    type Item = crate::Message<'x>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = self.data;
+
+        while self.pos < data.len() {
+            // Skip the separator line
+            let start = data[self.pos..]
+                .iter()
+                .position(|&ch| ch == b'\n')
+                .map_or(data.len(), |pos| self.pos + pos + 1);
+            let end = next_separator(data, start).unwrap_or(data.len());
+            self.pos = end;
+
+            // Remove the blank line preceding the next separator
+            let contents = &data[start..end];
+            let contents = if end < data.len() {
+                contents
+                    .strip_suffix(b"\r\n")
+                    .or_else(|| contents.strip_suffix(b"\n"))
+                    .unwrap_or(contents)
+            } else {
+                contents
+            };
+
+            let message = if contents.split(|&ch| ch == b'\n').any(is_quoted_from) {
+                let mut unquoted = Vec::with_capacity(contents.len());
+                for line in contents.split_inclusive(|&ch| ch == b'\n') {
+                    unquoted.extend_from_slice(if is_quoted_from(line) {
+                        &line[1..]
+                    } else {
+                        line
+                    });
+                }
+                self.parser
+                    .parse(&unquoted[..])
+                    .map(|message| message.into_owned())
+            } else {
+                self.parser.parse(contents)
+            };
+
+            if message.is_some() {
+                return message;
+            }
+        }
+
+        None
+    }
+}
+
+fn next_separator(data: &[u8], mut pos: usize) -> Option<usize> {
+    let mut prev_blank = pos == 0;
+
+    while pos < data.len() {
+        let line_end = data[pos..]
+            .iter()
+            .position(|&ch| ch == b'\n')
+            .map_or(data.len(), |end| pos + end + 1);
+        let line = &data[pos..line_end];
+
+        if prev_blank && line.starts_with(b"From ") {
+            return Some(pos);
+        }
+        prev_blank = matches!(line, b"\n" | b"\r\n");
+        pos = line_end;
+    }
+
+    None
+}
+
+fn is_quoted_from(line: &[u8]) -> bool {
+    line.iter()
+        .position(|&ch| ch != b'>')
+        .is_some_and(|pos| pos > 0 && line[pos..].starts_with(b"From "))
+}
+
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+
     use crate::mailbox::mbox::Message;
 
-    use super::MessageIterator;
+    use super::{MboxReader, MessageIterator};
 
     #[test]
     fn parse_mbox() {
@@ -276,4 +384,50 @@ Message 4
             assert_eq!(message.unwrap(), expected_messages);
         }
     }
+
+    #[test]
+    fn read_mbox() {
+        let mbox = concat!(
+            "From god@heaven.af.mil Sat Jan  3 01:05:34 1996\n",
+            "Subject: Message 1\n",
+            "\n",
+            "Sent From my phone\n",
+            "From here on this is not a separator\n",
+            "\n",
+            "From cras@irccrew.org  Tue Jul 23 19:39:23 2002\n",
+            "Subject: Message 2\n",
+            "\n",
+            "Quoted lines:\n",
+            ">From hello\n",
+            ">>From world\n",
+            "> From test\n",
+            "\n",
+            "From test@test.com Tue Aug  6 13:34:34 2002\n",
+            "Subject: Message 3\n",
+            "\n",
+            "Last message\n"
+        );
+
+        let messages = MboxReader::new(mbox.as_bytes()).collect::<Vec<_>>();
+        assert_eq!(messages.len(), 3);
+
+        assert_eq!(messages[0].subject(), Some("Message 1"));
+        assert_eq!(
+            messages[0].body_text(0).unwrap(),
+            "Sent From my phone\nFrom here on this is not a separator\n"
+        );
+        assert!(matches!(messages[0].raw_message, Cow::Borrowed(_)));
+
+        assert_eq!(messages[1].subject(), Some("Message 2"));
+        assert_eq!(
+            messages[1].body_text(0).unwrap(),
+            "Quoted lines:\nFrom hello\n>From world\n> From test\n"
+        );
+        assert!(matches!(messages[1].raw_message, Cow::Owned(_)));
+
+        assert_eq!(messages[2].subject(), Some("Message 3"));
+        assert_eq!(messages[2].body_text(0).unwrap(), "Last message\n");
+
+        assert_eq!(MboxReader::new(b"").count(), 0);
+    }
 }
@@ -28,6 +28,7 @@ pub struct AddressParser<'x> {
     is_token_email: bool,
     is_token_start: bool,
     is_escaped: bool,
+    is_null_address: bool,
 
     name_tokens: Vec<Cow<'x, str>>,
     mail_tokens: Vec<Cow<'x, str>>,
@@ -45,7 +46,12 @@ pub struct AddressParser<'x> {
 impl<'x> AddressParser<'x> {
     pub fn add_token(&mut self, stream: &MessageStream<'x>, add_trail_space: bool) {
         if self.token_start > 0 {
-            let token = String::from_utf8_lossy(&stream.data[self.token_start - 1..self.token_end]);
+            let range = self.token_start - 1..self.token_end;
+            let token = if self.state != AddressState::Name {
+                stream.unescape(range)
+            } else {
+                String::from_utf8_lossy(&stream.data[range])
+            };
             let mut add_space = false;
             let list = match self.state {
                 AddressState::Address => &mut self.mail_tokens,
@@ -64,11 +70,14 @@ impl<'x> AddressParser<'x> {
                 }
             };
 
-            if add_space && !list.is_empty() {
-                list.push(" ".into());
-            }
+            // A backslash right before a folded line leaves an empty token
+            if !token.is_empty() {
+                if add_space && !list.is_empty() {
+                    list.push(" ".into());
+                }
 
-            list.push(token);
+                list.push(token);
+            }
 
             if add_trail_space {
                 list.push(" ".into());
@@ -77,7 +86,6 @@ impl<'x> AddressParser<'x> {
             self.token_start = 0;
             self.is_token_email = false;
             self.is_token_start = true;
-            self.is_escaped = false;
         }
     }
 
@@ -85,48 +93,56 @@ impl<'x> AddressParser<'x> {
         let has_mail = !self.mail_tokens.is_empty();
         let has_name = !self.name_tokens.is_empty();
         let has_comment = !self.comment_tokens.is_empty();
+        let is_null_address = std::mem::take(&mut self.is_null_address);
 
         self.addresses.push(if has_mail && has_name && has_comment {
             Addr {
-                name: Some(
-                    format!(
-                        "{} ({})",
-                        concat_tokens(&mut self.name_tokens),
-                        concat_tokens(&mut self.comment_tokens)
-                    )
-                    .into(),
-                ),
+                name: concat_tokens(&mut self.name_tokens).into(),
                 address: concat_tokens(&mut self.mail_tokens).into(),
+                comment: concat_tokens(&mut self.comment_tokens).into(),
             }
         } else if has_name && has_mail {
             Addr {
                 name: concat_tokens(&mut self.name_tokens).into(),
                 address: concat_tokens(&mut self.mail_tokens).into(),
+                comment: None,
             }
         } else if has_mail && has_comment {
             Addr {
-                name: concat_tokens(&mut self.comment_tokens).into(),
+                name: None,
                 address: concat_tokens(&mut self.mail_tokens).into(),
+                comment: concat_tokens(&mut self.comment_tokens).into(),
             }
         } else if has_mail {
             Addr {
                 name: None,
                 address: concat_tokens(&mut self.mail_tokens).into(),
+                comment: None,
             }
         } else if has_name && has_comment {
             Addr {
-                name: concat_tokens(&mut self.comment_tokens).into(),
+                name: None,
                 address: concat_tokens(&mut self.name_tokens).into(),
+                comment: concat_tokens(&mut self.comment_tokens).into(),
             }
         } else if has_name {
             Addr {
                 name: concat_tokens(&mut self.name_tokens).into(),
                 address: None,
+                comment: None,
             }
         } else if has_comment {
             Addr {
-                name: concat_tokens(&mut self.comment_tokens).into(),
+                name: None,
+                address: None,
+                comment: concat_tokens(&mut self.comment_tokens).into(),
+            }
+        } else if is_null_address {
+            // Null address "<>", as used by bounces
+            Addr {
+                name: None,
                 address: None,
+                comment: None,
             }
         } else {
             return;
@@ -196,7 +212,27 @@ impl<'x> AddressParser<'x> {
 }
 
 impl<'x> MessageStream<'x> {
+    /// Parses an address list or group.
+    ///
+    /// A folded line break following a complete address is treated as an
+    /// address separator even if no comma is present. Display names that wrap
+    /// across lines are not affected as no address has been seen yet, but a
+    /// display name containing an `@` that is folded before its angle-addr
+    /// will be split into two entries.
+    ///
+    /// Comments are appended to the address name, use
+    /// `parse_address_with_comments` to obtain them separately.
     pub fn parse_address(&mut self) -> HeaderValue<'x> {
+        let mut value = self.parse_address_with_comments();
+        if let HeaderValue::Address(address) = &mut value {
+            address.merge_comments();
+        }
+        value
+    }
+
+    /// Parses an address list or group, keeping comments in `Addr::comment`
+    /// rather than appending them to the address name.
+    pub fn parse_address_with_comments(&mut self) -> HeaderValue<'x> {
         let mut parser = AddressParser {
             token_start: 0,
             token_end: 0,
@@ -204,6 +240,7 @@ impl<'x> MessageStream<'x> {
             is_token_email: false,
             is_token_start: true,
             is_escaped: false,
+            is_null_address: false,
 
             name_tokens: Vec::with_capacity(3),
             mail_tokens: Vec::with_capacity(3),
@@ -223,6 +260,14 @@ impl<'x> MessageStream<'x> {
                 b'\n' => {
                     parser.add_token(self, false);
                     if self.try_next_is_space() {
+                        if parser.state == AddressState::Name
+                            && !parser.mail_tokens.is_empty()
+                            && !self.next_non_space_is(b'(')
+                        {
+                            // Some senders fold one address per line without commas,
+                            // treat the fold as an implicit separator.
+                            parser.add_address();
+                        }
                         if !parser.is_token_start {
                             parser.is_token_start = true;
                         }
@@ -232,13 +277,15 @@ impl<'x> MessageStream<'x> {
                     }
                 }
                 b'\\' if parser.state != AddressState::Name && !parser.is_escaped => {
-                    if parser.token_start > 0 {
-                        if parser.state == AddressState::Quote {
-                            parser.token_end = self.offset() - 1;
-                        }
-                        parser.add_token(self, false);
-                    }
+                    // Quoted-pairs are kept in the token and resolved by add_token
                     parser.is_escaped = true;
+                    parser.is_token_start = false;
+                    if parser.token_start == 0 {
+                        parser.token_start = self.offset();
+                        parser.token_end = parser.token_start;
+                    } else {
+                        parser.token_end = self.offset();
+                    }
                     continue;
                 }
                 b',' if parser.state == AddressState::Name => {
@@ -253,8 +300,22 @@ impl<'x> MessageStream<'x> {
                     parser.state = AddressState::Address;
                     continue;
                 }
+                b':' if parser.state == AddressState::Address
+                    && !parser.is_escaped
+                    && parser.mail_tokens.first().map_or_else(
+                        || parser.token_start > 0 && self.data[parser.token_start - 1] == b'@',
+                        |token| token.starts_with('@'),
+                    ) =>
+                {
+                    // Obsolete source route "<@relay1,@relay2:user@host>",
+                    // discard the route and keep the final mailbox.
+                    parser.add_token(self, false);
+                    parser.mail_tokens.clear();
+                    continue;
+                }
                 b'>' if parser.state == AddressState::Address => {
                     parser.add_token(self, false);
+                    parser.is_null_address = parser.mail_tokens.is_empty();
                     parser.state = parser.state_stack.pop().unwrap();
                     continue;
                 }
@@ -330,6 +391,15 @@ impl<'x> MessageStream<'x> {
                     parser.add_group_details();
                     continue;
                 }
+                b';' if parser.state == AddressState::Name
+                    && self.semicolon_address_separator
+                    && parser.group_name.is_none() =>
+                {
+                    // Outlook style "a@x; b@y" list
+                    parser.add_token(self, false);
+                    parser.add_address();
+                    continue;
+                }
                 b';' if parser.state == AddressState::Name => {
                     parser.add_token(self, false);
                     parser.add_address();
@@ -368,6 +438,15 @@ impl<'x> MessageStream<'x> {
     }
 }
 
+impl MessageStream<'_> {
+    fn next_non_space_is(&self, ch: u8) -> bool {
+        self.data[self.offset()..]
+            .iter()
+            .find(|ch| !ch.is_ascii_whitespace())
+            .is_some_and(|&next_ch| next_ch == ch)
+    }
+}
+
 fn concat_tokens<'x>(tokens: &mut Vec<Cow<'x, str>>) -> Cow<'x, str> {
     if tokens.len() == 1 {
         tokens.pop().unwrap()
@@ -465,7 +544,10 @@ pub fn parse_address_detail_part(addr: &str) -> Option<&str> {
 
 #[cfg(test)]
 mod tests {
-    use crate::parsers::{fields::load_tests, MessageStream};
+    use crate::{
+        parsers::{fields::load_tests, MessageStream},
+        Addr, Address, MessageParser,
+    };
 
     #[test]
     fn parse_addresses() {
@@ -480,4 +562,238 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn parse_address_comments() {
+        let addr =
+            |name: Option<&'static str>, address: &'static str, comment: Option<&'static str>| {
+                Addr {
+                    name: name.map(Into::into),
+                    address: Some(address.into()),
+                    comment: comment.map(Into::into),
+                }
+            };
+
+        assert_eq!(
+            MessageStream::new(
+                b"\"John Doe\" <jdoe@example.com> (Sales), jane@example.com (Jane)\n"
+            )
+            .parse_address_with_comments()
+            .unwrap_address()
+            .into_list(),
+            vec![
+                addr(Some("John Doe"), "jdoe@example.com", Some("Sales")),
+                addr(None, "jane@example.com", Some("Jane")),
+            ]
+        );
+
+        let input = concat!(
+            "From: \"John Doe\" <jdoe@example.com> (Sales)\n",
+            "To: jane@example.com (Jane), Bob <bob@example.com>\n",
+            "\n",
+            "Body\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(
+            message.from().unwrap().first().unwrap(),
+            &addr(Some("John Doe (Sales)"), "jdoe@example.com", None)
+        );
+        assert_eq!(
+            message.to().unwrap().first().unwrap(),
+            &addr(Some("Jane"), "jane@example.com", None)
+        );
+
+        for parser in [
+            MessageParser::default().with_address_comments(true),
+            MessageParser::new()
+                .with_address_headers()
+                .with_address_comments(true),
+        ] {
+            let message = parser.parse(input).unwrap();
+            assert_eq!(
+                message.from().unwrap().first().unwrap(),
+                &addr(Some("John Doe"), "jdoe@example.com", Some("Sales"))
+            );
+            assert_eq!(
+                message.to().unwrap().as_list().unwrap(),
+                &[
+                    addr(None, "jane@example.com", Some("Jane")),
+                    addr(Some("Bob"), "bob@example.com", None),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn display_addresses() {
+        for (input, expected) in [
+            ("jdoe@example.com", "jdoe@example.com"),
+            ("John Doe <jdoe@example.com>", "John Doe <jdoe@example.com>"),
+            (
+                "\"Doe, John\" <jdoe@example.com>",
+                "\"Doe, John\" <jdoe@example.com>",
+            ),
+            (
+                "\"John \\\"Jr.\\\" Doe\" <jdoe@example.com>",
+                "\"John \\\"Jr.\\\" Doe\" <jdoe@example.com>",
+            ),
+            (
+                "\"C:\\\\Users\" <user@example.com>",
+                "\"C:\\\\Users\" <user@example.com>",
+            ),
+            (
+                "José <jose@example.com> (Sales EU), <>",
+                "José <jose@example.com> (Sales EU), <>",
+            ),
+            (
+                "a@example.com, Friends: b@example.com, \"B. C.\" <c@example.com>;",
+                "a@example.com, Friends: b@example.com, \"B. C.\" <c@example.com>;",
+            ),
+            ("Undisclosed recipients:;", "Undisclosed recipients:;"),
+        ] {
+            let input_line = format!("{input}\n");
+            let address = MessageStream::new(input_line.as_bytes())
+                .parse_address_with_comments()
+                .unwrap_address();
+            let output = address.to_string();
+            assert_eq!(output, expected, "failed for {input:?}");
+            assert_eq!(
+                MessageStream::new(format!("{output}\n").as_bytes())
+                    .parse_address_with_comments()
+                    .unwrap_address(),
+                address,
+                "round-trip failed for {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn srs_decode() {
+        for (address, expected) in [
+            (
+                "SRS0=HHH=TT=example.org=john@forwarder.example",
+                Some("john@example.org"),
+            ),
+            (
+                "srs0=Zk3q=2W=example.org=john.doe+tag@forwarder.example",
+                Some("john.doe+tag@example.org"),
+            ),
+            (
+                "SRS0=HHH=TT=example.org=a=b@forwarder.example",
+                Some("a=b@example.org"),
+            ),
+            (
+                "SRS1=HHH=first.example==HHH=TT=example.org=john@second.example",
+                Some("john@example.org"),
+            ),
+            (
+                "SRS1=X9kd=first.example=+Zk3q=2W=example.org=john@second.example",
+                Some("john@example.org"),
+            ),
+            ("john@example.org", None),
+            ("SRS0=HHH=TT=example.org@forwarder.example", None),
+            ("SRS0=HHH=TT==john@forwarder.example", None),
+            ("SRS1=HHH=first.example@second.example", None),
+            ("SRS0=HHH=TT=example.org=john", None),
+        ] {
+            assert_eq!(
+                Addr::new(None, address).srs_decode().as_deref(),
+                expected,
+                "failed for {address:?}"
+            );
+        }
+        assert_eq!(
+            Addr {
+                name: Some("John".into()),
+                address: None,
+                comment: None
+            }
+            .srs_decode(),
+            None
+        );
+    }
+
+    #[test]
+    fn semicolon_address_separator() {
+        let input = concat!(
+            "To: a@example.com; John <b@example.com>;\r\n",
+            "Cc: Team: c@example.com, d@example.com; e@example.com; f@example.com\r\n",
+            "\r\n",
+            "Hello\r\n"
+        );
+
+        // RFC 5322 semantics by default, each address is an unnamed group
+        let message = MessageParser::default().parse(input).unwrap();
+        assert!(matches!(message.to(), Some(Address::Group(groups)) if groups.len() == 2));
+
+        let message = MessageParser::default()
+            .with_semicolon_address_separator(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(
+            message.to(),
+            Some(&Address::List(vec![
+                Addr::new(None, "a@example.com"),
+                Addr::new(Some("John"), "b@example.com"),
+            ]))
+        );
+        let Some(Address::Group(groups)) = message.cc() else {
+            panic!("expected groups, got {:?}", message.cc());
+        };
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name.as_deref(), Some("Team"));
+        assert_eq!(groups[0].addresses.len(), 2);
+        assert_eq!(groups[1].name, None);
+        assert_eq!(
+            groups[1].addresses,
+            [
+                Addr::new(None, "e@example.com"),
+                Addr::new(None, "f@example.com")
+            ]
+        );
+    }
+
+    #[test]
+    fn subaddress() {
+        for (address, subaddress, base_address) in [
+            (
+                "user+tag@example.org",
+                Some(("user", "tag")),
+                "user@example.org",
+            ),
+            (
+                "user+tag+more@example.org",
+                Some(("user", "tag+more")),
+                "user@example.org",
+            ),
+            ("user+@example.org", Some(("user", "")), "user@example.org"),
+            ("user@example.org", None, "user@example.org"),
+            ("+tag@example.org", None, "+tag@example.org"),
+            ("user@plus+domain.example", None, "user@plus+domain.example"),
+            ("user+tag", None, "user+tag"),
+        ] {
+            let addr = Addr::new(None, address);
+            assert_eq!(addr.subaddress(), subaddress, "failed for {address:?}");
+            assert_eq!(
+                addr.base_address().as_deref(),
+                Some(base_address),
+                "failed for {address:?}"
+            );
+        }
+
+        let addr = Addr::new(None, "user@example.org");
+        assert!(matches!(
+            addr.base_address(),
+            Some(std::borrow::Cow::Borrowed("user@example.org"))
+        ));
+
+        let addr = Addr {
+            name: Some("John".into()),
+            address: None,
+            comment: None,
+        };
+        assert_eq!(addr.subaddress(), None);
+        assert_eq!(addr.base_address(), None);
+    }
 }
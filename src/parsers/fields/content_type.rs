@@ -89,8 +89,7 @@ impl<'x> ContentTypeParser<'x> {
 
     fn add_attribute_parameter(&mut self, stream: &MessageStream<'x>) {
         if self.token_start > 0 {
-            let attr_part =
-                String::from_utf8_lossy(&stream.data[self.token_start - 1..self.token_end]);
+            let attr_part = stream.unescape(self.token_start - 1..self.token_end);
 
             if self.attr_charset.is_none() {
                 self.attr_charset = attr_part.into();
@@ -110,18 +109,11 @@ impl<'x> ContentTypeParser<'x> {
         }
     }
 
-    fn add_partial_value(&mut self, stream: &MessageStream<'x>, to_cur_pos: bool) {
+    fn add_partial_value(&mut self, stream: &MessageStream<'x>) {
         if self.token_start > 0 {
-            let in_quote = self.state == ContentState::AttributeQuotedValue;
-
-            self.values.push(String::from_utf8_lossy(
-                &stream.data[self.token_start - 1..if in_quote && to_cur_pos {
-                    stream.offset() - 1
-                } else {
-                    self.token_end
-                }],
-            ));
-            if !in_quote {
+            self.values
+                .push(stream.unescape(self.token_start - 1..self.token_end));
+            if self.state != ContentState::AttributeQuotedValue {
                 self.values.push(" ".into());
             }
 
@@ -136,20 +128,15 @@ impl<'x> ContentTypeParser<'x> {
 
         let has_values = !self.values.is_empty();
         let value = if self.token_start > 0 {
-            let value = &stream.data[self.token_start - 1..self.token_end];
+            let value = stream.unescape(self.token_start - 1..self.token_end);
             Some(if !self.remove_crlf {
-                String::from_utf8_lossy(value)
+                value
             } else {
                 self.remove_crlf = false;
-                match String::from_utf8(
+                if value.contains(['\r', '\n']) {
+                    value.replace(['\r', '\n'], "").into()
+                } else {
                     value
-                        .iter()
-                        .filter(|&&ch| ch != b'\r' && ch != b'\n')
-                        .copied()
-                        .collect::<Vec<_>>(),
-                ) {
-                    Ok(value) => value.into(),
-                    Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned().into(),
                 }
             })
         } else {
@@ -279,6 +266,9 @@ impl<'x> MessageStream<'x> {
             token_end: 0,
         };
 
+        // The value may start on a folded line
+        self.skip_ws();
+
         while let Some(ch) = self.next() {
             match ch {
                 b' ' | b'\t' => {
@@ -295,14 +285,12 @@ impl<'x> MessageStream<'x> {
                     }
                     continue;
                 }
-                b'A'..=b'Z' => {
-                    if parser.is_lower_case {
-                        if let ContentState::Type
-                        | ContentState::SubType
-                        | ContentState::AttributeName = parser.state
-                        {
-                            parser.is_lower_case = false;
-                        }
+                b'A'..=b'Z' if parser.is_lower_case => {
+                    if let ContentState::Type
+                    | ContentState::SubType
+                    | ContentState::AttributeName = parser.state
+                    {
+                        parser.is_lower_case = false;
                     }
                 }
                 b'\n' => {
@@ -411,7 +399,7 @@ impl<'x> MessageStream<'x> {
                     {
                         self.checkpoint();
                         if let Some(token) = self.decode_rfc2047() {
-                            parser.add_partial_value(self, false);
+                            parser.add_partial_value(self);
                             parser.values.push(token.into());
                             continue;
                         }
@@ -421,11 +409,13 @@ impl<'x> MessageStream<'x> {
                 },
                 b'\"' => match parser.state {
                     ContentState::AttributeValue => {
-                        if !parser.is_token_start {
-                            parser.is_token_start = true;
+                        if !parser.is_escaped {
+                            if !parser.is_token_start {
+                                parser.is_token_start = true;
+                            }
+                            parser.state = ContentState::AttributeQuotedValue;
+                            continue;
                         }
-                        parser.state = ContentState::AttributeQuotedValue;
-                        continue;
                     }
                     ContentState::AttributeQuotedValue => {
                         if !parser.is_escaped {
@@ -441,8 +431,16 @@ impl<'x> MessageStream<'x> {
                 b'\\' => match parser.state {
                     ContentState::AttributeQuotedValue | ContentState::AttributeValue => {
                         if !parser.is_escaped {
-                            parser.add_partial_value(self, true);
+                            // Quoted-pairs are kept in the value and resolved
+                            // when it is added
                             parser.is_escaped = true;
+                            parser.is_token_start = false;
+                            if parser.token_start == 0 {
+                                parser.token_start = self.offset();
+                                parser.token_end = parser.token_start;
+                            } else {
+                                parser.token_end = self.offset();
+                            }
                             continue;
                         } else {
                             parser.is_escaped = false;
@@ -515,7 +513,10 @@ impl<'x> MessageStream<'x> {
 }
 #[cfg(test)]
 mod tests {
-    use crate::parsers::{fields::load_tests, MessageStream};
+    use crate::{
+        parsers::{fields::load_tests, MessageStream},
+        ContentType, MessageParser,
+    };
 
     #[test]
     fn parse_content_fields() {
@@ -543,4 +544,76 @@ mod tests {
 
         builder.write();*/
     }
+
+    #[test]
+    fn content_type_attribute_case() {
+        let mut ct =
+            MessageStream::new(b"application/pdf; FILENAME=\"report.pdf\"; Charset=utf-8\n")
+                .parse_content_type()
+                .into_content_type()
+                .unwrap();
+
+        assert_eq!(ct.attribute("filename"), Some("report.pdf"));
+        assert_eq!(ct.attribute("FileName"), Some("report.pdf"));
+        assert_eq!(ct.attribute("CHARSET"), Some("utf-8"));
+        assert!(ct.has_attribute("Charset"));
+        assert!(!ct.has_attribute("name"));
+        assert_eq!(ct.remove_attribute("charSet").as_deref(), Some("utf-8"));
+        assert!(!ct.has_attribute("charset"));
+
+        let mut ct = ContentType {
+            c_type: "text".into(),
+            c_subtype: Some("plain".into()),
+            attributes: Some(vec![
+                ("Name".into(), "notes.txt".into()),
+                ("CHARSET".into(), "iso-8859-1".into()),
+            ]),
+        };
+
+        assert_eq!(ct.attribute("name"), Some("notes.txt"));
+        assert_eq!(ct.attribute("charset"), Some("iso-8859-1"));
+        assert!(ct.has_attribute("NAME"));
+        assert_eq!(ct.remove_attribute("name").as_deref(), Some("notes.txt"));
+        assert_eq!(ct.attribute("Name"), None);
+    }
+
+    #[test]
+    fn folded_content_type() {
+        for input in [
+            "\r\n text/plain; charset=utf-8\r\n",
+            " \n\ttext/plain;\n charset=\"utf-8\"\n",
+        ] {
+            let ct = MessageStream::new(input.as_bytes())
+                .parse_content_type()
+                .into_content_type()
+                .unwrap_or_else(|| panic!("failed for {input:?}"));
+            assert_eq!(ct.ctype(), "text", "failed for {input:?}");
+            assert_eq!(ct.subtype(), Some("plain"), "failed for {input:?}");
+            assert_eq!(
+                ct.attribute("charset"),
+                Some("utf-8"),
+                "failed for {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn charset_with_trailing_junk() {
+        for content_type in [
+            "text/plain; charset=iso-8859-1; ",
+            "text/plain; charset=\"iso-8859-1\"",
+            "text/plain; charset=iso-8859-1(Latin 1)",
+            "text/plain; charset=iso-8859-1;format=flowed",
+            "text/plain; charset=\"iso-8859-1\" (Latin 1);;format=flowed",
+        ] {
+            let mut input = format!("Content-Type: {content_type}\r\n\r\n").into_bytes();
+            input.extend_from_slice(b"Caf\xe9\r\n");
+            let message = MessageParser::default().parse(&input).unwrap();
+            assert_eq!(
+                message.body_text(0).as_deref(),
+                Some("Café\r\n"),
+                "failed for {content_type:?}"
+            );
+        }
+    }
 }
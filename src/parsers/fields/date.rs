@@ -254,6 +254,25 @@ impl DateTime {
         c * 146097 / 4 + (year - c * 100) * 1461 / 4 + (month * 153 + 2) / 5 + day + 1721119
     }
 
+    /// Returns the wall clock time seen by the sender, as
+    /// `(year, month, day, hour, minute, second)` in the sender's timezone
+    pub fn to_local_naive(&self) -> (u16, u8, u8, u8, u8, u8) {
+        (
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        )
+    }
+
+    /// Converts the DateTime to UTC (`+0000`), adjusting the date when
+    /// applying the offset crosses a day boundary
+    pub fn to_utc(&self) -> DateTime {
+        DateTime::from_timestamp(self.to_timestamp())
+    }
+
     /// Converts the DateTime to the given timezone
     pub fn to_timezone(&self, tz: i64) -> DateTime {
         let mut dt = DateTime::from_timestamp(self.to_timestamp() + tz);
@@ -306,6 +325,7 @@ impl<'x> MessageStream<'x> {
         let mut is_plus = true;
         let mut is_new_token = true;
         let mut ignore = true;
+        let mut has_time_sep = false;
         let mut comment_count = 0;
 
         while let Some(ch) = self.next() {
@@ -346,10 +366,9 @@ impl<'x> MessageStream<'x> {
                         is_new_token = false;
                     }
                 }
-                b':' => {
-                    if !is_new_token && !ignore && (pos == 3 || pos == 4) {
-                        next_part = true;
-                    }
+                b':' if !is_new_token && !ignore && (pos == 3 || pos == 4) => {
+                    has_time_sep |= pos == 3;
+                    next_part = true;
                 }
                 b'+' => {
                     pos = 6;
@@ -358,10 +377,8 @@ impl<'x> MessageStream<'x> {
                     is_plus = false;
                     pos = 6;
                 }
-                b' ' | b'\t' => {
-                    if !is_new_token && !ignore {
-                        next_part = true;
-                    }
+                b' ' | b'\t' if !is_new_token && !ignore => {
+                    next_part = true;
                 }
                 b'a'..=b'z' | b'A'..=b'Z' => {
                     if pos == 1 {
@@ -401,6 +418,7 @@ impl<'x> MessageStream<'x> {
                     is_plus = true;
                     is_new_token = true;
                     ignore = true;
+                    has_time_sep = false;
                     continue;
                 }
                 _ => (),
@@ -412,11 +430,25 @@ impl<'x> MessageStream<'x> {
                 }
                 pos += 1;
                 is_new_token = true;
+
+                // Obsolete "hh:mm" time without seconds
+                if pos == 5 && has_time_sep && *ch != b':' {
+                    pos = 6;
+                }
             }
         }
 
+        // The date may end right after the minutes or seconds (missing zone)
+        if !is_new_token && !ignore && comment_count == 0 && (4..=5).contains(&pos) {
+            if parts_sizes[pos] > 0 {
+                parts[pos] /= u32::pow(10, parts_sizes[pos]);
+            }
+            pos = if pos == 4 && !has_time_sep { 5 } else { 6 };
+        }
+
         if pos >= 6 {
             HeaderValue::DateTime(DateTime {
+                // Obsolete two-digit years are interpreted as in RFC 5322, section 4.3
                 year: if (0..=49).contains(&parts[2]) {
                     parts[2] + 2000
                 } else if (50..=99).contains(&parts[2]) {
@@ -465,7 +497,10 @@ pub static MONTH_MAP: &[u8; 31] = &[
 mod tests {
     use chrono::{FixedOffset, LocalResult, SecondsFormat, TimeZone, Utc};
 
-    use crate::parsers::{fields::load_tests, MessageStream};
+    use crate::{
+        parsers::{fields::load_tests, MessageStream},
+        DateTime,
+    };
 
     #[test]
     fn parse_dates() {
@@ -511,4 +546,37 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn to_utc() {
+        for (input, local, utc) in [
+            (
+                "Sun, 31 Dec 2023 22:30:00 -0500",
+                (2023, 12, 31, 22, 30, 0),
+                "2024-01-01T03:30:00Z",
+            ),
+            (
+                "Fri, 1 Mar 2024 01:00:00 +1400",
+                (2024, 3, 1, 1, 0, 0),
+                "2024-02-29T11:00:00Z",
+            ),
+            (
+                "Mon, 1 Jan 2024 00:15:00 +0530",
+                (2024, 1, 1, 0, 15, 0),
+                "2023-12-31T18:45:00Z",
+            ),
+            (
+                "Wed, 15 May 2024 12:00:00 +0000",
+                (2024, 5, 15, 12, 0, 0),
+                "2024-05-15T12:00:00Z",
+            ),
+        ] {
+            let datetime = DateTime::parse_rfc822(input).unwrap();
+            assert_eq!(datetime.to_local_naive(), local, "{input}");
+
+            let datetime_utc = datetime.to_utc();
+            assert_eq!(datetime_utc.to_rfc3339(), utc, "{input}");
+            assert_eq!(datetime_utc.to_timestamp(), datetime.to_timestamp());
+        }
+    }
 }
@@ -18,19 +18,46 @@ impl<'x> MessageStream<'x> {
         let mut token_invalid_start: usize = 0; // Handle broken clients
         let mut token_invalid_end: usize = 0; // Handle broken clients
         let mut is_id_part = false;
+        let mut in_quote = false;
+        let mut comment_depth = 0;
+        let mut is_escaped = false;
         let mut ids = Vec::new();
 
         while let Some(&ch) = self.next() {
+            // Skip quoted strings and comments found outside ids, some
+            // clients add the quoted text being replied to.
+            if (in_quote || comment_depth > 0) && ch != b'\n' {
+                if is_escaped {
+                    is_escaped = false;
+                } else {
+                    match ch {
+                        b'\\' => is_escaped = true,
+                        b'"' if in_quote => in_quote = false,
+                        b'(' if comment_depth > 0 => comment_depth += 1,
+                        b')' if comment_depth > 0 => comment_depth -= 1,
+                        _ => (),
+                    }
+                }
+                continue;
+            }
+
             match ch {
                 b'\n' => {
                     if !self.try_next_is_space() {
                         return match ids.len() {
                             1 => HeaderValue::Text(ids.pop().unwrap()),
                             0 => {
-                                if token_invalid_start > 0 {
-                                    HeaderValue::Text(String::from_utf8_lossy(
-                                        self.bytes(token_invalid_start - 1..token_invalid_end),
-                                    ))
+                                // A single bare token is likely an id without
+                                // brackets, anything longer is just garbage.
+                                let token = if token_invalid_start > 0 {
+                                    self.bytes(token_invalid_start - 1..token_invalid_end)
+                                } else {
+                                    b""
+                                };
+                                if !token.is_empty()
+                                    && !token.iter().any(|ch| ch.is_ascii_whitespace())
+                                {
+                                    HeaderValue::Text(String::from_utf8_lossy(token))
                                 } else {
                                     HeaderValue::Empty
                                 }
@@ -45,6 +72,14 @@ impl<'x> MessageStream<'x> {
                     is_id_part = true;
                     continue;
                 }
+                b'"' if !is_id_part => {
+                    in_quote = true;
+                    continue;
+                }
+                b'(' if !is_id_part => {
+                    comment_depth = 1;
+                    continue;
+                }
                 b'>' => {
                     is_id_part = false;
                     if token_start > 0 {
@@ -75,11 +110,68 @@ impl<'x> MessageStream<'x> {
         HeaderValue::Empty
     }
 }
+
+/// Returns `true` when `id` is a well-formed RFC 5322 `msg-id`, that is
+/// `<id-left@id-right>` where the left side is a dot-atom and the right side
+/// is either a dot-atom or a domain literal. Surrounding whitespace is
+/// ignored but comments and folding are not accepted.
+pub fn is_valid_msg_id(id: &str) -> bool {
+    let Some((left, right)) = id
+        .trim()
+        .strip_prefix('<')
+        .and_then(|id| id.strip_suffix('>'))
+        .and_then(|id| id.split_once('@'))
+    else {
+        return false;
+    };
+
+    is_dot_atom(left)
+        && (is_dot_atom(right)
+            || right
+                .strip_prefix('[')
+                .and_then(|literal| literal.strip_suffix(']'))
+                .is_some_and(|literal| literal.bytes().all(|ch| matches!(ch, 33..=90 | 94..=126))))
+}
+
+fn is_dot_atom(text: &str) -> bool {
+    !text.is_empty()
+        && text.split('.').all(|atom| {
+            !atom.is_empty()
+                && atom.bytes().all(|ch| {
+                    ch.is_ascii_alphanumeric()
+                        || matches!(
+                            ch,
+                            b'!' | b'#'
+                                | b'$'
+                                | b'%'
+                                | b'&'
+                                | b'\''
+                                | b'*'
+                                | b'+'
+                                | b'-'
+                                | b'/'
+                                | b'='
+                                | b'?'
+                                | b'^'
+                                | b'_'
+                                | b'`'
+                                | b'{'
+                                | b'|'
+                                | b'}'
+                                | b'~'
+                        )
+                })
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Cow;
 
-    use crate::parsers::{fields::load_tests, MessageStream};
+    use crate::parsers::{
+        fields::{id::is_valid_msg_id, load_tests},
+        MessageStream,
+    };
 
     #[test]
     fn parse_message_ids() {
@@ -94,4 +186,39 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn validate_message_ids() {
+        for id in [
+            "<1234@local.machine.example>",
+            " <abcd.1234@local.machine.test>\r\n",
+            "<a!#$%&'*+-/=?^_`{|}~@example.com>",
+            "<1234@[192.168.0.1]>",
+        ] {
+            assert!(is_valid_msg_id(id), "expected valid: {id:?}");
+        }
+
+        for id in [
+            "",
+            "<>",
+            "1234@local.machine.example",
+            "<1234@local.machine.example",
+            "1234@local.machine.example>",
+            "<<1234@local.machine.example>>",
+            "<1234.local.machine.example>",
+            "<1234@>",
+            "<@local.machine.example>",
+            "<12 34@local.machine.example>",
+            "<1234@local@machine.example>",
+            "<.1234@local.machine.example>",
+            "<1234..5678@local.machine.example>",
+            "<1234@local.machine.example.>",
+            "<1234@[192.168.0.1>",
+            "<1234@[192.168[0].1]>",
+            "<\"1234\"@local.machine.example>",
+            "<1234@local.machine.example> (comment)",
+        ] {
+            assert!(!is_valid_msg_id(id), "expected invalid: {id:?}");
+        }
+    }
 }
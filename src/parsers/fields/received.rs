@@ -154,14 +154,18 @@ impl<'x> MessageStream<'x> {
                     state = State::By;
                 }
                 Token::For if token.comment_depth == 0 => {
+                    // Some MTAs list multiple recipients: for <a@b>, <c@d>
                     while let Some(token) = tokenizer.peek() {
                         match token.token {
-                            Token::Equal | Token::AngleOpen => {
+                            Token::Equal | Token::AngleOpen | Token::AngleClose | Token::Comma => {
                                 tokenizer.next();
                             }
                             Token::Email => {
-                                received.for_ = Some(tokenizer.next().unwrap().text.into());
-                                break;
+                                let for_ = tokenizer.next().unwrap().text;
+                                if received.for_.is_none() {
+                                    received.for_ = Some(for_.into());
+                                }
+                                received.for_list.push(for_.into());
                             }
                             _ => {
                                 break;
@@ -268,43 +272,32 @@ impl<'x> MessageStream<'x> {
                         }
                     }
                 }
-                Token::IpAddr(ip) => {
+                Token::IpAddr(ip)
                     if state == State::From
                         && (token.bracket_depth > 0
-                            || (token.comment_depth > 0 && received.from_ip.is_none()))
-                    {
-                        received.from_ip = Some(ip);
-                    }
+                            || (token.comment_depth > 0 && received.from_ip.is_none())) =>
+                {
+                    received.from_ip = Some(ip);
                 }
-                Token::Domain => {
-                    if state == State::From && token.comment_depth > 0 {
-                        received.from_iprev = Some(token.text.into());
-                    }
+                Token::Domain if state == State::From && token.comment_depth > 0 => {
+                    received.from_iprev = Some(token.text.into());
                 }
-                Token::Email => {
-                    if state == State::From {
-                        received.ident =
-                            Some(token.text.strip_suffix('@').unwrap_or(token.text).into());
-                    }
+                Token::Email if state == State::From => {
+                    received.ident =
+                        Some(token.text.strip_suffix('@').unwrap_or(token.text).into());
                 }
-                Token::Integer(num) => {
-                    if state == State::Date {
-                        if let Some(part) = date_part.next() {
-                            *part = num;
-                        }
+                Token::Integer(num) if state == State::Date => {
+                    if let Some(part) = date_part.next() {
+                        *part = num;
                     }
                 }
-                Token::Month(month) => {
-                    if state == State::Date {
-                        if let Some(part) = date_part.next() {
-                            *part = month.to_number();
-                        }
+                Token::Month(month) if state == State::Date => {
+                    if let Some(part) = date_part.next() {
+                        *part = month.to_number();
                     }
                 }
-                Token::Cipher => {
-                    if token.comment_depth > 0 || received.tls_cipher.is_none() {
-                        received.tls_cipher = Some(token.text.into());
-                    }
+                Token::Cipher if (token.comment_depth > 0 || received.tls_cipher.is_none()) => {
+                    received.tls_cipher = Some(token.text.into());
                 }
                 Token::TlsVersion(tls)
                     if token.comment_depth > 0 && received.tls_version.is_none() =>
@@ -395,6 +388,7 @@ impl<'x> Iterator for Tokenizer<'x, '_> {
         let comment_depth = self.comment_depth;
         let bracket_depth = self.bracket_depth;
 
+        self.stream.skip_ws();
         let mut start_pos = self.stream.offset();
 
         while let Some(ch) = self.stream.next() {
@@ -842,7 +836,10 @@ impl Token {
 #[cfg(test)]
 mod tests {
 
-    use crate::parsers::{fields::load_tests, MessageStream};
+    use crate::{
+        parsers::{fields::load_tests, MessageStream},
+        DateTime, Greeting, Host, Protocol, Received, TlsVersion,
+    };
 
     #[test]
     fn parse_received() {
@@ -857,4 +854,64 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn received_to_header_string() {
+        let received = Received {
+            from: Some(Host::Name("mail.example.org".into())),
+            from_ip: Some("192.0.2.1".parse().unwrap()),
+            from_iprev: Some("host.example.org".into()),
+            by: Some(Host::Name("mx.example.com".into())),
+            for_: Some("jane@example.com".into()),
+            for_list: vec!["jane@example.com".into(), "bob@example.com".into()],
+            with: Some(Protocol::ESMTPS),
+            tls_version: Some(TlsVersion::TLSv1_3),
+            tls_cipher: Some("TLS_AES_256_GCM_SHA384".into()),
+            id: Some("4Bq8Jx1Z2kz9sW@mx.example.com".into()),
+            ident: Some("jdoe".into()),
+            helo: Some(Host::Name("helo.example.org".into())),
+            helo_cmd: Some(Greeting::Ehlo),
+            via: Some("relay".into()),
+            date: DateTime::parse_rfc822("Tue, 1 Jul 2003 10:52:37 +0200"),
+        };
+        let header = received.to_header_string();
+        assert_eq!(
+            header,
+            concat!(
+                "from mail.example.org (EHLO helo.example.org) ",
+                "(host.example.org [192.0.2.1] ident=jdoe)\r\n",
+                "\tby mx.example.com via relay with ESMTPS ",
+                "(TLSv1.3 cipher TLS_AES_256_GCM_SHA384)\r\n",
+                "\tid 4Bq8Jx1Z2kz9sW@mx.example.com\r\n",
+                "\tfor <jane@example.com>, <bob@example.com>;\r\n",
+                "\tTue, 1 Jul 2003 10:52:37 +0200"
+            )
+        );
+        let header = format!("{header}\r\n");
+        assert_eq!(
+            MessageStream::new(header.as_bytes())
+                .parse_received()
+                .unwrap_received(),
+            received
+        );
+
+        let received = Received {
+            from_ip: Some("2001:db8::1".parse().unwrap()),
+            by: Some(Host::Name("mx.example.com".into())),
+            with: Some(Protocol::SMTP),
+            ..Default::default()
+        };
+        let header = received.to_header_string();
+        assert_eq!(
+            header,
+            "from [IPv6:2001:db8::1]\r\n\tby mx.example.com with SMTP"
+        );
+        let header = format!("{header}\r\n");
+        let reparsed = MessageStream::new(header.as_bytes())
+            .parse_received()
+            .unwrap_received();
+        assert_eq!(reparsed.from_ip(), received.from_ip);
+        assert_eq!(reparsed.by, received.by);
+        assert_eq!(reparsed.with, received.with);
+    }
 }
@@ -9,6 +9,11 @@
  * except according to those terms.
  */
 
+use crate::{decoders::base64::base64_decode, DateTime, ThreadIndex};
+
+// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01
+const FILETIME_UNIX_EPOCH_SECS: i64 = 11_644_473_600;
+
 static RE_PREFIXES: &[&str] = &[
     "re", "res", "sv", "antw", "ref", "aw", "απ", "השב", "vá", "r", "rif", "bls", "odp", "ynt",
     "atb", "رد", "回复", "转发",
@@ -39,6 +44,46 @@ static FWD_PREFIXES: &[&str] = &[
     "轉寄",
 ];
 
+impl ThreadIndex {
+    /// Parses a base64 encoded `Thread-Index` header value
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        let bytes = base64_decode(value)?;
+        if bytes.len() < 22 || (bytes.len() - 22) % 5 != 0 {
+            return None;
+        }
+
+        // The header block starts with the 6 most significant bytes of a FILETIME
+        let mut filetime = [0u8; 8];
+        filetime[..6].copy_from_slice(&bytes[..6]);
+        let filetime = u64::from_be_bytes(filetime);
+        let mut guid = [0u8; 16];
+        guid.copy_from_slice(&bytes[6..22]);
+
+        Some(ThreadIndex {
+            date: DateTime::from_timestamp(
+                (filetime / 10_000_000) as i64 - FILETIME_UNIX_EPOCH_SECS,
+            ),
+            guid,
+            children: bytes[22..]
+                .chunks_exact(5)
+                .map(|chunk| chunk.try_into().unwrap())
+                .collect(),
+        })
+    }
+
+    /// Returns the conversation GUID in its hyphenated hex form
+    pub fn guid_string(&self) -> String {
+        let mut result = String::with_capacity(36);
+        for (pos, byte) in self.guid.iter().enumerate() {
+            if [4, 6, 8, 10].contains(&pos) {
+                result.push('-');
+            }
+            result.push_str(&format!("{byte:02x}"));
+        }
+        result
+    }
+}
+
 pub fn thread_name(text: &str) -> &str {
     let mut token_start = 0;
     let mut token_end = 0;
@@ -220,7 +265,24 @@ pub fn trim_trailing_fwd(text: &str) -> &str {
 
 #[cfg(test)]
 mod tests {
-    use crate::parsers::fields::thread::{thread_name, trim_trailing_fwd};
+    use crate::{
+        parsers::fields::thread::{thread_name, trim_trailing_fwd},
+        ThreadIndex,
+    };
+
+    #[test]
+    fn parse_thread_index() {
+        let index = ThreadIndex::parse(b"AdfeXQowAAECAwQFBgcICQoLDA0ODwECAwQF").unwrap();
+        assert_eq!(index.date.to_rfc3339(), "2021-11-20T22:22:01Z");
+        assert_eq!(index.guid_string(), "00010203-0405-0607-0809-0a0b0c0d0e0f");
+        assert_eq!(index.children, vec![[1, 2, 3, 4, 5]]);
+
+        let index = ThreadIndex::parse(b"AdfeXQowAAECAwQFBgcICQoLDA0ODw==").unwrap();
+        assert!(index.children.is_empty());
+
+        assert_eq!(ThreadIndex::parse(b"AdfeXQowAAECAwQFBgcI"), None);
+        assert_eq!(ThreadIndex::parse(b"not base64!"), None);
+    }
 
     #[test]
     fn parse_thread_name() {
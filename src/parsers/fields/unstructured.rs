@@ -45,6 +45,10 @@ impl<'x> UnstructuredParser<'x> {
 
 impl<'x> MessageStream<'x> {
     pub fn parse_unstructured(&mut self) -> HeaderValue<'x> {
+        if let Some(value) = self.parse_unstructured_ascii() {
+            return value;
+        }
+
         let mut parser = UnstructuredParser {
             token_start: 0,
             token_end: 0,
@@ -58,11 +62,16 @@ impl<'x> MessageStream<'x> {
                     parser.add_token(self);
 
                     if !self.try_next_is_space() {
-                        return match parser.tokens.len() {
-                            1 => HeaderValue::Text(parser.tokens.pop().unwrap()),
-                            0 => HeaderValue::Empty,
-                            _ => HeaderValue::Text(parser.tokens.concat().into()),
+                        let value = match parser.tokens.len() {
+                            1 => parser.tokens.pop().unwrap(),
+                            0 => return HeaderValue::Empty,
+                            _ => parser.tokens.concat().into(),
                         };
+                        return HeaderValue::Text(if self.collapse_whitespace {
+                            collapse_whitespace(value)
+                        } else {
+                            value
+                        });
                     } else {
                         continue;
                     }
@@ -91,11 +100,65 @@ impl<'x> MessageStream<'x> {
 
         HeaderValue::Empty
     }
+
+    /// Fast path for the common single line, pure ASCII values without
+    /// encoded-words, which are borrowed from the message without going
+    /// through the tokenizer.
+    fn parse_unstructured_ascii(&mut self) -> Option<HeaderValue<'x>> {
+        let data = self.data.get(self.offset()..)?;
+        let line_len = data.iter().position(|&ch| ch == b'\n')?;
+        let line = &data[..line_len];
+        if matches!(data.get(line_len + 1), Some(b' ' | b'\t'))
+            || !line.is_ascii()
+            || line.windows(2).any(|bytes| bytes == b"=?")
+        {
+            return None;
+        }
+
+        let value = std::str::from_utf8(line)
+            .ok()?
+            .trim_matches(&[' ', '\t', '\r'][..]);
+        self.skip_bytes(line_len + 1);
+
+        Some(if value.is_empty() {
+            HeaderValue::Empty
+        } else if self.collapse_whitespace {
+            HeaderValue::Text(collapse_whitespace(value.into()))
+        } else {
+            HeaderValue::Text(value.into())
+        })
+    }
+}
+
+fn collapse_whitespace(value: Cow<'_, str>) -> Cow<'_, str> {
+    if !value.contains('\t') && !value.contains("  ") {
+        return value;
+    }
+
+    let mut result = String::with_capacity(value.len());
+    let mut last_is_space = false;
+    for ch in value.chars() {
+        if matches!(ch, ' ' | '\t') {
+            if !last_is_space {
+                result.push(' ');
+                last_is_space = true;
+            }
+        } else {
+            result.push(ch);
+            last_is_space = false;
+        }
+    }
+    result.into()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parsers::{fields::load_tests, MessageStream};
+    use std::borrow::Cow;
+
+    use crate::{
+        parsers::{fields::load_tests, MessageStream},
+        HeaderValue,
+    };
 
     #[test]
     fn parse_unstructured() {
@@ -110,4 +173,38 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn parse_unstructured_borrowed() {
+        for (input, expected, is_borrowed) in [
+            ("Saying Hello\r\nFrom: ", "Saying Hello", true),
+            ("  Saying  Hello \t\nFrom: ", "Saying  Hello", true),
+            ("Saying\r\n Hello\r\nFrom: ", "Saying Hello", false),
+            ("=?utf-8?q?Saying_Hello?=\r\nFrom: ", "Saying Hello", false),
+            ("Saying Hellö\r\nFrom: ", "Saying Hellö", true),
+        ] {
+            let mut stream = MessageStream::new(input.as_bytes());
+            let value = stream.parse_unstructured();
+            assert_eq!(
+                stream.offset(),
+                input.find("From").unwrap(),
+                "failed for {input:?}"
+            );
+            let HeaderValue::Text(text) = value else {
+                panic!("failed for {input:?}: {value:?}");
+            };
+            assert_eq!(text, expected, "failed for {input:?}");
+            assert_eq!(
+                matches!(text, Cow::Borrowed(_)),
+                is_borrowed,
+                "failed for {input:?}"
+            );
+        }
+
+        for input in [" \r\nFrom: ", "\nFrom: "] {
+            let mut stream = MessageStream::new(input.as_bytes());
+            assert_eq!(stream.parse_unstructured(), HeaderValue::Empty);
+            assert_eq!(stream.offset(), input.find("From").unwrap());
+        }
+    }
 }
@@ -11,12 +11,15 @@
 
 use std::borrow::Cow;
 
-use crate::{Header, HeaderName, MessageParser};
+use crate::{Address, HdrParseFnc, Header, HeaderName, HeaderValue, MessageParser};
 
 use super::MessageStream;
 
 impl<'x> MessageStream<'x> {
     pub fn parse_headers(&mut self, conf: &MessageParser, headers: &mut Vec<Header<'x>>) -> bool {
+        self.collapse_whitespace = conf.collapse_header_whitespace;
+        self.semicolon_address_separator = conf.semicolon_address_separator;
+
         loop {
             loop {
                 match self.peek() {
@@ -36,50 +39,26 @@ impl<'x> MessageStream<'x> {
 
             let offset_field = self.offset();
 
-            if let Some(header_name) = self.parse_header_name() {
-                let from_offset = self.offset();
-                let value = if conf.header_map.is_empty() {
-                    match &header_name {
-                        HeaderName::Subject
-                        | HeaderName::Comments
-                        | HeaderName::ContentDescription
-                        | HeaderName::ContentLocation
-                        | HeaderName::ContentTransferEncoding => self.parse_unstructured(),
-                        HeaderName::From
-                        | HeaderName::To
-                        | HeaderName::Cc
-                        | HeaderName::Bcc
-                        | HeaderName::ReplyTo
-                        | HeaderName::Sender
-                        | HeaderName::ResentTo
-                        | HeaderName::ResentFrom
-                        | HeaderName::ResentBcc
-                        | HeaderName::ResentCc
-                        | HeaderName::ResentSender
-                        | HeaderName::ListArchive
-                        | HeaderName::ListHelp
-                        | HeaderName::ListId
-                        | HeaderName::ListOwner
-                        | HeaderName::ListPost
-                        | HeaderName::ListSubscribe
-                        | HeaderName::ListUnsubscribe => self.parse_address(),
-                        HeaderName::Date | HeaderName::ResentDate => self.parse_date(),
-                        HeaderName::MessageId
-                        | HeaderName::References
-                        | HeaderName::InReplyTo
-                        | HeaderName::ReturnPath
-                        | HeaderName::ContentId
-                        | HeaderName::ResentMessageId => self.parse_id(),
-                        HeaderName::Keywords | HeaderName::ContentLanguage => {
-                            self.parse_comma_separared()
-                        }
-                        HeaderName::Received => self.parse_received(),
-                        HeaderName::MimeVersion => self.parse_raw(),
-                        HeaderName::ContentType | HeaderName::ContentDisposition => {
-                            self.parse_content_type()
+            let header_name = if conf.tolerant_headers {
+                self.parse_header_name_semicolon()
+            } else {
+                None
+            };
+
+            if let Some(mut header_name) = header_name.or_else(|| self.parse_header_name()) {
+                if conf.lowercase_other_headers {
+                    if let HeaderName::Other(name) = &mut header_name {
+                        if name.bytes().any(|ch| ch.is_ascii_uppercase()) {
+                            *name = name.to_ascii_lowercase().into();
                         }
-                        HeaderName::Other(_) => self.parse_raw(),
                     }
+                }
+
+                let from_offset = self.offset();
+                let mut value = if conf.raw_dkim_arc && is_dkim_or_arc(&header_name) {
+                    self.parse_raw()
+                } else if conf.header_map.is_empty() {
+                    parser_for(&header_name)(self)
                 } else {
                     (conf
                         .header_map
@@ -87,6 +66,22 @@ impl<'x> MessageStream<'x> {
                         .unwrap_or(&conf.def_hdr_parse_fnc))(self)
                 };
 
+                if let HeaderValue::Address(address) = &mut value {
+                    if !conf.address_comments {
+                        address.merge_comments();
+                    }
+                    if conf.flatten_address_groups {
+                        if let Address::Group(group) = address {
+                            *address = Address::List(
+                                std::mem::take(group)
+                                    .into_iter()
+                                    .flat_map(|group| group.addresses)
+                                    .collect(),
+                            );
+                        }
+                    }
+                }
+
                 headers.push(Header {
                     name: header_name,
                     value,
@@ -147,7 +142,7 @@ impl<'x> MessageStream<'x> {
         if token_start != 0 {
             let field = self.bytes(token_start - 1..token_end);
 
-            if (2..=25).contains(&token_len) {
+            if (2..=27).contains(&token_len) {
                 token_hash += token_len + {
                     #[cfg(feature = "ludicrous_mode")]
                     unsafe {
@@ -171,6 +166,26 @@ impl<'x> MessageStream<'x> {
             None
         }
     }
+
+    /// Parses a known header name followed by `;` instead of `:`, see
+    /// `MessageParser::with_tolerant_headers`. The stream is left untouched
+    /// when the line does not match.
+    fn parse_header_name_semicolon(&mut self) -> Option<HeaderName<'x>> {
+        let line = &self.data[self.offset()..];
+        let pos = line
+            .iter()
+            .position(|&ch| matches!(ch, b':' | b';' | b'\n'))
+            .filter(|&pos| line[pos] == b';')?;
+        let name = std::str::from_utf8(&line[..pos]).ok()?.trim();
+
+        match HeaderName::parse(name)? {
+            HeaderName::Other(_) => None,
+            header_name => {
+                self.skip_bytes(pos + 1);
+                Some(header_name.into_owned())
+            }
+        }
+    }
 }
 
 impl<'x> HeaderName<'x> {
@@ -200,7 +215,7 @@ impl<'x> HeaderName<'x> {
             }
         }
 
-        if (2..=25).contains(&data.len()) {
+        if (2..=27).contains(&data.len()) {
             token_hash += data.len() + {
                 #[cfg(feature = "ludicrous_mode")]
                 unsafe {
@@ -228,12 +243,74 @@ impl<'x> HeaderName<'x> {
     }
 }
 
+/// Returns the parser used for a header field when no custom parsers are
+/// configured with `MessageParser::header_*`.
+pub(crate) fn parser_for(name: &HeaderName<'_>) -> HdrParseFnc {
+    match name {
+        HeaderName::Subject
+        | HeaderName::Comments
+        | HeaderName::ContentDescription
+        | HeaderName::ContentLocation
+        | HeaderName::ContentBase
+        | HeaderName::ContentDuration
+        | HeaderName::ContentTransferEncoding
+        | HeaderName::Organization
+        | HeaderName::UserAgent
+        | HeaderName::XMailer => |s| s.parse_unstructured(),
+        HeaderName::From
+        | HeaderName::To
+        | HeaderName::Cc
+        | HeaderName::Bcc
+        | HeaderName::ReplyTo
+        | HeaderName::Sender
+        | HeaderName::ResentTo
+        | HeaderName::ResentFrom
+        | HeaderName::ResentBcc
+        | HeaderName::ResentCc
+        | HeaderName::ResentSender
+        | HeaderName::ListArchive
+        | HeaderName::ListHelp
+        | HeaderName::ListId
+        | HeaderName::ListOwner
+        | HeaderName::ListPost
+        | HeaderName::ListSubscribe
+        | HeaderName::ListUnsubscribe
+        | HeaderName::DispositionNotificationTo
+        | HeaderName::ReturnReceiptTo
+        | HeaderName::DeliveredTo
+        | HeaderName::XOriginalTo => |s| s.parse_address_with_comments(),
+        HeaderName::Date | HeaderName::ResentDate => |s| s.parse_date(),
+        HeaderName::MessageId
+        | HeaderName::References
+        | HeaderName::InReplyTo
+        | HeaderName::ReturnPath
+        | HeaderName::ContentId
+        | HeaderName::ResentMessageId => |s| s.parse_id(),
+        HeaderName::Keywords | HeaderName::ContentLanguage => |s| s.parse_comma_separared(),
+        HeaderName::Received => |s| s.parse_received(),
+        HeaderName::MimeVersion | HeaderName::Face | HeaderName::XFace => |s| s.parse_raw(),
+        HeaderName::ContentType | HeaderName::ContentDisposition => |s| s.parse_content_type(),
+        HeaderName::Other(_) => |s| s.parse_raw(),
+    }
+}
+
+fn is_dkim_or_arc(name: &HeaderName<'_>) -> bool {
+    if let HeaderName::Other(name) = name {
+        name.eq_ignore_ascii_case("DKIM-Signature")
+            || name
+                .get(..4)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("ARC-"))
+    } else {
+        false
+    }
+}
+
 static HDR_HASH: &[u8] = &[
     73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
+    73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 9, 73, 73,
     73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
     73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
-    73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
-    73, 0, 20, 5, 0, 0, 25, 0, 5, 20, 73, 25, 25, 30, 10, 10, 5, 73, 0, 0, 15, 73, 73, 73, 73, 20,
+    73, 4, 20, 5, 0, 0, 12, 11, 5, 20, 73, 25, 25, 30, 10, 21, 5, 73, 0, 0, 15, 5, 73, 73, 1, 20,
     73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
     73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
     73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73, 73,
@@ -246,69 +323,69 @@ static HDR_MAP: &[HeaderName<'_>] = &[
     HeaderName::Date,
     HeaderName::MimeVersion, // Invalid
     HeaderName::Sender,
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::XFace,
     HeaderName::Received,
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::XMailer,
     HeaderName::References,
     HeaderName::MimeVersion, // Invalid
     HeaderName::Cc,
     HeaderName::Comments,
     HeaderName::ResentCc,
     HeaderName::ContentId,
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::Face,
     HeaderName::ResentMessageId,
-    HeaderName::ReplyTo,
-    HeaderName::ResentTo,
+    HeaderName::MimeVersion, // Invalid
+    HeaderName::MimeVersion, // Invalid
     HeaderName::ResentBcc,
-    HeaderName::ContentLanguage,
+    HeaderName::ContentBase,
     HeaderName::Subject,
     HeaderName::ResentSender,
     HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::ContentLanguage,
     HeaderName::ResentDate,
-    HeaderName::To,
-    HeaderName::Bcc,
     HeaderName::MimeVersion, // Invalid
-    HeaderName::ContentTransferEncoding,
+    HeaderName::Bcc,
+    HeaderName::ReplyTo,
+    HeaderName::ResentTo,
     HeaderName::ReturnPath,
     HeaderName::ListId,
     HeaderName::Keywords,
     HeaderName::ContentDescription,
     HeaderName::ListOwner,
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::ContentDuration,
     HeaderName::ContentType,
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::To,
     HeaderName::ListHelp,
     HeaderName::MessageId,
-    HeaderName::ContentLocation,
-    HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::ContentTransferEncoding,
+    HeaderName::DeliveredTo,
+    HeaderName::ReturnReceiptTo,
     HeaderName::ListSubscribe,
-    HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::UserAgent,
+    HeaderName::From,
     HeaderName::MimeVersion, // Invalid
     HeaderName::MimeVersion, // Invalid
     HeaderName::ListPost,
     HeaderName::MimeVersion, // Invalid
-    HeaderName::ResentFrom,
     HeaderName::MimeVersion, // Invalid
+    HeaderName::ContentLocation,
     HeaderName::MimeVersion, // Invalid
     HeaderName::ContentDisposition,
     HeaderName::MimeVersion, // Invalid
-    HeaderName::InReplyTo,
+    HeaderName::MimeVersion, // Invalid
     HeaderName::ListArchive,
     HeaderName::MimeVersion, // Invalid
-    HeaderName::From,
     HeaderName::MimeVersion, // Invalid
+    HeaderName::XOriginalTo,
     HeaderName::ListUnsubscribe,
+    HeaderName::ResentFrom,
+    HeaderName::Organization,
     HeaderName::MimeVersion, // Invalid
     HeaderName::MimeVersion, // Invalid
     HeaderName::MimeVersion, // Invalid
+    HeaderName::InReplyTo,
     HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
-    HeaderName::MimeVersion, // Invalid
+    HeaderName::DispositionNotificationTo,
     HeaderName::MimeVersion, // Invalid
     HeaderName::MimeVersion, // Invalid
     HeaderName::MimeVersion,
@@ -318,69 +395,69 @@ static HDR_NAMES: &[&[u8]] = &[
     b"date",
     b"",
     b"sender",
-    b"",
+    b"x-face",
     b"received",
-    b"",
+    b"x-mailer",
     b"references",
     b"",
     b"cc",
     b"comments",
     b"resent-cc",
     b"content-id",
-    b"",
+    b"face",
     b"resent-message-id",
-    b"reply-to",
-    b"resent-to",
+    b"",
+    b"",
     b"resent-bcc",
-    b"content-language",
+    b"content-base",
     b"subject",
     b"resent-sender",
     b"",
-    b"",
+    b"content-language",
     b"resent-date",
-    b"to",
-    b"bcc",
     b"",
-    b"content-transfer-encoding",
+    b"bcc",
+    b"reply-to",
+    b"resent-to",
     b"return-path",
     b"list-id",
     b"keywords",
     b"content-description",
     b"list-owner",
-    b"",
+    b"content-duration",
     b"content-type",
-    b"",
+    b"to",
     b"list-help",
     b"message-id",
-    b"content-location",
-    b"",
-    b"",
+    b"content-transfer-encoding",
+    b"delivered-to",
+    b"return-receipt-to",
     b"list-subscribe",
-    b"",
-    b"",
+    b"user-agent",
+    b"from",
     b"",
     b"",
     b"list-post",
     b"",
-    b"resent-from",
     b"",
+    b"content-location",
     b"",
     b"content-disposition",
     b"",
-    b"in-reply-to",
+    b"",
     b"list-archive",
     b"",
-    b"from",
     b"",
+    b"x-original-to",
     b"list-unsubscribe",
+    b"resent-from",
+    b"organization",
     b"",
     b"",
     b"",
+    b"in-reply-to",
     b"",
-    b"",
-    b"",
-    b"",
-    b"",
+    b"disposition-notification-to",
     b"",
     b"",
     b"mime-version",
@@ -388,7 +465,7 @@ static HDR_NAMES: &[&[u8]] = &[
 
 #[cfg(test)]
 mod tests {
-    use crate::{parsers::MessageStream, HeaderName};
+    use crate::{parsers::MessageStream, Address, HeaderName, MessageParser};
 
     #[test]
     fn header_name_parse() {
@@ -403,6 +480,21 @@ mod tests {
             (" T : ", HeaderName::Other("T".into())),
             ("mal formed: ", HeaderName::Other("mal formed".into())),
             ("MIME-version : ", HeaderName::MimeVersion),
+            ("Content-Duration: ", HeaderName::ContentDuration),
+            (
+                "Disposition-Notification-To: ",
+                HeaderName::DispositionNotificationTo,
+            ),
+            ("return-receipt-to: ", HeaderName::ReturnReceiptTo),
+            ("Organization: ", HeaderName::Organization),
+            ("User-Agent: ", HeaderName::UserAgent),
+            ("X-Mailer: ", HeaderName::XMailer),
+            ("Content-Base: ", HeaderName::ContentBase),
+            ("Delivered-To: ", HeaderName::DeliveredTo),
+            ("x-original-to: ", HeaderName::XOriginalTo),
+            ("Face: ", HeaderName::Face),
+            ("X-Face: ", HeaderName::XFace),
+            ("X-Mailers: ", HeaderName::Other("X-Mailers".into())),
         ];
 
         for (input, expected_result) in inputs {
@@ -415,4 +507,186 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn lowercase_other_headers() {
+        let input = concat!(
+            "From: john@example.com\n",
+            "X-Custom-Field: value\n",
+            "x-lower: value\n",
+            "\n",
+            "Body\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(
+            message
+                .headers()
+                .iter()
+                .map(|h| h.name.as_str())
+                .collect::<Vec<_>>(),
+            ["From", "X-Custom-Field", "x-lower"]
+        );
+
+        let message = MessageParser::default()
+            .with_lowercase_other_headers(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(
+            message
+                .headers()
+                .iter()
+                .map(|h| h.name.as_str())
+                .collect::<Vec<_>>(),
+            ["From", "x-custom-field", "x-lower"]
+        );
+        assert_eq!(message.header_raw("X-Custom-Field"), Some(" value\n"));
+    }
+
+    #[test]
+    fn flatten_address_groups() {
+        let input = concat!(
+            "From: john@example.com\n",
+            "To: Friends: jane@example.com, Bob <bob@example.com>;, ",
+            "carol@example.com, Empty:;\n",
+            "Cc: Undisclosed recipients:;\n",
+            "\n",
+            "Body\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert!(matches!(message.to(), Some(Address::Group(_))));
+
+        for parser in [
+            MessageParser::default(),
+            MessageParser::new().with_minimal_headers(),
+        ] {
+            let message = parser
+                .with_flatten_address_groups(true)
+                .parse(input)
+                .unwrap();
+            let to = message.to().unwrap().as_list().unwrap();
+            assert_eq!(
+                to.iter()
+                    .map(|addr| addr.address().unwrap())
+                    .collect::<Vec<_>>(),
+                ["jane@example.com", "bob@example.com", "carol@example.com"]
+            );
+            assert_eq!(to[1].name(), Some("Bob"));
+            assert_eq!(message.cc().unwrap().as_list(), Some(&[][..]));
+            assert!(message.from().unwrap().as_list().is_some());
+        }
+    }
+
+    #[test]
+    fn without_dkim_arc() {
+        let signature = format!(
+            "v=1; a=rsa-sha256; d=example.com; s=sel; =?utf-8?q?not_a_word?=;\n b={}",
+            "QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVo=\n\t".repeat(500)
+        );
+        let input = format!(
+            concat!(
+                "DKIM-Signature: {}\n",
+                "ARC-Seal: i=1; cv=none; =?utf-8?q?raw?=\n",
+                "X-Other: =?utf-8?q?decoded?=\n",
+                "Subject: =?utf-8?q?decoded?=\n",
+                "\n",
+                "Body\n"
+            ),
+            signature
+        );
+
+        let message = MessageParser::default()
+            .with_minimal_headers()
+            .default_header_text()
+            .without_dkim_arc()
+            .parse(&input)
+            .unwrap();
+        assert_eq!(
+            message.header("DKIM-Signature").unwrap().as_text(),
+            Some(signature.trim_end())
+        );
+        assert_eq!(
+            message.header("ARC-Seal").unwrap().as_text(),
+            Some("i=1; cv=none; =?utf-8?q?raw?=")
+        );
+        assert_eq!(
+            message.header("X-Other").unwrap().as_text(),
+            Some("decoded")
+        );
+        assert_eq!(message.subject(), Some("decoded"));
+
+        let message = MessageParser::default()
+            .with_minimal_headers()
+            .default_header_text()
+            .parse(&input)
+            .unwrap();
+        assert_eq!(
+            message.header("ARC-Seal").unwrap().as_text(),
+            Some("i=1; cv=none; raw")
+        );
+    }
+
+    #[test]
+    fn tolerant_headers() {
+        let input = concat!(
+            "From: jdoe@example.com\r\n",
+            "Subject; Hello world\r\n",
+            "X-Foo: a;b\r\n",
+            "X-Bar; c\r\n",
+            "Keywords: one; two\r\n",
+            "\r\n",
+            "Body\r\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(message.subject(), None);
+
+        let message = MessageParser::default()
+            .with_tolerant_headers(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.subject(), Some("Hello world"));
+        assert_eq!(
+            message.from().unwrap().first().unwrap().address(),
+            Some("jdoe@example.com")
+        );
+        assert_eq!(message.header("X-Foo").unwrap().as_text(), Some("a;b"));
+        assert_eq!(message.header("X-Bar"), None);
+        assert_eq!(message.keywords(), ["one; two"]);
+        assert_eq!(message.body_text(0).unwrap(), "Body\r\n");
+    }
+
+    #[test]
+    fn collapse_header_whitespace() {
+        let input = concat!(
+            "Subject: Weekly   report\r\n",
+            "\t\tfor\t Q3  =?utf-8?q?r=C3=A9sum=C3=A9__final?=\r\n",
+            "Comments: single spaces\r\n",
+            "X-Custom: a   b\r\n",
+            "\r\n",
+            "Body\r\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(
+            message.subject(),
+            Some("Weekly   report for\t Q3 résumé  final")
+        );
+
+        let message = MessageParser::default()
+            .with_collapse_header_whitespace(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.subject(), Some("Weekly report for Q3 résumé final"));
+        assert_eq!(message.comments(), ["single spaces"]);
+        assert_eq!(message.header("X-Custom").unwrap().as_text(), Some("a   b"));
+
+        let message = MessageParser::default()
+            .header_text("X-Custom")
+            .with_collapse_header_whitespace(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.header("X-Custom").unwrap().as_text(), Some("a b"));
+    }
 }
@@ -9,24 +9,94 @@
  * except according to those terms.
  */
 
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::ControlFlow};
 
 use crate::{
-    decoders::{charsets::map::charset_decoder, DecodeFnc},
-    ContentType, Encoding, GetHeader, HeaderName, HeaderValue, Message, MessageParser, MessagePart,
-    MessagePartId, PartType,
+    decoders::{
+        base64::{base64_decode, base64_decode_lenient},
+        charsets::{
+            map::{charset_decoder, charset_name},
+            DecoderFnc,
+        },
+        html::html_to_text,
+        quoted_printable::quoted_printable_decode,
+        DecodeFnc,
+    },
+    ContentType, DuplicateHeaderPolicy, Encoding, Header, HeaderName, HeaderValue, LazyMessage,
+    LineEnding, Message, MessageParser, MessagePart, MessagePartId, MessageVisitor, PartType,
 };
 
 use super::MessageStream;
 
+#[cfg(feature = "compression")]
+use crate::{decoders::compression::decompress, GetHeader};
+
+// Moves the headers of a part out of the parse buffer. Parts passed to a
+// visitor are only kept to track the part ids, their headers are dropped so
+// that memory does not grow with the header tree.
+fn take_headers<'x>(headers: &mut Vec<Header<'x>>, is_visit: bool) -> Vec<Header<'x>> {
+    if is_visit {
+        headers.clear();
+        Vec::new()
+    } else {
+        std::mem::take(headers)
+    }
+}
+
+// Flags a message cut off at the end of its raw buffer and the parts that
+// reach that end, descending into the nested messages they hold
+fn mark_incomplete(message: &mut Message<'_>) {
+    let len = message.raw_message.len();
+    message.is_incomplete = true;
+    for part in &mut message.parts {
+        if part.offset_end >= len {
+            part.is_incomplete = true;
+            if let PartType::Message(nested) = &mut part.body {
+                mark_incomplete(nested);
+            }
+        }
+    }
+}
+
+// Replaces the HTML parts listed as text bodies, which is how HTML-only
+// messages are represented, with synthetic text parts converted from them.
+fn generate_text_bodies(message: &mut Message<'_>) {
+    for part in &mut message.parts {
+        if let PartType::Message(nested) = &mut part.body {
+            generate_text_bodies(nested);
+        }
+    }
+
+    for pos in 0..message.text_body.len() {
+        let part = &message.parts[message.text_body[pos]];
+        if let PartType::Html(html) = &part.body {
+            let text_part = MessagePart {
+                headers: Vec::new(),
+                is_encoding_problem: false,
+                body: PartType::Text(html_to_text(html).into()),
+                encoding: Encoding::None,
+                is_synthetic: true,
+                is_incomplete: false,
+                is_undecoded: false,
+                offset_header: part.offset_header,
+                offset_body: part.offset_body,
+                offset_end: part.offset_end,
+            };
+            message.text_body[pos] = message.parts.len();
+            message.parts.push(text_part);
+        }
+    }
+}
+
 const MAX_NESTED_ENCODED: usize = 3;
 
-#[derive(Debug, PartialEq, Default)]
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
 enum MimeType {
     MultipartMixed,
     MultipartAlternative,
     MultipartRelated,
     MultipartDigest,
+    MultipartAppleDouble,
     TextPlain,
     TextHtml,
     TextOther,
@@ -52,6 +122,7 @@ fn mime_type(
                     Some("alternative") => MimeType::MultipartAlternative,
                     Some("related") => MimeType::MultipartRelated,
                     Some("digest") => MimeType::MultipartDigest,
+                    Some("appledouble") => MimeType::MultipartAppleDouble,
                     _ => MimeType::Other,
                 },
             ),
@@ -73,6 +144,109 @@ fn mime_type(
     }
 }
 
+// Detects bodies declared as 7bit or 8bit that are actually base64 (lines
+// made of base64 characters only, the first one at least 40 characters long)
+// or quoted-printable (only valid escapes and soft line breaks, at least two
+// of them) and decodes them.
+fn detect_transfer_encoding(bytes: &[u8]) -> Option<(Encoding, Vec<u8>)> {
+    let mut lines = bytes
+        .split(|&ch| ch == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .filter(|line| !line.is_empty())
+        .peekable();
+
+    if lines.peek().is_some_and(|line| line.len() >= 40) {
+        let mut is_base64 = true;
+        let mut has_padding = false;
+        let mut len = 0;
+
+        for line in lines {
+            let data = &line[..line
+                .iter()
+                .rposition(|&ch| ch != b'=')
+                .map_or(0, |pos| pos + 1)];
+            if has_padding
+                || line.len() - data.len() > 2
+                || !data
+                    .iter()
+                    .all(|ch| ch.is_ascii_alphanumeric() || [b'+', b'/'].contains(ch))
+            {
+                is_base64 = false;
+                break;
+            }
+            has_padding = data.len() != line.len();
+            len += line.len();
+        }
+
+        if is_base64 && len % 4 == 0 {
+            return base64_decode(bytes).map(|decoded| (Encoding::Base64, decoded));
+        }
+    }
+
+    let mut escapes = 0;
+    for (pos, _) in bytes.iter().enumerate().filter(|(_, &ch)| ch == b'=') {
+        match bytes.get(pos + 1..) {
+            Some([b'\n', ..] | [b'\r', b'\n', ..] | []) => (),
+            Some([hex1, hex2, ..])
+                if [hex1, hex2]
+                    .iter()
+                    .all(|ch| ch.is_ascii_digit() || (b'A'..=b'F').contains(ch)) => {}
+            _ => return None,
+        }
+        escapes += 1;
+    }
+
+    if escapes >= 2 {
+        quoted_printable_decode(bytes).map(|decoded| (Encoding::QuotedPrintable, decoded))
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseMode {
+    Full,
+    Headers,
+    Lazy,
+    /// Full parse of a message cut off at the end of the input
+    Prefix,
+}
+
+// Passes the parts found by the parser to a `MessageVisitor`
+struct VisitorSink<'a, 'x> {
+    visitor: &'a mut dyn MessageVisitor<'x>,
+    is_stopped: bool,
+}
+
+impl<'x> VisitorSink<'_, 'x> {
+    fn visit_part(
+        &mut self,
+        part_id: MessagePartId,
+        headers: &[Header<'x>],
+        content_type: Option<&ContentType<'x>>,
+    ) -> Option<()> {
+        let result = headers
+            .iter()
+            .try_for_each(|header| self.visitor.on_header(part_id, header));
+        let result = if result.is_continue() {
+            self.visitor.on_part_start(part_id, content_type)
+        } else {
+            result
+        };
+        self.check(result)
+    }
+
+    fn visit_body(&mut self, part_id: MessagePartId, bytes: &[u8]) -> Option<()> {
+        let result = self.visitor.on_body_chunk(part_id, bytes);
+        self.check(result)
+    }
+
+    fn check(&mut self, result: ControlFlow<()>) -> Option<()> {
+        self.is_stopped = result.is_break();
+        (!self.is_stopped).then_some(())
+    }
+}
+
 #[derive(Default, Debug)]
 struct MessageParserState {
     mime_type: MimeType,
@@ -113,56 +287,317 @@ impl MessageParser {
     /// This function never panics, a best-effort is made to parse the message and
     /// if no headers are found None is returned.
     ///
+    /// Every delimiter of a multipart body starts a part, so consecutive
+    /// delimiters such as `--b\r\n--b\r\n` yield empty `text/plain` parts
+    /// with no headers. These are kept to preserve part numbering (as used
+    /// by IMAP `BODYSTRUCTURE`).
+    ///
     pub fn parse<'x>(&self, raw_message: impl IntoByteSlice<'x>) -> Option<Message<'x>> {
-        self.parse_(raw_message.into_byte_slice(), MAX_NESTED_ENCODED, false)
+        self.parse_(
+            self.skip_mbox_from_line(raw_message.into_byte_slice()),
+            MAX_NESTED_ENCODED,
+            ParseMode::Full,
+            &mut 0,
+        )
     }
 
     /// Parses a byte slice containing the RFC5322 raw message and returns a
     /// `Message` struct containing only the headers.
+    ///
+    /// Parsing stops at the first empty line, which may be omitted when the
+    /// input is a bare header block with no body.
     pub fn parse_headers<'x>(
         &self,
         raw_message: impl IntoByteSlice<'x> + 'x,
     ) -> Option<Message<'x>> {
-        self.parse_(raw_message.into_byte_slice(), MAX_NESTED_ENCODED, true)
+        self.parse_(
+            self.skip_mbox_from_line(raw_message.into_byte_slice()),
+            MAX_NESTED_ENCODED,
+            ParseMode::Headers,
+            &mut 0,
+        )
+    }
+
+    /// Parses a byte slice containing the RFC5322 raw message and returns a
+    /// `LazyMessage` that decodes part bodies only when requested.
+    ///
+    /// This is faster than `parse` when most bodies are never looked at, for
+    /// example when scanning the headers of large messages. Base64 and
+    /// quoted-printable encoded nested messages are decoded to parse their
+    /// structure, their parts are decoded on request as well. The
+    /// `with_autodetect_cte`, `with_decode_composite_cte` and
+    /// `with_generate_text_from_html` options do not apply and, with the
+    /// `compression` feature, parts are decompressed on request.
+    ///
+    /// The leaf parts are flagged with `MessagePart::is_undecoded` and the
+    /// body accessors of `Message` and `MessagePart` return `None` for them,
+    /// see `LazyMessage`.
+    pub fn parse_lazy<'x>(&self, raw_message: impl IntoByteSlice<'x>) -> Option<LazyMessage<'x>> {
+        self.parse_(
+            self.skip_mbox_from_line(raw_message.into_byte_slice()),
+            MAX_NESTED_ENCODED,
+            ParseMode::Lazy,
+            &mut 0,
+        )
+        .map(|message| LazyMessage {
+            message,
+            line_ending: self.line_ending,
+            #[cfg(feature = "compression")]
+            max_decompressed_size: self.max_decompressed_size,
+            unknown_charset_fallback: self.unknown_charset_fallback,
+        })
+    }
+
+    /// Parses the first bytes of a message that is `total_len` bytes long,
+    /// for example to preview a large message without downloading its
+    /// attachments. When the prefix is shorter than `total_len` the message
+    /// is flagged with `Message::is_incomplete` and the parts that were cut
+    /// off, the last part and its ancestors, with `MessagePart::is_incomplete`.
+    /// Nested messages held by these parts are flagged the same way. The
+    /// bodies are decoded up to the end of the prefix.
+    pub fn parse_prefix<'x>(
+        &self,
+        raw_message: impl IntoByteSlice<'x>,
+        total_len: usize,
+    ) -> Option<Message<'x>> {
+        let raw_message = raw_message.into_byte_slice();
+        let is_truncated = raw_message.len() < total_len;
+        let raw_message = self.skip_mbox_from_line(raw_message);
+        let mut message = self.parse_(
+            raw_message,
+            MAX_NESTED_ENCODED,
+            if is_truncated {
+                ParseMode::Prefix
+            } else {
+                ParseMode::Full
+            },
+            &mut 0,
+        )?;
+
+        if is_truncated {
+            mark_incomplete(&mut message);
+        }
+
+        Some(message)
+    }
+
+    /// Parses a byte slice containing the RFC5322 raw message, passing each
+    /// header and part to the visitor instead of building a `Message`.
+    ///
+    /// The parts are found exactly as `parse` does, including the nested
+    /// messages that are not transfer encoded. Base64 and quoted-printable
+    /// encoded nested messages and, with `with_decode_composite_cte`,
+    /// multiparts are not parsed, their decoded contents are passed to
+    /// `MessageVisitor::on_body_chunk` instead.
+    ///
+    /// Returns `ControlFlow::Break` if the visitor stopped the parser.
+    pub fn parse_with_visitor<'x>(
+        &self,
+        raw_message: impl IntoByteSlice<'x>,
+        visitor: &mut impl MessageVisitor<'x>,
+    ) -> ControlFlow<()> {
+        let mut sink = VisitorSink {
+            visitor,
+            is_stopped: false,
+        };
+        self.parse_parts(
+            self.skip_mbox_from_line(raw_message.into_byte_slice()),
+            MAX_NESTED_ENCODED,
+            ParseMode::Full,
+            None,
+            &mut 0,
+            Some(&mut sink),
+        );
+
+        if sink.is_stopped {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    // Parses the decoded body of a base64 or quoted-printable encoded
+    // multipart as the body of a part with the same Content-Type, picking the
+    // body parts as if it was found within the enclosing multipart.
+    fn parse_encoded_composite<'x>(
+        &self,
+        content_type: Option<&ContentType<'x>>,
+        bytes: &'x [u8],
+        parent: &MessageParserState,
+        depth: usize,
+        total_parts: &mut usize,
+    ) -> Option<Message<'static>> {
+        let root = Header {
+            name: HeaderName::ContentType,
+            value: HeaderValue::ContentType(content_type?.clone()),
+            offset_field: 0,
+            offset_start: 0,
+            offset_end: 0,
+        };
+        let state = MessageParserState {
+            mime_type: parent.mime_type,
+            in_alternative: parent.in_alternative,
+            need_html_body: parent.need_html_body,
+            need_text_body: parent.need_text_body,
+            ..MessageParserState::new()
+        };
+
+        self.parse_parts(
+            bytes,
+            depth.checked_sub(1)?,
+            ParseMode::Full,
+            Some((root, state)),
+            total_parts,
+            None,
+        )
+        .filter(|message| {
+            message
+                .parts
+                .first()
+                .is_some_and(|part| part.is_multipart())
+        })
+        .map(|message| message.into_owned())
+    }
+
+    fn is_recurse_content_type(&self, content_type: Option<&ContentType<'_>>) -> bool {
+        content_type.is_some_and(|ct| {
+            self.recurse_content_types.iter().any(|(ctype, subtype)| {
+                ct.ctype().eq_ignore_ascii_case(ctype)
+                    && ct
+                        .subtype()
+                        .is_some_and(|ct_subtype| ct_subtype.eq_ignore_ascii_case(subtype))
+            })
+        })
+    }
+
+    fn skip_mbox_from_line<'x>(&self, raw_message: &'x [u8]) -> &'x [u8] {
+        if self.mbox_from_line && raw_message.starts_with(b"From ") {
+            raw_message
+                .iter()
+                .position(|&ch| ch == b'\n')
+                .map_or(&[], |pos| &raw_message[pos + 1..])
+        } else {
+            raw_message
+        }
     }
 
     fn parse_<'x>(
         &self,
         raw_message: &'x [u8],
         depth: usize,
-        skip_body: bool,
+        mode: ParseMode,
+        total_parts: &mut usize,
     ) -> Option<Message<'x>> {
+        self.parse_parts(raw_message, depth, mode, None, total_parts, None)
+    }
+
+    // Parses a message or, when `root` is set, the body of a part with the
+    // given Content-Type header starting in the given state. The parts
+    // created are added to `total_parts`, which is shared with the nested
+    // parses so that `max_parts` bounds the whole message. When `visitor` is
+    // set the parts are passed to it and their bodies are not kept.
+    fn parse_parts<'x>(
+        &self,
+        raw_message: &'x [u8],
+        depth: usize,
+        mode: ParseMode,
+        root: Option<(Header<'x>, MessageParserState)>,
+        total_parts: &mut usize,
+        mut visitor: Option<&mut VisitorSink<'_, 'x>>,
+    ) -> Option<Message<'x>> {
+        let is_lazy = mode == ParseMode::Lazy;
+        let is_composite = root.is_some();
+        let is_visit = visitor.is_some();
         let mut stream = MessageStream::new(raw_message);
+        stream.case_insensitive_boundaries = self.case_insensitive_boundaries;
+        stream.is_prefix = mode == ParseMode::Prefix;
 
         let mut message = Message::new();
 
-        let mut state = MessageParserState::new();
+        let (mut state, mut root_header) = match root {
+            Some((header, state)) => (state, Some(header)),
+            None => (MessageParserState::new(), None),
+        };
         let mut state_stack = Vec::with_capacity(4);
 
         let mut part_headers = Vec::new();
+        let mut is_truncated = false;
 
         'outer: loop {
-            // Parse headers
+            // Parse headers, a delimiter right after the previous one starts
+            // an empty part that has to be kept for part numbering
             state.offset_header = stream.offset();
-            if !stream.parse_headers(self, &mut part_headers) {
+            let is_root = root_header.is_some();
+            if let Some(header) = root_header.take() {
+                part_headers.push(header);
+            } else if !state
+                .mime_boundary
+                .as_deref()
+                .is_some_and(|boundary| stream.is_boundary_next(boundary))
+                && !stream.parse_headers(self, &mut part_headers)
+            {
                 break;
             }
             state.offset_body = stream.offset();
-            if skip_body {
+            if mode == ParseMode::Headers {
                 break;
             }
 
-            state.parts += 1;
-            state.sub_part_ids.push(message.parts.len());
+            // The root part was already counted by the enclosing parse
+            if !is_root {
+                *total_parts += 1;
+                if *total_parts > self.max_parts {
+                    is_truncated = true;
+                    break;
+                }
+            }
 
-            let content_type = part_headers
-                .header_value(&HeaderName::ContentType)
+            let content_type = self
+                .mime_header(&part_headers, &HeaderName::ContentType)
                 .and_then(|c| c.as_content_type());
+            if let Some(visitor) = visitor.as_mut() {
+                visitor.visit_part(*total_parts - 1, &part_headers, content_type)?;
+            }
+
+            state.parts += 1;
+            state.sub_part_ids.push(message.parts.len());
 
             let (is_multipart, mut is_inline, mut is_text, mut mime_type) =
                 mime_type(content_type, &state.mime_type);
+            if !is_multipart && self.is_recurse_content_type(content_type) {
+                mime_type = MimeType::Message;
+            }
+            let mut is_boundary_missing = false;
 
-            if is_multipart {
+            let (mut encoding, decode_fnc): (Encoding, DecodeFnc<'_>) = match self
+                .mime_header(&part_headers, &HeaderName::ContentTransferEncoding)
+            {
+                Some(HeaderValue::Text(encoding)) if encoding.eq_ignore_ascii_case("base64") => {
+                    (Encoding::Base64, MessageStream::decode_base64_mime)
+                }
+                Some(HeaderValue::Text(encoding))
+                    if encoding.eq_ignore_ascii_case("quoted-printable") =>
+                {
+                    (
+                        Encoding::QuotedPrintable,
+                        MessageStream::decode_quoted_printable_mime,
+                    )
+                }
+                _ => (Encoding::None, MessageStream::mime_part),
+            };
+            // Lazy parts keep their raw body, which is decoded on request.
+            let decode_fnc = if is_lazy {
+                MessageStream::mime_part
+            } else {
+                decode_fnc
+            };
+
+            // RFC 2045 does not allow encoding composite types, by default
+            // the encoding is ignored and the boundaries searched for as is.
+            let is_encoded_composite =
+                is_multipart && self.decode_composite_cte && encoding != Encoding::None;
+
+            if is_multipart && !is_encoded_composite {
                 if let Some(mime_boundary) = content_type.and_then(|f| f.attribute("boundary")) {
                     if stream.seek_next_part(mime_boundary.as_bytes()) {
                         let part_id = message.parts.len();
@@ -180,12 +615,15 @@ impl MessageParser {
                         };
                         //add_missing_type(&mut part_header, "text".into(), "plain".into());
                         message.parts.push(MessagePart {
-                            headers: std::mem::take(&mut part_headers),
+                            headers: take_headers(&mut part_headers, is_visit),
                             offset_header: state.offset_header,
                             offset_body: state.offset_body,
                             offset_end: 0,
                             is_encoding_problem: false,
                             encoding: Encoding::None,
+                            is_synthetic: false,
+                            is_incomplete: false,
+                            is_undecoded: false,
                             body: PartType::default(),
                         });
                         state_stack.push((state, None));
@@ -193,29 +631,21 @@ impl MessageParser {
                         stream.skip_crlf();
                         continue;
                     } else {
+                        // The boundary never appears, keep the remainder as a
+                        // single text part flagged as an encoding problem.
                         mime_type = MimeType::TextOther;
                         is_text = true;
+                        is_boundary_missing = true;
                     }
+                } else {
+                    // Without a boundary parameter the body cannot be split,
+                    // keep it as a single binary part flagged as an encoding
+                    // problem.
+                    mime_type = MimeType::Other;
+                    is_boundary_missing = true;
                 }
             }
 
-            let (mut encoding, decode_fnc): (Encoding, DecodeFnc<'_>) = match part_headers
-                .header_value(&HeaderName::ContentTransferEncoding)
-            {
-                Some(HeaderValue::Text(encoding)) if encoding.eq_ignore_ascii_case("base64") => {
-                    (Encoding::Base64, MessageStream::decode_base64_mime)
-                }
-                Some(HeaderValue::Text(encoding))
-                    if encoding.eq_ignore_ascii_case("quoted-printable") =>
-                {
-                    (
-                        Encoding::QuotedPrintable,
-                        MessageStream::decode_quoted_printable_mime,
-                    )
-                }
-                _ => (Encoding::None, MessageStream::mime_part),
-            };
-
             if mime_type == MimeType::Message && encoding == Encoding::None {
                 let new_state = MessageParserState {
                     mime_type: MimeType::Message,
@@ -227,8 +657,11 @@ impl MessageParser {
                 };
                 message.attachments.push(message.parts.len());
                 message.parts.push(MessagePart {
-                    headers: std::mem::take(&mut part_headers),
+                    headers: take_headers(&mut part_headers, is_visit),
                     encoding,
+                    is_synthetic: false,
+                    is_incomplete: false,
+                    is_undecoded: false,
                     is_encoding_problem: false,
                     offset_header: state.offset_header,
                     offset_body: state.offset_body,
@@ -249,29 +682,133 @@ impl MessageParser {
             // Attempt to recover contents of an invalid message
             let mut is_encoding_problem = offset_end == usize::MAX;
             if is_encoding_problem {
-                encoding = Encoding::None;
-                mime_type = MimeType::TextOther;
-                is_inline = false;
-                is_text = true;
-
                 let (offset_end, boundary_found) =
                     stream.seek_part_end(state.mime_boundary.as_deref());
                 state.offset_end = offset_end;
-                bytes = stream.data[state.offset_body..state.offset_end].into();
+                let raw_bytes = &stream.data[state.offset_body..state.offset_end];
+
+                // Keep whatever can be decoded from corrupted base64, unless
+                // more than one character per line had to be skipped, in
+                // which case the body is most likely not base64 at all.
+                let decoded = (encoding == Encoding::Base64 && !is_multipart && !is_lazy)
+                    .then(|| base64_decode_lenient(raw_bytes))
+                    .filter(|(_, invalid_count)| invalid_count * 76 <= raw_bytes.len());
+
+                if let Some((decoded, _)) = decoded {
+                    bytes = decoded.into();
+                } else {
+                    encoding = Encoding::None;
+                    mime_type = MimeType::TextOther;
+                    is_inline = false;
+                    is_text = true;
+                    bytes = raw_bytes.into();
+                }
 
                 if !boundary_found {
                     state.mime_boundary = None;
                 }
             } else {
                 state.offset_end = offset_end;
+                is_encoding_problem = is_boundary_missing;
+
+                if self.autodetect_cte
+                    && !is_lazy
+                    && encoding == Encoding::None
+                    && !is_multipart
+                    && self
+                        .mime_header(&part_headers, &HeaderName::ContentTransferEncoding)
+                        .and_then(|cte| cte.as_text())
+                        .is_some_and(|cte| {
+                            let cte = cte.trim();
+                            cte.eq_ignore_ascii_case("7bit") || cte.eq_ignore_ascii_case("8bit")
+                        })
+                {
+                    if let Some((detected_encoding, decoded)) = detect_transfer_encoding(&bytes) {
+                        encoding = detected_encoding;
+                        bytes = decoded.into();
+                        is_encoding_problem = true;
+                    }
+                }
+
+                #[cfg(feature = "compression")]
+                if !is_multipart && !is_lazy {
+                    if let Some(result) = part_headers
+                        .header_value(&HeaderName::Other("Content-Encoding".into()))
+                        .and_then(|value| value.as_text())
+                        .and_then(|value| decompress(value, &bytes, self.max_decompressed_size))
+                    {
+                        match result {
+                            Ok(decompressed) => bytes = decompressed.into(),
+                            Err(_) => is_encoding_problem = true,
+                        }
+                    }
+                }
             }
 
-            let body_part = if mime_type != MimeType::Message {
+            if let Some(visitor) = visitor.as_mut() {
+                visitor.visit_body(*total_parts - 1, &bytes)?;
+            }
+
+            let mut composite_parts = Vec::new();
+            let composite = if is_encoded_composite && !is_encoding_problem && !is_lazy && !is_visit
+            {
+                self.parse_encoded_composite(
+                    content_type,
+                    bytes.as_ref(),
+                    &state,
+                    depth,
+                    total_parts,
+                )
+            } else {
+                None
+            };
+
+            let body_part = if is_visit {
+                // Already passed to the visitor
+                PartType::default()
+            } else if let Some(composite) = composite {
+                // Add the decoded parts after the composite part, these have
+                // no raw representation so they and their headers point to
+                // the encoded body.
+                let base = message.parts.len();
+                let mut parts = composite.parts.into_iter();
+                let sub_part_ids = parts.next().map_or_else(Vec::new, |part| match part.body {
+                    PartType::Multipart(ids) => ids.into_iter().map(|id| id + base).collect(),
+                    _ => Vec::new(),
+                });
+                for mut part in parts {
+                    if let PartType::Multipart(ids) = &mut part.body {
+                        ids.iter_mut().for_each(|id| *id += base);
+                    }
+                    part.offset_header = state.offset_body;
+                    part.offset_body = state.offset_body;
+                    part.offset_end = state.offset_end;
+                    for header in &mut part.headers {
+                        header.offset_field = state.offset_body;
+                        header.offset_start = state.offset_body;
+                        header.offset_end = state.offset_body;
+                    }
+                    composite_parts.push(part);
+                }
+
+                is_truncated |= composite.is_truncated;
+                message
+                    .html_body
+                    .extend(composite.html_body.into_iter().map(|id| id + base));
+                message
+                    .text_body
+                    .extend(composite.text_body.into_iter().map(|id| id + base));
+                message
+                    .attachments
+                    .extend(composite.attachments.into_iter().map(|id| id + base));
+
+                PartType::Multipart(sub_part_ids)
+            } else if mime_type != MimeType::Message {
                 let is_inline = is_inline
-                    && part_headers
-                        .header_value(&HeaderName::ContentDisposition)
+                    && self
+                        .mime_header(&part_headers, &HeaderName::ContentDisposition)
                         .map_or(true, |d| {
-                            !d.as_content_type().map_or(false, |ct| ct.is_attachment())
+                            !d.as_content_type().is_some_and(|ct| ct.is_attachment())
                         })
                     && (state.parts == 1
                         || state.mime_type != MimeType::MultipartRelated
@@ -310,36 +847,37 @@ impl MessageParser {
                 }
 
                 if is_text {
-                    let text = match (
-                        bytes,
-                        content_type.and_then(|ct| {
-                            ct.attribute("charset")
-                                .and_then(|c| charset_decoder(c.as_bytes()))
-                        }),
-                    ) {
-                        (Cow::Owned(vec), Some(charset_decoder)) => charset_decoder(&vec).into(),
-                        (Cow::Owned(vec), None) => String::from_utf8(vec)
-                            .unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned())
-                            .into(),
-                        (Cow::Borrowed(bytes), Some(charset_decoder)) => {
-                            charset_decoder(bytes).into()
-                        }
-                        (Cow::Borrowed(bytes), None) => String::from_utf8_lossy(bytes),
-                    };
-
                     let is_html = mime_type == MimeType::TextHtml;
 
                     if !add_to_html && is_html || !add_to_text && !is_html {
                         message.attachments.push(message.parts.len());
                     }
 
-                    if is_html {
-                        PartType::Html(text)
+                    if is_lazy {
+                        PartType::Binary(bytes)
                     } else {
-                        PartType::Text(text)
+                        let text = decode_text(
+                            bytes,
+                            content_type.and_then(|ct| ct.attribute("charset")),
+                            self.unknown_charset_fallback,
+                            self.line_ending,
+                        );
+                        if is_html {
+                            PartType::Html(text)
+                        } else {
+                            PartType::Text(text)
+                        }
                     }
                 } else {
-                    message.attachments.push(message.parts.len());
+                    // The AppleDouble header part only holds Mac metadata for
+                    // the data fork that follows it.
+                    if state.mime_type != MimeType::MultipartAppleDouble
+                        || !content_type.is_some_and(|ct| {
+                            ct.ctype() == "application" && ct.subtype() == Some("applefile")
+                        })
+                    {
+                        message.attachments.push(message.parts.len());
+                    }
 
                     if !is_inline {
                         PartType::Binary(bytes)
@@ -350,9 +888,29 @@ impl MessageParser {
             } else {
                 message.attachments.push(message.parts.len());
 
-                if depth != 0 {
-                    if let Some(nested_message) = self.parse_(bytes.as_ref(), depth - 1, false) {
+                // Lazy bodies are still encoded, nested messages are decoded
+                // to parse their structure but their own parts are not.
+                let decoded = match encoding {
+                    Encoding::Base64 if is_lazy => base64_decode(bytes.as_ref()),
+                    Encoding::QuotedPrintable if is_lazy => quoted_printable_decode(bytes.as_ref()),
+                    _ => None,
+                };
+
+                if depth != 0 && (decoded.is_some() || !is_lazy || encoding == Encoding::None) {
+                    if let Some(nested_message) = self.parse_(
+                        decoded.as_deref().unwrap_or(bytes.as_ref()),
+                        depth - 1,
+                        if is_lazy {
+                            ParseMode::Lazy
+                        } else {
+                            ParseMode::Full
+                        },
+                        total_parts,
+                    ) {
+                        is_truncated |= nested_message.is_truncated;
                         PartType::Message(Message {
+                            is_truncated: nested_message.is_truncated,
+                            is_incomplete: false,
                             html_body: nested_message.html_body,
                             text_body: nested_message.text_body,
                             attachments: nested_message.attachments,
@@ -361,8 +919,12 @@ impl MessageParser {
                                 .into_iter()
                                 .map(|p| p.into_owned())
                                 .collect(),
-                            raw_message: bytes.into_owned().into(),
+                            raw_message: decoded.unwrap_or_else(|| bytes.into_owned()).into(),
                         })
+                    } else if *total_parts > self.max_parts {
+                        // No parts left for the nested message
+                        is_truncated = true;
+                        PartType::Binary(bytes)
                     } else {
                         is_encoding_problem = true;
                         PartType::Binary(bytes)
@@ -374,21 +936,28 @@ impl MessageParser {
             };
 
             // Add part
+            let is_undecoded =
+                is_lazy && matches!(body_part, PartType::Binary(_) | PartType::InlineBinary(_));
             message.parts.push(MessagePart {
-                headers: std::mem::take(&mut part_headers),
+                headers: take_headers(&mut part_headers, is_visit),
                 encoding,
+                is_synthetic: false,
+                is_incomplete: false,
+                is_undecoded,
                 is_encoding_problem,
                 body: body_part,
                 offset_header: state.offset_header,
                 offset_body: state.offset_body,
                 offset_end: state.offset_end,
             });
+            message.parts.append(&mut composite_parts);
 
             if state.mime_boundary.is_some() {
                 // Currently processing a MIME part
                 'inner: loop {
-                    if let MimeType::Message = state.mime_type {
-                        // Finished processing a nested message, restore parent message from stack
+                    while let MimeType::Message = state.mime_type {
+                        // Finished processing a nested message, restore parent message from stack.
+                        // Messages nested directly in a message end together.
                         if let Some((mut prev_state, Some(mut prev_message))) = state_stack.pop() {
                             let offset_end = state
                                 .mime_boundary
@@ -490,6 +1059,7 @@ impl MessageParser {
         while let Some((prev_state, prev_message)) = state_stack.pop() {
             if let Some(mut prev_message) = prev_message {
                 message.raw_message = raw_message.into(); //raw_message[state.offset_header..stream.offset()].as_ref().into();
+                message.is_truncated = is_truncated;
 
                 if let Some(part) = prev_message.parts.get_mut(state.part_id) {
                     part.body = PartType::Message(message);
@@ -509,15 +1079,30 @@ impl MessageParser {
         }
 
         message.raw_message = raw_message.into();
+        message.is_truncated = is_truncated;
 
         if !message.is_empty() {
             message.parts[0].offset_end = message.raw_message.len();
+            if self.generate_text_from_html && !is_lazy && !is_composite && !is_visit {
+                generate_text_bodies(&mut message);
+            }
             Some(message)
-        } else if !part_headers.is_empty() {
+        } else if !part_headers.is_empty() && !is_truncated {
             // Message without a body
+            if let Some(visitor) = visitor.as_mut() {
+                visitor.visit_part(
+                    0,
+                    &part_headers,
+                    self.mime_header(&part_headers, &HeaderName::ContentType)
+                        .and_then(|c| c.as_content_type()),
+                )?;
+            }
             message.parts.push(MessagePart {
-                headers: part_headers,
+                headers: take_headers(&mut part_headers, is_visit),
                 encoding: Encoding::None,
+                is_synthetic: false,
+                is_incomplete: false,
+                is_undecoded: false,
                 is_encoding_problem: true,
                 body: PartType::Text("".into()),
                 offset_header: 0,
@@ -529,6 +1114,26 @@ impl MessageParser {
             None
         }
     }
+
+    // Returns the occurrence of a MIME header selected by the duplicate
+    // header policy
+    fn mime_header<'a, 'x>(
+        &self,
+        headers: &'a [Header<'x>],
+        name: &HeaderName<'_>,
+    ) -> Option<&'a HeaderValue<'x>> {
+        match self.duplicate_header_policy {
+            DuplicateHeaderPolicy::First => headers
+                .iter()
+                .find(|header| &header.name == name)
+                .map(|header| &header.value),
+            DuplicateHeaderPolicy::Last => headers
+                .iter()
+                .rev()
+                .find(|header| &header.name == name)
+                .map(|header| &header.value),
+        }
+    }
 }
 
 impl<'x> Message<'x> {
@@ -544,6 +1149,65 @@ impl<'x> Message<'x> {
     }
 }
 
+/// Decodes a text body using its declared charset, see
+/// `MessageParser::with_unknown_charset_fallback` for bodies with a missing or
+/// unsupported charset that are not valid UTF-8.
+pub(crate) fn decode_text<'x>(
+    bytes: Cow<'x, [u8]>,
+    charset: Option<&str>,
+    fallback: Option<DecoderFnc>,
+    line_ending: LineEnding,
+) -> Cow<'x, str> {
+    let decode_unknown = |bytes: &[u8]| -> String {
+        match fallback {
+            Some(decoder)
+                if charset.map_or(true, |c| charset_name(c.trim().as_bytes()) != Some("UTF-8")) =>
+            {
+                decoder(bytes)
+            }
+            _ => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    };
+    let text = match (bytes, charset.and_then(|c| charset_decoder(c.as_bytes()))) {
+        (Cow::Owned(vec), Some(charset_decoder)) => charset_decoder(&vec).into(),
+        (Cow::Owned(vec), None) => match String::from_utf8(vec) {
+            Ok(text) => text.into(),
+            Err(err) => decode_unknown(err.as_bytes()).into(),
+        },
+        (Cow::Borrowed(bytes), Some(charset_decoder)) => charset_decoder(bytes).into(),
+        (Cow::Borrowed(bytes), None) => match std::str::from_utf8(bytes) {
+            Ok(text) => text.into(),
+            Err(_) => decode_unknown(bytes).into(),
+        },
+    };
+    normalize_eol(text, line_ending)
+}
+
+fn normalize_eol(text: Cow<'_, str>, line_ending: LineEnding) -> Cow<'_, str> {
+    match line_ending {
+        LineEnding::Lf if text.contains("\r\n") => text.replace("\r\n", "\n").into(),
+        LineEnding::CrLf
+            if text.starts_with('\n')
+                || text
+                    .as_bytes()
+                    .windows(2)
+                    .any(|w| w[1] == b'\n' && w[0] != b'\r') =>
+        {
+            let mut result = String::with_capacity(text.len() + text.len() / 32);
+            let mut last_ch = '\0';
+            for ch in text.chars() {
+                if ch == '\n' && last_ch != '\r' {
+                    result.push('\r');
+                }
+                result.push(ch);
+                last_ch = ch;
+            }
+            result.into()
+        }
+        _ => text,
+    }
+}
+
 pub trait IntoByteSlice<'x> {
     fn into_byte_slice(self) -> &'x [u8];
 }
@@ -580,9 +1244,13 @@ impl<'x> IntoByteSlice<'x> for &'x Vec<u8> {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs, path::PathBuf};
+    use std::{fs, ops::ControlFlow, path::PathBuf};
 
-    use crate::MessageParser;
+    use super::{ParseMode, VisitorSink, MAX_NESTED_ENCODED};
+    use crate::{
+        ContentType, DuplicateHeaderPolicy, Encoding, Header, HeaderName, HeaderValue, LineEnding,
+        Message, MessageParser, MessagePart, MessagePartId, MessageVisitor, MimeHeaders, PartType,
+    };
 
     #[test]
     fn parse_full_messages() {
@@ -596,7 +1264,7 @@ mod tests {
 
             for file_name in fs::read_dir(&test_dir).unwrap() {
                 let mut file_name = file_name.unwrap().path();
-                if file_name.extension().map_or(false, |e| e == "eml") {
+                if file_name.extension().is_some_and(|e| e == "eml") {
                     let raw_original = fs::read(&file_name).unwrap();
                     tests_run += 1;
 
@@ -668,4 +1336,1233 @@ mod tests {
 
         result
     }
+
+    #[test]
+    fn duplicate_mime_headers() {
+        let input = concat!(
+            "Subject: test\n",
+            "Content-Type: text/plain\n",
+            "Subject: another test\n",
+            "Content-Transfer-Encoding: 7bit\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "Content-Transfer-Encoding: base64\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "SGVsbG8=\n",
+            "--b--\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert!(matches!(message.parts[0].body, PartType::Multipart(_)));
+        assert_eq!(message.body_text(0).unwrap(), "SGVsbG8=");
+        assert_eq!(message.subject(), Some("another test"));
+        assert_eq!(message.content_type().unwrap().ctype(), "multipart");
+        assert_eq!(message.content_transfer_encoding(), Some("base64"));
+        assert_eq!(
+            message,
+            MessageParser::default()
+                .with_duplicate_header_policy(DuplicateHeaderPolicy::Last)
+                .parse(input)
+                .unwrap()
+        );
+
+        let message = MessageParser::default()
+            .with_duplicate_header_policy(DuplicateHeaderPolicy::First)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.parts.len(), 1);
+        assert!(message.body_text(0).unwrap().starts_with("--b\n"));
+        // The headers are kept as they are, the accessors return the last
+        // occurrence
+        assert_eq!(message.subject(), Some("another test"));
+        assert_eq!(message.content_type().unwrap().ctype(), "multipart");
+        assert_eq!(message.header_values("Content-Type").count(), 2);
+        assert_eq!(
+            message.parts[0]
+                .headers
+                .iter()
+                .map(|header| header.name.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                HeaderName::Subject,
+                HeaderName::ContentType,
+                HeaderName::Subject,
+                HeaderName::ContentTransferEncoding,
+                HeaderName::ContentType,
+                HeaderName::ContentTransferEncoding,
+            ]
+        );
+
+        let input = concat!(
+            "Subject: test\n",
+            "Content-Transfer-Encoding: base64\n",
+            "Content-Transfer-Encoding: 7bit\n",
+            "\n",
+            "SGVsbG8=\n",
+        );
+        assert_eq!(
+            MessageParser::default()
+                .parse(input)
+                .unwrap()
+                .body_text(0)
+                .unwrap(),
+            "SGVsbG8=\n"
+        );
+        assert_eq!(
+            MessageParser::default()
+                .with_duplicate_header_policy(DuplicateHeaderPolicy::First)
+                .parse(input)
+                .unwrap()
+                .body_text(0)
+                .unwrap(),
+            "Hello"
+        );
+    }
+
+    #[derive(Default)]
+    struct PartCollector<'x> {
+        headers: Vec<Vec<Header<'x>>>,
+        bodies: Vec<Option<Vec<u8>>>,
+        stop_after_headers: bool,
+    }
+
+    impl<'x> MessageVisitor<'x> for PartCollector<'x> {
+        fn on_header(&mut self, part_id: MessagePartId, header: &Header<'x>) -> ControlFlow<()> {
+            if self.headers.len() == part_id {
+                self.headers.push(Vec::new());
+                self.bodies.push(None);
+            }
+            self.headers[part_id].push(header.clone());
+            ControlFlow::Continue(())
+        }
+
+        fn on_part_start(
+            &mut self,
+            part_id: MessagePartId,
+            _: Option<&ContentType<'x>>,
+        ) -> ControlFlow<()> {
+            if self.headers.len() == part_id {
+                self.headers.push(Vec::new());
+                self.bodies.push(None);
+            }
+            if self.stop_after_headers {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+
+        fn on_body_chunk(&mut self, part_id: MessagePartId, chunk: &[u8]) -> ControlFlow<()> {
+            self.bodies[part_id] = Some(chunk.to_vec());
+            ControlFlow::Continue(())
+        }
+    }
+
+    // Lists the parts in the order they are visited, descending into the
+    // nested messages that are not transfer encoded
+    fn visited_parts<'a, 'x>(message: &'a Message<'x>, parts: &mut Vec<&'a MessagePart<'x>>) {
+        for part in &message.parts {
+            parts.push(part);
+            if let (PartType::Message(nested), Encoding::None) = (&part.body, part.encoding) {
+                visited_parts(nested, parts);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_with_visitor() {
+        for test_suite in ["rfc", "legacy", "thirdparty", "malformed"] {
+            let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("resources")
+                .join("eml")
+                .join(test_suite);
+
+            for file_name in fs::read_dir(&test_dir).unwrap() {
+                let file_name = file_name.unwrap().path();
+                if file_name.extension().map_or(true, |e| e != "eml") {
+                    continue;
+                }
+                let raw_message = fs::read(&file_name).unwrap();
+                let message = MessageParser::default().parse(&raw_message).unwrap();
+                let mut visitor = PartCollector::default();
+
+                assert_eq!(
+                    MessageParser::default().parse_with_visitor(&raw_message, &mut visitor),
+                    ControlFlow::Continue(()),
+                    "{}",
+                    file_name.display()
+                );
+                let mut parts = Vec::new();
+                visited_parts(&message, &mut parts);
+                assert_eq!(
+                    visitor.headers.len(),
+                    parts.len(),
+                    "{}",
+                    file_name.display()
+                );
+
+                for ((part, headers), body) in
+                    parts.into_iter().zip(visitor.headers).zip(visitor.bodies)
+                {
+                    assert_eq!(part.headers, headers, "{}", file_name.display());
+                    match &part.body {
+                        PartType::Binary(contents) | PartType::InlineBinary(contents) => {
+                            assert_eq!(
+                                body.as_deref(),
+                                Some(contents.as_ref()),
+                                "{}",
+                                file_name.display()
+                            );
+                        }
+                        PartType::Multipart(_) => {
+                            assert_eq!(body, None, "{}", file_name.display());
+                        }
+                        PartType::Message(nested) if part.encoding != Encoding::None => {
+                            assert_eq!(
+                                body.as_deref(),
+                                Some(nested.raw_message.as_ref()),
+                                "{}",
+                                file_name.display()
+                            );
+                        }
+                        PartType::Message(_) => {
+                            assert_eq!(body, None, "{}", file_name.display());
+                        }
+                        _ => {
+                            assert!(body.is_some(), "{}", file_name.display());
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut visitor = PartCollector {
+            stop_after_headers: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            MessageParser::default().parse_with_visitor(
+                "Subject: test\nContent-Type: text/plain\n\nHello\n",
+                &mut visitor
+            ),
+            ControlFlow::Break(())
+        );
+        assert_eq!(visitor.headers[0].len(), 2);
+        assert_eq!(visitor.bodies, [None]);
+
+        // Parser options apply as they do to `parse`
+        let raw_message = concat!(
+            "Content-Type: multipart/mixed; boundary=\"b\"\n\n",
+            "--b\nContent-Type: message/rfc822\n\n",
+            "Subject: nested\n\nHello\n",
+            "--b\nContent-Type: text/x-report\n\n",
+            "Subject: report\n\nWorld\n",
+            "--b--\n"
+        );
+        let mut visitor = PartCollector::default();
+        assert_eq!(
+            MessageParser::default()
+                .with_recurse_content_types(&[("text", "x-report")])
+                .parse_with_visitor(raw_message, &mut visitor),
+            ControlFlow::Continue(())
+        );
+        assert_eq!(
+            visitor.bodies,
+            [
+                None,
+                None,
+                Some(b"Hello".to_vec()),
+                None,
+                Some(b"World".to_vec())
+            ]
+        );
+        assert_eq!(
+            visitor.headers[2][0].value,
+            HeaderValue::Text("nested".into())
+        );
+
+        let mut visitor = PartCollector::default();
+        assert_eq!(
+            MessageParser::default()
+                .with_max_parts(3)
+                .parse_with_visitor(raw_message, &mut visitor),
+            ControlFlow::Continue(())
+        );
+        assert_eq!(visitor.headers.len(), 3);
+
+        // Visited parts do not keep their headers or bodies
+        let mut visitor = PartCollector::default();
+        let message = MessageParser::default()
+            .parse_parts(
+                raw_message.as_bytes(),
+                MAX_NESTED_ENCODED,
+                ParseMode::Full,
+                None,
+                &mut 0,
+                Some(&mut VisitorSink {
+                    visitor: &mut visitor,
+                    is_stopped: false,
+                }),
+            )
+            .unwrap();
+        let mut parts = Vec::new();
+        visited_parts(&message, &mut parts);
+        assert_eq!(parts.len(), 4);
+        assert!(parts.iter().all(|part| part.headers.is_empty()
+            && matches!(&part.body, PartType::Multipart(_) | PartType::Message(_))));
+    }
+
+    #[test]
+    fn normalize_eol() {
+        let input = concat!(
+            "Subject: test\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "line 1\r\nline 2\nline 3\r\n",
+            "--b\r\n",
+            "Content-Type: text/html\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "PHA+MTwvcD4NCjxwPjI8L3A+CjxwPjM8L3A+\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "Content-Transfer-Encoding: quoted-printable\r\n",
+            "\r\n",
+            "a=0D=0Ab=0Ac\r\n",
+            "--b--\r\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(
+            message.parts[1].text_contents().unwrap(),
+            "line 1\r\nline 2\nline 3"
+        );
+        assert_eq!(
+            message.parts[2].text_contents().unwrap(),
+            "<p>1</p>\r\n<p>2</p>\n<p>3</p>"
+        );
+        assert_eq!(message.parts[3].text_contents().unwrap(), "a\r\nb\nc");
+
+        for (line_ending, expected) in [
+            (
+                LineEnding::Lf,
+                [
+                    "line 1\nline 2\nline 3",
+                    "<p>1</p>\n<p>2</p>\n<p>3</p>",
+                    "a\nb\nc",
+                ],
+            ),
+            (
+                LineEnding::CrLf,
+                [
+                    "line 1\r\nline 2\r\nline 3",
+                    "<p>1</p>\r\n<p>2</p>\r\n<p>3</p>",
+                    "a\r\nb\r\nc",
+                ],
+            ),
+        ] {
+            let normalized = MessageParser::default()
+                .with_normalize_eol(line_ending)
+                .parse(input)
+                .unwrap();
+            assert_eq!(normalized.parts[1].text_contents().unwrap(), expected[0]);
+            assert_eq!(normalized.parts[2].text_contents().unwrap(), expected[1]);
+            assert_eq!(normalized.parts[3].text_contents().unwrap(), expected[2]);
+            assert_eq!(normalized.raw_message, message.raw_message);
+            for (part, normalized_part) in message.parts.iter().zip(normalized.parts.iter()) {
+                assert_eq!(part.offset_header, normalized_part.offset_header);
+                assert_eq!(part.offset_body, normalized_part.offset_body);
+                assert_eq!(part.offset_end, normalized_part.offset_end);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_header_block() {
+        for input in [
+            "From: john@example.com\r\nSubject: =?utf-8?q?caf=C3=A9?=\r\nX-Other: value\r\n\r\n",
+            "From: john@example.com\r\nSubject: =?utf-8?q?caf=C3=A9?=\r\nX-Other: value\r\n",
+            "From: john@example.com\nSubject: =?utf-8?q?caf=C3=A9?=\nX-Other: value\n",
+        ] {
+            let message = MessageParser::default().parse_headers(input).unwrap();
+            assert_eq!(message.parts.len(), 1, "{input:?}");
+            assert_eq!(message.headers().len(), 3, "{input:?}");
+            assert_eq!(
+                message.from().unwrap().first().unwrap().address(),
+                Some("john@example.com")
+            );
+            assert_eq!(message.subject(), Some("café"));
+            assert_eq!(message.header("X-Other").unwrap().as_text(), Some("value"));
+        }
+    }
+
+    #[test]
+    fn missing_multipart_boundary() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"xyz\"\n",
+                "\n",
+                "This message was truncated\n",
+                "before its first boundary.\n"
+            ))
+            .unwrap();
+
+        assert_eq!(message.parts.len(), 1);
+        let part = &message.parts[0];
+        assert!(part.is_encoding_problem);
+        assert_eq!(
+            part.text_contents(),
+            Some("This message was truncated\nbefore its first boundary.\n")
+        );
+
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"xyz\"\n",
+                "\n",
+                "--xyz\n",
+                "Content-Type: text/plain\n",
+                "\n",
+                "Complete part\n",
+                "--xyz--\n"
+            ))
+            .unwrap();
+
+        assert!(message.parts.iter().all(|part| !part.is_encoding_problem));
+        assert_eq!(message.parts[0].sub_parts(), Some(&[1][..]));
+    }
+
+    #[test]
+    fn mbox_from_line() {
+        let input = concat!(
+            "From MAILER-DAEMON Fri Jul  8 12:08:34 2011\n",
+            "From: Author <author@example.com>\n",
+            "Subject: Mbox entry\n",
+            "\n",
+            "Body\n"
+        );
+        let raw_message = input.split_once('\n').unwrap().1;
+
+        let message = MessageParser::default()
+            .with_mbox_from_line(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.headers().len(), 2);
+        assert_eq!(
+            message.from().unwrap().first().unwrap().address(),
+            Some("author@example.com")
+        );
+        assert_eq!(message.subject(), Some("Mbox entry"));
+        assert_eq!(message.body_text(0).unwrap(), "Body\n");
+        assert_eq!(message.raw_message(), raw_message.as_bytes());
+
+        let headers = MessageParser::default()
+            .with_mbox_from_line(true)
+            .parse_headers(input)
+            .unwrap();
+        assert_eq!(headers.subject(), Some("Mbox entry"));
+
+        // Without the option the separator line is parsed as a header
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(message.headers().len(), 3);
+
+        // Messages without a separator line are not affected
+        let message = MessageParser::default()
+            .with_mbox_from_line(true)
+            .parse(raw_message)
+            .unwrap();
+        assert_eq!(message.subject(), Some("Mbox entry"));
+        assert_eq!(message.raw_message(), raw_message.as_bytes());
+    }
+
+    #[test]
+    fn boundary_trailing_whitespace() {
+        let input = concat!(
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "\n",
+            "--b  \n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "Plain\n",
+            "--b\t\n",
+            "Content-Type: text/plain\n",
+            "Content-Transfer-Encoding: base64\n",
+            "\n",
+            "QmFzZTY0\n",
+            "--b \t \n",
+            "Content-Type: text/plain\n",
+            "Content-Transfer-Encoding: quoted-printable\n",
+            "\n",
+            "Quoted=3DPrintable\n",
+            "--b--  \t\n",
+            "Epilogue\n"
+        );
+
+        for input in [input.to_string(), input.replace('\n', "\r\n")] {
+            let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+            assert_eq!(message.parts.len(), 4, "{input:?}");
+            assert_eq!(message.parts[0].sub_parts(), Some(&[1, 2, 3][..]));
+            assert_eq!(message.body_text(0).unwrap(), "Plain");
+            assert_eq!(message.body_text(1).unwrap(), "Base64");
+            assert_eq!(message.body_text(2).unwrap(), "Quoted=Printable");
+            assert!(message.parts.iter().all(|part| !part.is_encoding_problem));
+        }
+    }
+
+    #[test]
+    fn recurse_content_types() {
+        let input = concat!(
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "See attached\n",
+            "--b\n",
+            "Content-Type: application/octet-stream\n",
+            "Content-Disposition: attachment; filename=\"forwarded.eml\"\n",
+            "\n",
+            "From: sender@example.com\n",
+            "Subject: Embedded\n",
+            "\n",
+            "Embedded body\n",
+            "--b\n",
+            "Content-Type: Application/Octet-Stream\n",
+            "Content-Transfer-Encoding: base64\n",
+            "\n",
+            "U3ViamVjdDogRW5jb2RlZAoKRW5jb2RlZCBib2R5Cg==\n",
+            "--b--\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert!(message
+            .attachments()
+            .all(|part| matches!(part.body, PartType::Binary(_))));
+
+        let message = MessageParser::default()
+            .with_recurse_content_types(&[("application", "octet-stream")])
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.attachment_count(), 2);
+        assert!(message.parts.iter().all(|part| !part.is_encoding_problem));
+        assert_eq!(message.body_text(0).unwrap(), "See attached");
+
+        let nested = message.attachment(0).unwrap().message().unwrap();
+        assert_eq!(nested.subject(), Some("Embedded"));
+        assert_eq!(nested.body_text(0).unwrap(), "Embedded body");
+
+        let nested = message.attachment(1).unwrap().message().unwrap();
+        assert_eq!(nested.subject(), Some("Encoded"));
+        assert_eq!(nested.body_text(0).unwrap(), "Encoded body\n");
+    }
+
+    #[test]
+    fn decode_composite_cte() {
+        let input = concat!(
+            "Subject: Encoded multipart\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "Content-Transfer-Encoding: base64\n",
+            "\n",
+            "LS1iCkNvbnRlbnQtVHlwZTogdGV4dC9wbGFpbgoKSGVsbG8KLS1iCkNvbnRlbnQtVHlwZTogYXBw\n",
+            "bGljYXRpb24vcGRmCgpQREYKLS1iLS0K\n",
+        );
+
+        // By default the boundary is not found in the encoded body
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(message.parts.len(), 1);
+        assert!(message.parts[0].is_encoding_problem);
+
+        let message = MessageParser::default()
+            .with_decode_composite_cte(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.subject(), Some("Encoded multipart"));
+        assert_eq!(message.parts.len(), 3);
+        assert_eq!(message.parts[0].sub_parts(), Some(&[1, 2][..]));
+        assert!(message.parts.iter().all(|part| !part.is_encoding_problem));
+        assert_eq!(message.text_body_count(), 1);
+        assert_eq!(message.body_text(0).unwrap(), "Hello");
+        assert_eq!(message.attachment_count(), 1);
+        assert_eq!(message.attachment(0).unwrap().contents(), b"PDF");
+
+        // Nested encoded multipart
+        let message = MessageParser::default()
+            .with_decode_composite_cte(true)
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"outer\"\n",
+                "\n",
+                "--outer\n",
+                "Content-Type: text/plain\n",
+                "\n",
+                "First\n",
+                "--outer\n",
+                "Content-Type: multipart/mixed; boundary=\"b\"\n",
+                "Content-Transfer-Encoding: base64\n",
+                "\n",
+                "LS1iCkNvbnRlbnQtVHlwZTogdGV4dC9wbGFpbgoKSGVsbG8KLS1iCkNvbnRlbnQtVHlwZTogYXBw\n",
+                "bGljYXRpb24vcGRmCgpQREYKLS1iLS0K\n",
+                "--outer\n",
+                "Content-Type: text/plain\n",
+                "\n",
+                "Last\n",
+                "--outer--\n",
+            ))
+            .unwrap();
+        assert_eq!(message.parts.len(), 6);
+        assert_eq!(message.parts[0].sub_parts(), Some(&[1, 2, 5][..]));
+        assert_eq!(message.parts[2].sub_parts(), Some(&[3, 4][..]));
+        assert_eq!(message.body_text(1).unwrap(), "Hello");
+        assert_eq!(message.body_text(2).unwrap(), "Last");
+        assert_eq!(message.attachment(0).unwrap().contents(), b"PDF");
+
+        // The decoded parts are picked as if they were in the enclosing
+        // multipart/alternative, keeping all the Content-Type parameters
+        let input = concat!(
+            "Content-Type: multipart/alternative; boundary=\"outer\"\r\n",
+            "\r\n",
+            "--outer\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Hello\r\n",
+            "--outer\r\n",
+            "Content-Type: multipart/related; type=\"text/html\";\r\n",
+            " boundary=\"a\\\"b\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "LS1hImINCkNvbnRlbnQtVHlwZTogdGV4dC9odG1sDQoNCjxwPkhlbGxvIDxpbWcgc3JjPSJjaWQ6\r\n",
+            "bG9nbyI+PC9wPg0KLS1hImINCkNvbnRlbnQtVHlwZTogaW1hZ2UvcG5nDQpDb250ZW50LUlEOiA8\r\n",
+            "bG9nbz4NCg0KUE5HDQotLWEiYi0tDQo=\r\n",
+            "--outer--\r\n",
+        );
+        let message = MessageParser::default()
+            .with_decode_composite_cte(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.parts.len(), 5);
+        assert_eq!(message.parts[2].sub_parts(), Some(&[3, 4][..]));
+        assert_eq!(message.parts[2].related_type(), Some("text/html"));
+        assert_eq!(message.text_body, [1]);
+        assert_eq!(message.html_body, [3]);
+        assert_eq!(
+            message.body_html(0).unwrap(),
+            "<p>Hello <img src=\"cid:logo\"></p>"
+        );
+        assert_eq!(message.parts[4].content_id(), Some("logo"));
+        assert_eq!(message.parts[4].contents(), b"PNG");
+
+        // Decoded parts and their headers point to the encoded body
+        let encoded = &message.parts[2];
+        for part in &message.parts[3..] {
+            assert_eq!(part.offset_header, encoded.offset_body);
+            assert_eq!(part.offset_body, encoded.offset_body);
+            assert_eq!(part.offset_end, encoded.offset_end);
+            for header in &part.headers {
+                assert_eq!(header.offset_start, encoded.offset_body);
+                assert_eq!(header.offset_end, encoded.offset_body);
+            }
+        }
+    }
+
+    #[test]
+    fn multipart_without_boundary() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Subject: No boundary\n",
+                "Content-Type: multipart/mixed\n",
+                "\n",
+                "--unknown\n",
+                "Content-Type: text/plain\n",
+                "\n",
+                "Hello\n",
+                "--unknown--\n"
+            ))
+            .unwrap();
+
+        assert_eq!(message.parts.len(), 1);
+        assert!(message.parts[0].is_encoding_problem);
+        assert!(matches!(message.parts[0].body, PartType::Binary(_)));
+        assert_eq!(
+            message.parts[0].contents(),
+            b"--unknown\nContent-Type: text/plain\n\nHello\n--unknown--\n"
+        );
+        assert_eq!(message.attachment_count(), 1);
+    }
+
+    #[test]
+    fn corrupted_base64() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Subject: Corrupted base64\n",
+                "Content-Type: multipart/mixed; boundary=b\n",
+                "\n",
+                "--b\n",
+                "Content-Type: text/plain\n",
+                "Content-Transfer-Encoding: base64\n",
+                "\n",
+                "VGhlIHF1aWNrIGJyb3duIGZveCBqdW1wcyBvdmVy\x01IHRoZSBsYXp5IGRvZy4gVGhlIHF1aWNrIGJy\n",
+                "b3duIGZveCBqdW1wcyBvdmVyIHRoZSBsYXp5IGRvZy4=\n",
+                "--b\n",
+                "Content-Type: audio/basic\n",
+                "Content-Transfer-Encoding: base64\n",
+                "\n",
+                "... base64-encoded audio data goes here ...\n",
+                "--b--\n"
+            ))
+            .unwrap();
+
+        // A single invalid character per line is skipped
+        assert!(message.parts[1].is_encoding_problem);
+        assert_eq!(message.parts[1].text_contents(), Some("The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog."));
+        assert_eq!(message.body_text(0).unwrap(), "The quick brown fox jumps over the lazy dog. The quick brown fox jumps over the lazy dog.");
+
+        // Bodies that are not base64 are kept as text
+        assert!(message.parts[2].is_encoding_problem);
+        assert_eq!(
+            message.parts[2].text_contents(),
+            Some("... base64-encoded audio data goes here ...")
+        );
+    }
+
+    #[test]
+    fn autodetect_cte() {
+        let input = concat!(
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "Content-Transfer-Encoding: 7bit\n",
+            "\n",
+            "Plain text = not encoded, even with an equals sign.\n",
+            "--b\n",
+            "Content-Type: application/pdf\n",
+            "Content-Transfer-Encoding: 7bit\n",
+            "\n",
+            "JVBERi0xLjQgdGhpcyBhdHRhY2htZW50IHdhcyBzZW50IHdpdGggdGhlIHdyb25nIHRyYW5zZmVy\n",
+            "IGVuY29kaW5nIGhlYWRlcg==\n",
+            "--b\n",
+            "Content-Type: text/plain; charset=utf-8\n",
+            "Content-Transfer-Encoding: 8bit\n",
+            "\n",
+            "Caf=C3=A9 au lait, soft =\n",
+            "break\n",
+            "--b--\n"
+        );
+
+        let message = MessageParser::default().parse(input).unwrap();
+        assert!(message.parts.iter().all(|part| !part.is_encoding_problem));
+        assert!(message.parts[2].contents().starts_with(b"JVBERi0x"));
+        assert_eq!(
+            message.body_text(1).unwrap(),
+            "Caf=C3=A9 au lait, soft =\nbreak"
+        );
+
+        let message = MessageParser::default()
+            .with_autodetect_cte(true)
+            .parse(input)
+            .unwrap();
+        assert!(!message.parts[1].is_encoding_problem);
+        assert_eq!(message.parts[1].encoding, Encoding::None);
+        assert_eq!(
+            message.body_text(0).unwrap(),
+            "Plain text = not encoded, even with an equals sign."
+        );
+
+        assert!(message.parts[2].is_encoding_problem);
+        assert_eq!(message.parts[2].encoding, Encoding::Base64);
+        assert_eq!(
+            message.parts[2].contents(),
+            b"%PDF-1.4 this attachment was sent with the wrong transfer encoding header"
+        );
+
+        assert!(message.parts[3].is_encoding_problem);
+        assert_eq!(message.parts[3].encoding, Encoding::QuotedPrintable);
+        assert_eq!(message.body_text(1).unwrap(), "Café au lait, soft break");
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_parts() {
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+                "\r\n",
+                "--b\r\n",
+                "Content-Type: text/plain\r\n",
+                "Content-Transfer-Encoding: base64\r\n",
+                "Content-Encoding: gzip\r\n",
+                "\r\n",
+                "H4sIAAAAAAACA/NIzcnJ11FIzs8tKEotLk5NUSjPL8pJUeTlAgCL0AXcGgAAAA==\r\n",
+                "--b\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "Content-Transfer-Encoding: base64\r\n",
+                "Content-Encoding: Deflate\r\n",
+                "\r\n",
+                "eJxzLzq85/D8VIXE0mKFlNQiBbfUorxUXi4Ae4gI6w==\r\n",
+                "--b\r\n",
+                "Content-Type: application/octet-stream\r\n",
+                "Content-Encoding: gzip\r\n",
+                "\r\n",
+                "not compressed\r\n",
+                "--b--\r\n",
+            ))
+            .unwrap();
+
+        assert_eq!(message.parts[1].content_encoding(), Some("gzip"));
+        assert_eq!(
+            message.parts[1].text_contents(),
+            Some("Hello, compressed world!\r\n")
+        );
+        assert!(!message.parts[1].is_encoding_problem);
+        assert_eq!(message.parts[2].content_encoding(), Some("Deflate"));
+        assert_eq!(
+            message.parts[2].text_contents(),
+            Some("Grüße aus der Ferne\r\n")
+        );
+        assert!(message.parts[3].is_encoding_problem);
+        assert_eq!(message.parts[3].contents(), b"not compressed");
+
+        // 100000 bytes once decompressed
+        let bomb = concat!(
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "Content-Encoding: gzip\r\n",
+            "\r\n",
+            "H4sIAAAAAAACA+3BMQEAAADCoErrn84aHkABAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\r\n",
+            "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\r\n",
+            "AAAAAAAAAAAArwaFwk3FoIYBAA==\r\n",
+        );
+        let message = MessageParser::default().parse(bomb).unwrap();
+        assert!(!message.parts[0].is_encoding_problem);
+        assert_eq!(message.parts[0].contents(), vec![b'0'; 100000]);
+
+        for max_size in [99999, 1024] {
+            let parser = MessageParser::default().with_max_decompressed_size(max_size);
+            let message = parser.parse(bomb).unwrap();
+            assert!(message.parts[0].is_encoding_problem);
+            assert_eq!(&message.parts[0].contents()[..3], b"\x1f\x8b\x08");
+
+            let lazy = parser.parse_lazy(bomb).unwrap();
+            assert_eq!(
+                &lazy.contents(lazy.part(0).unwrap()).unwrap()[..3],
+                b"\x1f\x8b\x08"
+            );
+        }
+    }
+
+    #[test]
+    fn generate_text_from_html() {
+        let html_only = concat!(
+            "Subject: Sale\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<html><body><p>Big <b>sale</b> today!</p></body></html>\r\n"
+        );
+
+        let message = MessageParser::default().parse(html_only).unwrap();
+        assert_eq!(message.text_body, message.html_body);
+        assert!(message.parts.iter().all(|part| !part.is_synthetic));
+
+        let message = MessageParser::default()
+            .with_generate_text_from_html(true)
+            .parse(html_only)
+            .unwrap();
+        assert_eq!(message.parts.len(), 2);
+        assert_eq!(message.html_body, [0]);
+        assert_eq!(message.text_body, [1]);
+        let text_part = message.text_part(0).unwrap();
+        assert!(text_part.is_synthetic);
+        assert!(!message.html_part(0).unwrap().is_synthetic);
+        assert!(
+            matches!(&text_part.body, PartType::Text(text) if text.contains("Big sale today!"))
+        );
+        assert_eq!(text_part.offset_body, message.parts[0].offset_body);
+
+        let alternative = concat!(
+            "Content-Type: multipart/alternative; boundary=a\r\n",
+            "\r\n",
+            "--a\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Plain\r\n",
+            "--a\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p>Html</p>\r\n",
+            "--a--\r\n"
+        );
+        let message = MessageParser::default()
+            .with_generate_text_from_html(true)
+            .parse(alternative)
+            .unwrap();
+        assert_eq!(message.parts.len(), 3);
+        assert!(message.parts.iter().all(|part| !part.is_synthetic));
+        assert_eq!(message.body_text(0).unwrap(), "Plain");
+    }
+
+    #[test]
+    fn max_parts() {
+        let mut input = String::from("Content-Type: multipart/mixed; boundary=b\r\n\r\n");
+        for _ in 0..1_000_000 {
+            input.push_str("--b\r\n\r\n");
+        }
+        input.push_str("--b--\r\n");
+
+        let message = MessageParser::default()
+            .with_max_parts(100)
+            .parse(input.as_bytes())
+            .unwrap();
+        assert!(message.is_truncated);
+        assert_eq!(message.parts.len(), 100);
+        assert_eq!(
+            message.parts[0].sub_parts().map(|parts| parts.len()),
+            Some(99)
+        );
+
+        let message = MessageParser::default()
+            .with_max_parts(100)
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=b\r\n",
+                "\r\n",
+                "--b\r\n",
+                "Content-Type: text/plain\r\n",
+                "\r\n",
+                "Hello\r\n",
+                "--b--\r\n"
+            ))
+            .unwrap();
+        assert!(!message.is_truncated);
+        assert_eq!(message.parts.len(), 2);
+
+        // The limit is reached inside a nested message
+        let message = MessageParser::default()
+            .with_max_parts(4)
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=b\r\n",
+                "\r\n",
+                "--b\r\n",
+                "Content-Type: message/rfc822\r\n",
+                "\r\n",
+                "Content-Type: multipart/mixed; boundary=c\r\n",
+                "\r\n",
+                "--c\r\n",
+                "\r\n",
+                "One\r\n",
+                "--c\r\n",
+                "\r\n",
+                "Two\r\n",
+                "--c--\r\n",
+                "--b--\r\n"
+            ))
+            .unwrap();
+        assert!(message.is_truncated);
+        assert!(message.parts[1].message().unwrap().is_truncated);
+        assert!(!message.is_incomplete);
+
+        // Encoded nested messages share the limit of the enclosing message
+        let nested = concat!(
+            "--b\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "Q29udGVudC1UeXBlOiBtdWx0aXBhcnQvbWl4ZWQ7IGJvdW5kYXJ5PWMNCg0KLS1jDQoNCk9uZQ0K",
+            "LS1jDQoNClR3bw0KLS1jDQoNClRocmVlDQotLWMtLQ0K\r\n",
+        );
+        let input = format!(
+            "Content-Type: multipart/mixed; boundary=b\r\n\r\n{nested}{nested}{nested}--b--\r\n"
+        );
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert!(!message.is_truncated);
+        assert_eq!(message.parts.len(), 4);
+
+        let message = MessageParser::default()
+            .with_max_parts(8)
+            .parse(input.as_bytes())
+            .unwrap();
+        assert!(message.is_truncated);
+        assert_eq!(message.parts.len(), 3);
+        let first = message.parts[1].message().unwrap();
+        assert!(!first.is_truncated);
+        assert_eq!(first.parts.len(), 4);
+        let second = message.parts[2].message().unwrap();
+        assert!(second.is_truncated);
+        assert_eq!(second.parts.len(), 1);
+    }
+
+    #[test]
+    fn charset_aliases() {
+        for (charset, body, expected) in [
+            ("cp1252", &b"\x80 5, \x93quoted\x94"[..], "€ 5, “quoted”"),
+            ("iso8859-1", &b"caf\xe9"[..], "café"),
+            ("latin-1", &b"na\xefve"[..], "naïve"),
+            ("utf8", "ünïcödé €".as_bytes(), "ünïcödé €"),
+        ] {
+            let mut input =
+                format!("Subject: test\r\nContent-Type: text/plain; charset={charset}\r\n\r\n")
+                    .into_bytes();
+            input.extend_from_slice(body);
+
+            let message = MessageParser::default().parse(&input).unwrap();
+            assert_eq!(
+                message.body_text(0).as_deref(),
+                Some(expected),
+                "failed for {charset}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_charset_fallback() {
+        let body = b"\x93Smart quotes\x94 \x96 \x80100\r\n";
+        for (charset, expected, expected_fallback) in [
+            (
+                None,
+                "\u{fffd}Smart quotes\u{fffd} \u{fffd} \u{fffd}100\r\n",
+                "“Smart quotes” – €100\r\n",
+            ),
+            (
+                Some("x-unknown"),
+                "\u{fffd}Smart quotes\u{fffd} \u{fffd} \u{fffd}100\r\n",
+                "“Smart quotes” – €100\r\n",
+            ),
+            (
+                Some("us-ascii"),
+                "\u{fffd}Smart quotes\u{fffd} \u{fffd} \u{fffd}100\r\n",
+                "“Smart quotes” – €100\r\n",
+            ),
+            (
+                Some("utf-8"),
+                "\u{fffd}Smart quotes\u{fffd} \u{fffd} \u{fffd}100\r\n",
+                "\u{fffd}Smart quotes\u{fffd} \u{fffd} \u{fffd}100\r\n",
+            ),
+        ] {
+            let mut input = b"Subject: test\r\n".to_vec();
+            if let Some(charset) = charset {
+                input.extend_from_slice(
+                    format!("Content-Type: text/plain; charset={charset}\r\n").as_bytes(),
+                );
+            }
+            input.extend_from_slice(b"\r\n");
+            input.extend_from_slice(body);
+
+            let message = MessageParser::default().parse(&input).unwrap();
+            assert_eq!(message.body_text(0).as_deref(), Some(expected));
+
+            let message = MessageParser::default()
+                .with_unknown_charset_fallback("windows-1252")
+                .parse(&input)
+                .unwrap();
+            assert_eq!(
+                message.body_text(0).as_deref(),
+                Some(expected_fallback),
+                "failed for {charset:?}"
+            );
+        }
+
+        // Valid UTF-8 is not affected by the fallback
+        let message = MessageParser::default()
+            .with_unknown_charset_fallback("windows-1252")
+            .parse("Subject: test\r\n\r\n“Smart quotes”\r\n".as_bytes())
+            .unwrap();
+        assert_eq!(message.body_text(0).as_deref(), Some("“Smart quotes”\r\n"));
+    }
+
+    #[test]
+    fn case_insensitive_boundaries() {
+        let input = concat!(
+            "From: john@example.org\r\n",
+            "Content-Type: multipart/mixed; boundary=\"AbC-123\"\r\n",
+            "\r\n",
+            "--abc-123\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Hello\r\n",
+            "--ABC-123\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "SGVsbG8=\r\n",
+            "--abc-123--\r\n"
+        );
+
+        // Boundaries are case-sensitive by default
+        let message = MessageParser::default().parse(input).unwrap();
+        assert_eq!(message.parts.len(), 1);
+        assert!(message.parts[0].is_encoding_problem);
+        assert!(message.parts[0].contents().starts_with(b"--abc-123\r\n"));
+
+        let message = MessageParser::default()
+            .with_case_insensitive_boundaries(true)
+            .parse(input)
+            .unwrap();
+        assert_eq!(message.parts.len(), 3);
+        assert!(!message.parts[0].is_encoding_problem);
+        assert_eq!(message.body_text(0).unwrap(), "Hello");
+        assert_eq!(message.attachment(0).unwrap().contents(), b"Hello");
+
+        // Exact matches are unaffected
+        let exact = input
+            .replace("abc-123", "AbC-123")
+            .replace("ABC-123", "AbC-123");
+        for parser in [
+            MessageParser::default(),
+            MessageParser::default().with_case_insensitive_boundaries(true),
+        ] {
+            let message = parser.parse(&exact).unwrap();
+            assert_eq!(message.parts.len(), 3);
+            assert_eq!(message.body_text(0).unwrap(), "Hello");
+        }
+    }
+
+    #[test]
+    fn parse_prefix() {
+        let input = concat!(
+            "From: john@example.org\r\n",
+            "Subject: Report\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See attached\r\n",
+            "--b\r\n",
+            "Content-Type: application/octet-stream; name=\"report.bin\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "SGVsbG8gd29y\r\n",
+            "bGQsIHRoaXMg\r\n",
+            "aXMgdGhlIHJl\r\n",
+            "cG9ydA==\r\n",
+            "--b--\r\n"
+        );
+        let prefix_len = input.find("bGQsIHRo").unwrap() + 6;
+
+        let message = MessageParser::default()
+            .parse_prefix(&input[..prefix_len], input.len())
+            .unwrap();
+        assert!(message.is_incomplete);
+        assert!(!message.is_truncated);
+        assert_eq!(message.subject(), Some("Report"));
+        assert_eq!(message.parts.len(), 3);
+        assert_eq!(message.body_text(0).unwrap(), "See attached");
+        assert_eq!(
+            message
+                .parts
+                .iter()
+                .map(|part| part.is_incomplete)
+                .collect::<Vec<_>>(),
+            [true, false, true]
+        );
+        let attachment = message.attachment(0).unwrap();
+        assert!(!attachment.is_encoding_problem);
+        assert_eq!(attachment.contents(), b"Hello world,");
+
+        // A complete message is parsed as usual
+        let message = MessageParser::default()
+            .parse_prefix(input, input.len())
+            .unwrap();
+        assert!(!message.is_incomplete);
+        assert!(message.parts.iter().all(|part| !part.is_incomplete));
+        assert_eq!(
+            message.attachment(0).unwrap().contents(),
+            b"Hello world, this is the report"
+        );
+        assert_eq!(message, MessageParser::default().parse(input).unwrap());
+
+        // Nested messages cut off are flagged as well
+        let input = concat!(
+            "Subject: Forward\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See below\r\n",
+            "--b\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "\r\n",
+            "Subject: Original\r\n",
+            "Content-Type: multipart/mixed; boundary=\"c\"\r\n",
+            "\r\n",
+            "--c\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Hello\r\n",
+            "--c\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "\r\n",
+            "0123456789\r\n",
+            "--c--\r\n",
+            "--b--\r\n"
+        );
+        let prefix_len = input.find("01234").unwrap() + 5;
+        let message = MessageParser::default()
+            .parse_prefix(&input[..prefix_len], input.len())
+            .unwrap();
+        assert!(message.is_incomplete);
+        let nested = message.parts[2].message().unwrap();
+        assert!(message.parts[2].is_incomplete);
+        assert!(nested.is_incomplete);
+        assert_eq!(nested.subject(), Some("Original"));
+        assert_eq!(
+            nested
+                .parts
+                .iter()
+                .map(|part| part.is_incomplete)
+                .collect::<Vec<_>>(),
+            [true, false, true]
+        );
+        assert_eq!(nested.parts[2].contents(), b"01234");
+    }
+
+    #[test]
+    fn empty_parts() {
+        for input in [
+            "Content-Type: multipart/mixed; boundary=b\r\n\r\n--b\r\n--b\r\n--b--\r\n",
+            "Content-Type: multipart/mixed; boundary=b\n\n--b\n--b\n--b--\n",
+            "Content-Type: multipart/mixed; boundary=b\r\n\r\n--b\r\n\r\n--b\r\n\r\n--b--\r\n",
+        ] {
+            let message = MessageParser::default().parse(input).unwrap();
+            assert_eq!(message.parts.len(), 3, "{input:?}");
+            assert_eq!(message.parts[0].sub_parts(), Some(&[1, 2][..]), "{input:?}");
+            for part in &message.parts[1..] {
+                assert!(part.headers.is_empty(), "{input:?}");
+                assert_eq!(part.body, PartType::Text("".into()), "{input:?}");
+                assert_eq!(part.offset_body, part.offset_end, "{input:?}");
+            }
+
+            let mut visitor = PartCollector::default();
+            assert_eq!(
+                MessageParser::default().parse_with_visitor(input, &mut visitor),
+                ControlFlow::Continue(())
+            );
+            assert_eq!(visitor.headers.len(), 3, "{input:?}");
+        }
+
+        // Empty parts keep the following parts at their position
+        let message = MessageParser::default()
+            .parse(concat!(
+                "Content-Type: multipart/mixed; boundary=b\r\n",
+                "\r\n",
+                "--b\r\n",
+                "--b\r\n",
+                "Content-Type: text/plain\r\n",
+                "\r\n",
+                "Second\r\n",
+                "--b\r\n",
+                "--b\r\n",
+                "Content-Type: application/octet-stream\r\n",
+                "\r\n",
+                "Fourth\r\n",
+                "--b--\r\n"
+            ))
+            .unwrap();
+        assert_eq!(message.parts.len(), 5);
+        assert_eq!(message.parts[2].text_contents(), Some("Second"));
+        assert_eq!(message.parts[3].body, PartType::Text("".into()));
+        assert_eq!(message.parts[4].contents(), b"Fourth");
+    }
 }
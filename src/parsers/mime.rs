@@ -14,6 +14,36 @@ use std::borrow::Cow;
 use super::MessageStream;
 
 impl<'x> MessageStream<'x> {
+    /// Skips `boundary` when it follows, ignoring ASCII case when the parser
+    /// was built with `MessageParser::with_case_insensitive_boundaries`.
+    #[inline(always)]
+    pub fn try_skip_boundary(&mut self, boundary: &[u8]) -> bool {
+        if !self.case_insensitive_boundaries {
+            self.try_skip(boundary)
+        } else if self
+            .peek_bytes(boundary.len())
+            .is_some_and(|bytes| bytes.eq_ignore_ascii_case(boundary))
+        {
+            self.skip_bytes(boundary.len());
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `true` when the stream is at a `--boundary` delimiter line,
+    /// which starts a part without headers nor body.
+    pub fn is_boundary_next(&self, boundary: &[u8]) -> bool {
+        self.peek_bytes(boundary.len() + 2).is_some_and(|bytes| {
+            bytes.starts_with(b"--")
+                && if self.case_insensitive_boundaries {
+                    bytes[2..].eq_ignore_ascii_case(boundary)
+                } else {
+                    &bytes[2..] == boundary
+                }
+        })
+    }
+
     pub fn seek_next_part(&mut self, boundary: &[u8]) -> bool {
         if !boundary.is_empty() {
             let mut last_ch = 0;
@@ -21,7 +51,7 @@ impl<'x> MessageStream<'x> {
             self.checkpoint();
 
             while let Some(&ch) = self.next() {
-                if ch == b'-' && last_ch == b'-' && self.try_skip(boundary) {
+                if ch == b'-' && last_ch == b'-' && self.try_skip_boundary(boundary) {
                     return true;
                 }
 
@@ -46,7 +76,7 @@ impl<'x> MessageStream<'x> {
                 } else {
                     self.offset() - 1
                 };
-            } else if ch == b'-' && last_ch == b'-' && self.try_skip(boundary) {
+            } else if ch == b'-' && last_ch == b'-' && self.try_skip_boundary(boundary) {
                 return offset_pos.into();
             }
 
@@ -76,7 +106,7 @@ impl<'x> MessageStream<'x> {
             } else if ch == b'-'
                 && !boundary.is_empty()
                 && last_ch == b'-'
-                && self.try_skip(boundary)
+                && self.try_skip_boundary(boundary)
             {
                 if before_last_ch != b'\n' {
                     end_pos = self.offset() - boundary.len() - 2;
@@ -89,7 +119,8 @@ impl<'x> MessageStream<'x> {
         }
 
         (
-            if boundary.is_empty() {
+            // A part cut off by the end of a prefix ends there
+            if boundary.is_empty() || self.is_prefix {
                 self.offset()
             } else {
                 self.restore();
@@ -112,7 +143,7 @@ impl<'x> MessageStream<'x> {
                     } else {
                         self.offset() - 1
                     };
-                } else if ch == b'-' && last_ch == b'-' && self.try_skip(boundary) {
+                } else if ch == b'-' && last_ch == b'-' && self.try_skip_boundary(boundary) {
                     if before_last_ch != b'\n' {
                         end_pos = self.offset() - boundary.len() - 2;
                     }
@@ -9,7 +9,7 @@
  * except according to those terms.
  */
 
-use std::{iter::Peekable, ops::Range, slice::Iter};
+use std::{borrow::Cow, iter::Peekable, ops::Range, slice::Iter};
 
 pub mod fields;
 pub mod header;
@@ -22,6 +22,10 @@ pub struct MessageStream<'x> {
     iter: Peekable<Iter<'x, u8>>,
     pos: usize,
     restore_pos: usize,
+    collapse_whitespace: bool,
+    semicolon_address_separator: bool,
+    case_insensitive_boundaries: bool,
+    pub(crate) is_prefix: bool,
 }
 
 impl<'x> MessageStream<'x> {
@@ -31,6 +35,10 @@ impl<'x> MessageStream<'x> {
             iter: data.iter().peekable(),
             pos: 0,
             restore_pos: 0,
+            collapse_whitespace: false,
+            semicolon_address_separator: false,
+            case_insensitive_boundaries: false,
+            is_prefix: false,
         }
     }
 
@@ -108,6 +116,32 @@ impl<'x> MessageStream<'x> {
         &self.data[range]
     }
 
+    /// Returns the bytes in `range` as text, resolving the quoted-pairs of
+    /// quoted strings and comments (RFC 5322, section 3.2.1): a backslash
+    /// followed by any character yields that character literally.
+    pub fn unescape(&self, range: Range<usize>) -> Cow<'x, str> {
+        let bytes = self.bytes(range);
+        if !bytes.contains(&b'\\') {
+            return String::from_utf8_lossy(bytes);
+        }
+
+        let mut result = Vec::with_capacity(bytes.len());
+        let mut is_escaped = false;
+        for &ch in bytes {
+            if ch == b'\\' && !is_escaped {
+                is_escaped = true;
+            } else {
+                result.push(ch);
+                is_escaped = false;
+            }
+        }
+
+        match String::from_utf8(result) {
+            Ok(text) => text.into(),
+            Err(err) => String::from_utf8_lossy(err.as_bytes()).into_owned().into(),
+        }
+    }
+
     #[inline(always)]
     pub fn seek_end(&mut self) {
         self.pos = self.data.len();
@@ -134,6 +168,118 @@ impl<'x> MessageStream<'x> {
         }
     }
 
+    /// Skips spaces and tabs. A line break (`\n` or `\r\n`) followed by a
+    /// space or tab is a folded header line and is skipped as well, any other
+    /// line break ends the header value and the stream is left positioned on
+    /// it (on the `\r` of a `\r\n`).
+    pub fn skip_ws(&mut self) {
+        let data = self.data;
+        loop {
+            match data.get(self.offset()..).unwrap_or_default() {
+                [b' ' | b'\t', ..] | [b'\n', b' ' | b'\t', ..] => {
+                    self.next();
+                }
+                [b'\r', b'\n', b' ' | b'\t', ..] => {
+                    self.skip_bytes(2);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Skips whitespace as described in `skip_ws` and returns the next byte
+    /// without consuming it.
+    pub fn peek_skip_ws(&mut self) -> Option<u8> {
+        self.skip_ws();
+        self.peek().map(|&&ch| ch)
+    }
+
+    /// Skips whitespace as described in `skip_ws` and returns the token that
+    /// follows, which ends before any of the `stop` bytes, whitespace or a
+    /// line break. The byte that ends the token is not consumed. An empty
+    /// string is returned when no token follows or the token is not valid
+    /// UTF-8.
+    pub fn parse_token(&mut self, stop: &[u8]) -> &'x str {
+        self.skip_ws();
+        let start = self.offset();
+        while let Some(&&ch) = self.peek() {
+            if matches!(ch, b' ' | b'\t' | b'\r' | b'\n') || stop.contains(&ch) {
+                break;
+            }
+            self.next();
+        }
+        std::str::from_utf8(self.bytes(start..self.offset())).unwrap_or_default()
+    }
+
+    /// Skips whitespace as described in `skip_ws` and parses an unsigned
+    /// decimal integer. Returns `None` when no digit follows or the number
+    /// overflows a `u64`, in which case its digits are consumed anyway.
+    pub fn parse_u64(&mut self) -> Option<u64> {
+        self.skip_ws();
+        let mut value: Option<u64> = None;
+        let mut has_overflow = false;
+        while let Some(&&ch) = self.peek() {
+            if !ch.is_ascii_digit() {
+                break;
+            }
+            self.next();
+            if let Some(next) = value
+                .unwrap_or(0)
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(u64::from(ch - b'0')))
+            {
+                value = Some(next);
+            } else {
+                has_overflow = true;
+            }
+        }
+        value.filter(|_| !has_overflow)
+    }
+
+    /// Skips whitespace as described in `skip_ws` and parses a quoted string
+    /// (RFC 5322, section 3.2.4), returning its contents with quoted-pairs
+    /// resolved and folded lines unfolded. Returns `None` without consuming
+    /// anything else when no `"` follows, and `None` with the stream left on
+    /// the line break that ends the header when the string is not closed.
+    pub fn parse_quoted_string(&mut self) -> Option<Cow<'x, str>> {
+        if self.peek_skip_ws()? != b'"' {
+            return None;
+        }
+        self.next();
+
+        let start = self.offset();
+        let mut is_escaped = false;
+        let mut is_folded = false;
+        while let Some(&&ch) = self.peek() {
+            match ch {
+                b'"' if !is_escaped => {
+                    let value = self.unescape(start..self.offset());
+                    self.next();
+                    return Some(if is_folded {
+                        value.replace(['\r', '\n'], "").into()
+                    } else {
+                        value
+                    });
+                }
+                b'\n' if !matches!(self.data.get(self.offset() + 1), Some(b' ' | b'\t')) => {
+                    break;
+                }
+                b'\r'
+                    if self.data.get(self.offset() + 1) == Some(&b'\n')
+                        && !matches!(self.data.get(self.offset() + 2), Some(b' ' | b'\t')) =>
+                {
+                    break;
+                }
+                b'\n' => is_folded = true,
+                _ => (),
+            }
+            is_escaped = ch == b'\\' && !is_escaped;
+            self.next();
+        }
+
+        None
+    }
+
     #[allow(clippy::len_without_is_empty)]
     #[inline(always)]
     pub fn len(&self) -> usize {
@@ -155,3 +301,118 @@ impl<'x> Iterator for MessageStream<'x> {
         self.iter.next()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::MessageStream;
+
+    #[test]
+    fn skip_ws() {
+        for (input, expected_byte, expected_offset) in [
+            ("  \tvalue", Some(b'v'), 3),
+            ("value", Some(b'v'), 0),
+            (" \n folded", Some(b'f'), 3),
+            (" \r\n\tfolded", Some(b'f'), 4),
+            (" \nNext: header", Some(b'\n'), 1),
+            (" \r\nNext: header", Some(b'\r'), 1),
+            (" \r value", Some(b'\r'), 1),
+            ("   ", None, 3),
+            (" \n", Some(b'\n'), 1),
+            ("", None, 0),
+        ] {
+            let mut stream = MessageStream::new(input.as_bytes());
+            assert_eq!(stream.peek_skip_ws(), expected_byte, "{input:?}");
+            assert_eq!(stream.offset(), expected_offset, "{input:?}");
+            assert_eq!(stream.next().copied(), expected_byte, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn unescape() {
+        for (input, expected, borrowed) in [
+            ("plain", "plain", true),
+            (r#"a\"b"#, "a\"b", false),
+            (r"a\\b", r"a\b", false),
+            (r"\(EU\)", "(EU)", false),
+            (r#"a\\\"b"#, r#"a\"b"#, false),
+            (r"trailing\", "trailing", false),
+        ] {
+            let stream = MessageStream::new(input.as_bytes());
+            let value = stream.unescape(0..input.len());
+            assert_eq!(value, expected, "{input:?}");
+            assert_eq!(matches!(value, Cow::Borrowed(_)), borrowed, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parse_token() {
+        for (input, stop, expected, next) in [
+            (
+                "  text/plain; charset=utf-8",
+                &b"/;"[..],
+                "text",
+                Some(b'/'),
+            ),
+            ("charset=utf-8\r\n", b"=", "charset", Some(b'=')),
+            ("utf-8\r\n", b";", "utf-8", Some(b'\r')),
+            ("\r\n folded token", b";", "folded", Some(b' ')),
+            (";", b";", "", Some(b';')),
+            ("", b";", "", None),
+        ] {
+            let mut stream = MessageStream::new(input.as_bytes());
+            assert_eq!(stream.parse_token(stop), expected, "{input:?}");
+            assert_eq!(stream.peek().map(|&&ch| ch), next, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parse_u64() {
+        for (input, expected, offset) in [
+            ("42", Some(42), 2),
+            ("  7; size=10", Some(7), 3),
+            ("\r\n 123\r\n", Some(123), 6),
+            ("18446744073709551615", Some(u64::MAX), 20),
+            ("18446744073709551616 ", None, 20),
+            ("abc", None, 0),
+            ("", None, 0),
+        ] {
+            let mut stream = MessageStream::new(input.as_bytes());
+            assert_eq!(stream.parse_u64(), expected, "{input:?}");
+            assert_eq!(stream.offset(), offset, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn parse_quoted_string() {
+        for (input, expected, is_borrowed, next) in [
+            (
+                r#""John Doe" <jdoe@example.org>"#,
+                Some("John Doe"),
+                true,
+                Some(b' '),
+            ),
+            (r#"  "a\"b";"#, Some("a\"b"), false, Some(b';')),
+            (r#""a\\b""#, Some(r"a\b"), false, None),
+            ("\"\"", Some(""), true, None),
+            (
+                "\"folded\r\n string\"\r\n",
+                Some("folded string"),
+                false,
+                Some(b'\r'),
+            ),
+            ("\"unterminated\r\nNext: header", None, false, Some(b'\r')),
+            ("\"unterminated", None, false, None),
+            ("token", None, false, Some(b't')),
+        ] {
+            let mut stream = MessageStream::new(input.as_bytes());
+            let value = stream.parse_quoted_string();
+            assert_eq!(value.as_deref(), expected, "{input:?}");
+            if let Some(value) = value {
+                assert_eq!(matches!(value, Cow::Borrowed(_)), is_borrowed, "{input:?}");
+            }
+            assert_eq!(stream.peek().map(|&&ch| ch), next, "{input:?}");
+        }
+    }
+}
@@ -17,30 +17,68 @@ pub fn preview_html<'x>(html: Cow<'_, str>, max_len: usize) -> Cow<'x, str> {
     preview_text(html_to_text(html.as_ref()).into(), max_len)
 }
 
-pub fn preview_text<'x>(text: Cow<'_, str>, mut max_len: usize) -> Cow<'x, str> {
-    if text.len() > max_len {
-        let add_dots = max_len > 6;
-        if add_dots {
-            max_len -= 3;
-        }
-        let mut result = String::with_capacity(max_len);
-        for ch in text.chars() {
-            if ch.len_utf8() + result.len() > max_len {
-                break;
+pub fn preview_html_bytes<'x>(html: Cow<'_, str>, max_bytes: usize) -> Cow<'x, str> {
+    preview_text_bytes(html_to_text(html.as_ref()).into(), max_bytes)
+}
+
+/// Truncates the text to at most `max_len` characters (or grapheme clusters
+/// when the `unicode_segmentation` feature is enabled).
+pub fn preview_text<'x>(text: Cow<'_, str>, max_len: usize) -> Cow<'x, str> {
+    truncate_units(text, max_len, |_| 1)
+}
+
+/// Truncates the text to at most `max_bytes` bytes without splitting
+/// characters (or grapheme clusters when the `unicode_segmentation`
+/// feature is enabled).
+pub fn preview_text_bytes<'x>(text: Cow<'_, str>, max_bytes: usize) -> Cow<'x, str> {
+    truncate_units(text, max_bytes, str::len)
+}
+
+pub fn truncate_text<'x>(text: Cow<'_, str>, max_len: usize) -> Cow<'x, str> {
+    preview_text_bytes(text, max_len)
+}
+
+fn truncate_units<'x>(
+    text: Cow<'_, str>,
+    max_len: usize,
+    unit_len: fn(&str) -> usize,
+) -> Cow<'x, str> {
+    let add_dots = max_len > 6;
+    let limit = if add_dots { max_len - 3 } else { max_len };
+    let mut len = 0;
+    let mut offset = 0;
+    let mut cut_offset = 0;
+
+    for unit in text_units(text.as_ref()) {
+        len += unit_len(unit);
+        if len > max_len {
+            let mut result = String::with_capacity(cut_offset + 3);
+            result.push_str(&text[..cut_offset]);
+            if add_dots {
+                result.push_str("...");
             }
-            result.push(ch);
+            return result.into();
         }
-        if add_dots {
-            result.push_str("...");
+        offset += unit.len();
+        if len <= limit {
+            cut_offset = offset;
         }
-        result.into()
-    } else {
-        text.into_owned().into()
     }
+
+    text.into_owned().into()
 }
 
-pub fn truncate_text<'x>(text: Cow<'_, str>, max_len: usize) -> Cow<'x, str> {
-    preview_text(text, max_len)
+#[cfg(feature = "unicode_segmentation")]
+#[inline(always)]
+fn text_units(text: &str) -> impl Iterator<Item = &str> {
+    unicode_segmentation::UnicodeSegmentation::graphemes(text, true)
+}
+
+#[cfg(not(feature = "unicode_segmentation"))]
+#[inline(always)]
+fn text_units(text: &str) -> impl Iterator<Item = &str> {
+    text.char_indices()
+        .map(|(pos, ch)| &text[pos..pos + ch.len_utf8()])
 }
 
 pub fn truncate_html<'x>(html: Cow<'_, str>, mut max_len: usize) -> Cow<'x, str> {
@@ -137,6 +175,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn text_preview_multibyte() {
+        let text = "ñandú 🦀🦀🦀 ☕☕☕ ハロー・ワールド";
+
+        for max_len in 0..=text.len() + 1 {
+            let preview = super::preview_text(text.into(), max_len);
+            assert!(preview.chars().count() <= max_len.max(3));
+            let preview = super::preview_text_bytes(text.into(), max_len);
+            assert!(preview.len() <= max_len.max(3));
+        }
+
+        assert_eq!(super::preview_text(text.into(), 13), "ñandú 🦀🦀🦀 ...");
+        assert_eq!(super::preview_text_bytes(text.into(), 16), "ñandú 🦀...");
+        assert_eq!(super::preview_text(text.into(), 100), text);
+    }
+
+    #[cfg(feature = "unicode_segmentation")]
+    #[test]
+    fn text_preview_graphemes() {
+        // Family emoji (ZWJ sequence) and 'e' followed by a combining acute accent
+        let text = "hi 👨\u{200d}👩\u{200d}👧 cafe\u{301} bonjour";
+
+        assert_eq!(
+            super::preview_text(text.into(), 12),
+            "hi 👨\u{200d}👩\u{200d}👧 cafe\u{301}..."
+        );
+        assert_eq!(super::preview_text_bytes(text.into(), 20), "hi ...");
+    }
+
     #[test]
     fn html_truncate() {
         for (html, expected_result) in [
@@ -77,6 +77,18 @@ R0lGODlhAQABAIAAAAAAAP///yH5BAEAAAAALAAAAAABAAEAAAIBRAA7
         message.parts[0].headers
     );
 
+    let comments_message = MessageParser::default()
+        .with_address_comments(true)
+        .parse(input)
+        .unwrap();
+    assert_eq!(
+        bincode::deserialize::<Vec<Header>>(
+            &bincode::serialize(&comments_message.parts[0].headers).unwrap()
+        )
+        .unwrap(),
+        comments_message.parts[0].headers
+    );
+
     assert_eq!(
         message.from().unwrap().first().unwrap(),
         &Addr::new(
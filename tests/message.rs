@@ -0,0 +1,1592 @@
+/*
+ * Copyright Stalwart Labs Ltd. See the COPYING
+ * file at the top-level directory of this distribution.
+ *
+ * Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+ * https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+ * <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+ * option. This file may not be copied, modified, or distributed
+ * except according to those terms.
+ */
+
+use std::{borrow::Cow, net::IpAddr};
+
+use mail_parser::{
+    Address, GetHeader, HeaderName, HeaderValue, InlineImage, ListId, Message, MessageParser,
+    MessageSecurity, MimeHeaders, Priority, SecurityProtocol,
+};
+
+#[test]
+fn message_id_accessors() {
+    let input = concat!(
+        "Message-ID: <first@example.com> <second@example.com>\n",
+        "In-Reply-To: <parent@example.com>\n",
+        "References: <root@example.com>\n",
+        "  <child@example.com>\n",
+        "\t<grandchild@example.com>\n",
+        "  <parent@example.com>\n",
+        "Subject: test\n",
+        "\n",
+        "Body\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(message.message_id(), Some("first@example.com"));
+    assert_eq!(message.in_reply_to(), vec!["parent@example.com"]);
+    assert_eq!(
+        message.references(),
+        vec![
+            "root@example.com",
+            "child@example.com",
+            "grandchild@example.com",
+            "parent@example.com"
+        ]
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject: test\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.message_id(), None);
+    assert!(message.in_reply_to().is_empty());
+    assert!(message.references().is_empty());
+}
+
+#[test]
+fn part_charset() {
+    let input = concat!(
+        "Content-Type: multipart/mixed; boundary=\"b\"\n",
+        "\n",
+        "--b\n",
+        "Content-Type: text/plain; charset=Latin1\n",
+        "\n",
+        "Hola\n",
+        "--b\n",
+        "Content-Type: text/html; charset=\"UTF-8\"\n",
+        "\n",
+        "<p>Hello</p>\n",
+        "--b\n",
+        "Content-Type: text/plain\n",
+        "\n",
+        "No charset\n",
+        "--b\n",
+        "Content-Type: application/octet-stream; charset=utf-8\n",
+        "\n",
+        "binary\n",
+        "--b--\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(message.parts[1].charset(), Some("Latin1"));
+    assert_eq!(message.parts[1].normalized_charset(), Some("ISO-8859-1"));
+    assert_eq!(message.parts[2].charset(), Some("UTF-8"));
+    assert_eq!(message.parts[2].normalized_charset(), Some("UTF-8"));
+    assert_eq!(message.parts[3].charset(), None);
+    assert_eq!(message.parts[3].normalized_charset(), None);
+    assert_eq!(message.parts[4].charset(), None);
+    assert_eq!(message.parts[4].normalized_charset(), None);
+}
+
+#[test]
+fn thread_index_and_topic() {
+    let input = concat!(
+        "Thread-Topic: =?utf-8?q?Caf=C3=A9?= meeting\n",
+        "Thread-Index: AdfeXQowAAECAwQFBgcICQoLDA0ODwECAwQF\n",
+        "Subject: RE: =?utf-8?q?Caf=C3=A9?= meeting\n",
+        "\n",
+        "Body\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(message.thread_topic().unwrap(), "Café meeting");
+    let index = message.thread_index().unwrap();
+    assert_eq!(index.date.to_rfc3339(), "2021-11-20T22:22:01Z");
+    assert_eq!(index.guid_string(), "00010203-0405-0607-0809-0a0b0c0d0e0f");
+    assert_eq!(index.children.len(), 1);
+
+    let message = MessageParser::default()
+        .parse("Subject: test\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.thread_index(), None);
+    assert_eq!(message.thread_topic(), None);
+}
+
+#[test]
+fn sent_and_received_dates() {
+    let input = concat!(
+        "Received: from mx.example.com (mx.example.com [192.0.2.1])\n",
+        "\tby inbound.example.org; Tue, 1 Jul 2003 10:52:37 +0200\n",
+        "Received: from relay.example.net by mx.example.com;\n",
+        "\tTue, 1 Jul 2003 10:50:12 +0200\n",
+        "Date: yesterday afternoon\n",
+        "Subject: test\n",
+        "\n",
+        "Body\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(message.date(), None);
+    assert_eq!(message.sent_date(), None);
+    assert_eq!(
+        message.received_date().unwrap().to_rfc3339(),
+        "2003-07-01T10:52:37+02:00"
+    );
+
+    let input = concat!(
+        "Date: Mon, 45 Jan 2020 10:00:00 +0000\n",
+        "Date: Mon, 6 Jan 2020 10:00:00 +0000\n",
+        "Subject: test\n",
+        "\n",
+        "Body\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(
+        message.sent_date().unwrap().to_rfc3339(),
+        "2020-01-06T10:00:00Z"
+    );
+    assert_eq!(message.received_date(), None);
+}
+
+#[test]
+fn message_security() {
+    let security = |content_type: &str, body: &str| {
+        MessageParser::default()
+            .parse(format!("Subject: test\nContent-Type: {content_type}\n\n{body}").as_bytes())
+            .unwrap()
+            .security()
+            .into_owned()
+    };
+
+    assert_eq!(
+        security(
+            "multipart/signed; protocol=\"application/pkcs7-signature\"; micalg=sha-256; boundary=\"b\"",
+            concat!(
+                "--b\nContent-Type: text/plain\n\nHello\n",
+                "--b\nContent-Type: application/pkcs7-signature; name=smime.p7s\n\nMIAG\n",
+                "--b--\n"
+            )
+        ),
+        MessageSecurity {
+            signed: Some(SecurityProtocol::SMime),
+            encrypted: None,
+            micalg: Some(Cow::Borrowed("sha-256")),
+        }
+    );
+    assert_eq!(
+        security(
+            "multipart/signed; micalg=pgp-sha512; protocol=\"application/pgp-signature\"; boundary=b",
+            concat!(
+                "--b\nContent-Type: text/plain\n\nHello\n",
+                "--b\nContent-Type: application/pgp-signature\n\n-----BEGIN PGP SIGNATURE-----\n",
+                "--b--\n"
+            )
+        ),
+        MessageSecurity {
+            signed: Some(SecurityProtocol::Pgp),
+            encrypted: None,
+            micalg: Some(Cow::Borrowed("pgp-sha512")),
+        }
+    );
+    assert_eq!(
+        security(
+            "multipart/encrypted; protocol=\"application/pgp-encrypted\"; boundary=b",
+            concat!(
+                "--b\nContent-Type: application/pgp-encrypted\n\nVersion: 1\n",
+                "--b\nContent-Type: application/octet-stream\n\n-----BEGIN PGP MESSAGE-----\n",
+                "--b--\n"
+            )
+        ),
+        MessageSecurity {
+            signed: None,
+            encrypted: Some(SecurityProtocol::Pgp),
+            micalg: None,
+        }
+    );
+    assert_eq!(
+        security(
+            "application/pkcs7-mime; smime-type=enveloped-data; name=smime.p7m",
+            "MIAGCSqGSIb3DQEHA6CAMIACAQAxggHXMIIB0wIBADCBuzCBtTELMAkGA1UEBhMC\n"
+        ),
+        MessageSecurity {
+            signed: None,
+            encrypted: Some(SecurityProtocol::SMime),
+            micalg: None,
+        }
+    );
+    assert_eq!(
+        security(
+            "application/x-pkcs7-mime; smime-type=signed-data; name=smime.p7m",
+            "MIAGCSqGSIb3DQEHAqCAMIACAQExDzANBglghkgBZQMEAgEFADCABgkqhkiG9w0B\n"
+        ),
+        MessageSecurity {
+            signed: Some(SecurityProtocol::SMime),
+            encrypted: None,
+            micalg: None,
+        }
+    );
+    assert_eq!(
+        security(
+            "multipart/mixed; boundary=b",
+            "--b\nContent-Type: text/plain\n\nHello\n--b--\n"
+        ),
+        MessageSecurity::default()
+    );
+}
+
+#[test]
+fn owned_message_outlives_input() {
+    let message = {
+        let input = concat!(
+            "Subject: outer\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "Hello\n",
+            "--b\n",
+            "Content-Type: message/rfc822\n",
+            "\n",
+            "Subject: inner\n",
+            "\n",
+            "Inner body\n",
+            "--b--\n"
+        )
+        .to_string();
+        MessageParser::default()
+            .parse(input.as_bytes())
+            .unwrap()
+            .into_owned()
+    };
+
+    assert!(matches!(message.raw_message, Cow::Owned(_)));
+    assert_eq!(message.subject(), Some("outer"));
+    assert_eq!(message.body_text(0).unwrap(), "Hello");
+
+    let nested = message.attachment(0).unwrap().message().unwrap();
+    assert!(matches!(nested.raw_message, Cow::Owned(_)));
+    assert_eq!(nested.subject(), Some("inner"));
+    assert_eq!(nested.body_text(0).unwrap(), "Inner body");
+}
+
+#[test]
+fn raw_and_decoded_sizes() {
+    let input = concat!(
+        "Subject: outer\n",
+        "Content-Type: multipart/mixed; boundary=b\n",
+        "\n",
+        "--b\n",
+        "Content-Type: text/plain\n",
+        "\n",
+        "Hello\n",
+        "--b\n",
+        "Content-Type: application/octet-stream\n",
+        "Content-Transfer-Encoding: base64\n",
+        "\n",
+        "aGVsbG8gd29ybGQ=\n",
+        "--b\n",
+        "Content-Type: message/rfc822\n",
+        "\n",
+        "Subject: inner\n",
+        "\n",
+        "Inner body\n",
+        "--b--\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(message.raw_len(), input.len());
+    assert_eq!(
+        message.decoded_size(),
+        "Hello".len() + "hello world".len() + "Inner body".len()
+    );
+
+    let part = message.attachment(1).unwrap();
+    let nested = part.message().unwrap();
+    assert_eq!(nested.raw_len(), "Subject: inner\n\nInner body".len());
+    assert_eq!(part.body.len(), nested.raw_len());
+    assert_eq!(part.len(), nested.raw_len());
+    assert_eq!(nested.decoded_size(), "Inner body".len());
+}
+
+#[test]
+fn message_id_valid() {
+    for (header, expected) in [
+        ("Message-ID: <1234@local.machine.example>\n", true),
+        ("Message-ID:\n <1234@local.machine.example>\n", true),
+        ("Message-ID: 1234@local.machine.example\n", false),
+        ("Message-ID: <12 34@local.machine.example>\n", false),
+        ("Message-ID: <1234>\n", false),
+        ("Subject: no id\n", false),
+    ] {
+        let input = format!("{header}\nbody");
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert_eq!(message.message_id_valid(), expected, "{header:?}");
+    }
+}
+
+#[test]
+fn from_display_name() {
+    for (from, expected) in [
+        ("John Doe <jdoe@example.com>", Some("John Doe")),
+        ("\"John Doe\" <jdoe@example.com>", Some("John Doe")),
+        ("=?utf-8?Q?Jos=C3=A9?= <jose@example.com>", Some("José")),
+        ("\"\\\"Quoted\\\"\" <quoted@example.com>", Some("Quoted")),
+        ("<jdoe@example.com>", Some("jdoe")),
+        ("jdoe@example.com", Some("jdoe")),
+        ("\"\" <jdoe@example.com>", Some("jdoe")),
+        ("<@example.com>", Some("@example.com")),
+        ("undisclosed", Some("undisclosed")),
+        ("<>", None),
+    ] {
+        let input = format!("From: {from}\n\nbody");
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert_eq!(message.from_display_name().as_deref(), expected, "{from:?}");
+    }
+
+    let message = MessageParser::default()
+        .parse(b"Subject: no sender\n\nbody")
+        .unwrap();
+    assert_eq!(message.from_display_name(), None);
+}
+
+#[test]
+fn body_fingerprint() {
+    let parse = |input: &str| {
+        MessageParser::default()
+            .parse(input)
+            .unwrap()
+            .body_fingerprint()
+    };
+
+    let base64 = parse(concat!(
+        "Subject: base64\n",
+        "Content-Type: text/plain; charset=utf-8\n",
+        "Content-Transfer-Encoding: base64\n",
+        "\n",
+        "SGVsbG8gd8O2cmxkIQ==\n"
+    ));
+    let quoted_printable = parse(concat!(
+        "Subject: quoted-printable\n",
+        "Message-ID: <1234@example.com>\n",
+        "Content-Type: text/plain; charset=utf-8\n",
+        "Content-Transfer-Encoding: quoted-printable\n",
+        "\n",
+        "Hello w=C3=B6rld!"
+    ));
+    let latin1 = parse(concat!(
+        "Content-Type: text/plain; charset=iso-8859-1\n",
+        "Content-Transfer-Encoding: quoted-printable\n",
+        "\n",
+        "Hello w=F6rld!"
+    ));
+    let different = parse(concat!(
+        "Subject: base64\n",
+        "Content-Type: text/plain; charset=utf-8\n",
+        "\n",
+        "Hello world!"
+    ));
+
+    assert_eq!(base64, quoted_printable);
+    assert_eq!(base64, latin1);
+    assert_ne!(base64, different);
+
+    let multipart = |first: &str, second: &str| {
+        parse(&format!(
+            concat!(
+                "Content-Type: multipart/mixed; boundary=b\n\n",
+                "--b\nContent-Type: text/plain\n\n{}\n",
+                "--b\nContent-Type: text/plain\n\n{}\n",
+                "--b--\n"
+            ),
+            first, second
+        ))
+    };
+    assert_eq!(multipart("ab", "c"), multipart("ab", "c"));
+    assert_ne!(multipart("ab", "c"), multipart("a", "bc"));
+    assert_ne!(multipart("ab", "c"), multipart("c", "ab"));
+}
+
+#[test]
+fn priority() {
+    for (headers, expected) in [
+        ("X-Priority: 1\n", Some(Priority::High)),
+        ("X-Priority: 2 (High)\n", Some(Priority::High)),
+        ("X-Priority: 3 (Normal)\n", Some(Priority::Normal)),
+        ("X-Priority: 5 (Lowest)\n", Some(Priority::Low)),
+        ("Importance: low\n", Some(Priority::Low)),
+        ("Importance: High\n", Some(Priority::High)),
+        ("Priority: urgent\n", Some(Priority::High)),
+        ("Priority: non-urgent\n", Some(Priority::Low)),
+        (
+            "X-Priority: 1\nImportance: low\nPriority: non-urgent\n",
+            Some(Priority::High),
+        ),
+        ("X-Priority: high\nImportance: low\n", Some(Priority::Low)),
+        (
+            "Importance: normal\nPriority: urgent\n",
+            Some(Priority::Normal),
+        ),
+        ("Importance: whenever\n", None),
+        ("Subject: none\n", None),
+    ] {
+        let input = format!("{headers}\nbody");
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert_eq!(message.priority(), expected, "{headers:?}");
+    }
+}
+
+#[test]
+fn attachment_by_name() {
+    let input = concat!(
+        "Content-Type: multipart/mixed; boundary=b\n",
+        "\n",
+        "--b\n",
+        "Content-Type: text/plain\n",
+        "\n",
+        "Body\n",
+        "--b\n",
+        "Content-Type: application/pdf\n",
+        "Content-Disposition: attachment; filename=\"Report.pdf\"\n",
+        "\n",
+        "first\n",
+        "--b\n",
+        "Content-Type: image/png; name=\"logo.png\"\n",
+        "\n",
+        "logo\n",
+        "--b\n",
+        "Content-Type: application/pdf; name=\"report.PDF\"\n",
+        "\n",
+        "second\n",
+        "--b--\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(
+        message.attachment_by_name("report.pdf").unwrap().contents(),
+        b"first"
+    );
+    assert_eq!(
+        message.attachment_by_name("LOGO.png").unwrap().contents(),
+        b"logo"
+    );
+    assert!(message.attachment_by_name("missing.txt").is_none());
+}
+
+#[test]
+fn calendar_parts() {
+    let input = concat!(
+        "Content-Type: multipart/mixed; boundary=b\n",
+        "\n",
+        "--b\n",
+        "Content-Type: multipart/alternative; boundary=a\n",
+        "\n",
+        "--a\n",
+        "Content-Type: text/plain\n",
+        "\n",
+        "You have been invited\n",
+        "--a\n",
+        "Content-Type: text/calendar; method=REQUEST; charset=utf-8\n",
+        "\n",
+        "BEGIN:VCALENDAR\n",
+        "--a--\n",
+        "--b\n",
+        "Content-Type: TEXT/Calendar; name=\"invite.ics\"\n",
+        "\n",
+        "BEGIN:VCALENDAR\n",
+        "--b--\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    let parts = message.calendar_parts().collect::<Vec<_>>();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].0.text_contents(), Some("BEGIN:VCALENDAR"));
+    assert_eq!(parts[0].1, Some("REQUEST"));
+    assert_eq!(parts[1].1, None);
+
+    let message = MessageParser::default()
+        .parse("Content-Type: text/plain\n\nNo invites")
+        .unwrap();
+    assert_eq!(message.calendar_parts().count(), 0);
+}
+
+#[test]
+fn keywords() {
+    let input = concat!(
+        "Keywords: project, Urgent,\n",
+        "  review\n",
+        "Subject: tagged\n",
+        "Keywords: urgent, =?utf-8?q?caf=C3=A9?=, , REVIEW, CAFÉ, follow-up,\n",
+        "\n",
+        "body"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+    assert_eq!(
+        message.keywords(),
+        vec!["project", "Urgent", "review", "café", "follow-up"]
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject: untagged\n\nbody")
+        .unwrap();
+    assert!(message.keywords().is_empty());
+}
+
+#[test]
+fn nested_alternative_bodies() {
+    let input = concat!(
+        "Content-Type: multipart/mixed; boundary=m\n",
+        "\n",
+        "--m\n",
+        "Content-Type: multipart/alternative; boundary=a\n",
+        "\n",
+        "--a\n",
+        "Content-Type: text/plain\n",
+        "\n",
+        "Plain body\n",
+        "--a\n",
+        "Content-Type: text/html\n",
+        "\n",
+        "<p>HTML body</p>\n",
+        "--a--\n",
+        "--m\n",
+        "Content-Type: text/plain; name=notes.txt\n",
+        "Content-Disposition: attachment\n",
+        "\n",
+        "Attached notes\n",
+        "--m--\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+
+    assert_eq!(message.text_body, [2]);
+    assert_eq!(message.html_body, [3]);
+    assert_eq!(message.attachments, [4]);
+    assert_eq!(message.body_text(0).unwrap(), "Plain body");
+    assert_eq!(message.body_html(0).unwrap(), "<p>HTML body</p>");
+    assert_eq!(message.text_body_count(), 1);
+    assert_eq!(message.html_body_count(), 1);
+    assert_eq!(message.attachment_count(), 1);
+    assert_eq!(
+        message.attachment(0).unwrap().text_contents(),
+        Some("Attached notes")
+    );
+}
+
+#[test]
+fn has_body() {
+    let parser = MessageParser::default();
+
+    for input in [
+        "From: john@example.com\nSubject: headers only\n",
+        "From: john@example.com\nSubject: headers only\n\n",
+    ] {
+        let message = parser.parse(input).unwrap();
+        assert!(!message.has_body(), "{input:?}");
+        assert!(message.is_headers_only(), "{input:?}");
+    }
+
+    let message = parser
+        .parse("From: john@example.com\nSubject: hello\n\nHello world\n")
+        .unwrap();
+    assert!(message.has_body());
+    assert!(!message.is_headers_only());
+
+    let message = parser
+        .parse(concat!(
+            "Content-Type: multipart/mixed; boundary=b\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "\n",
+            "--b--\n"
+        ))
+        .unwrap();
+    assert!(!message.has_body());
+    assert!(!message.is_headers_only());
+}
+
+#[test]
+fn read_receipt_to() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Return-Receipt-To: legacy@example.com\n",
+            "Disposition-Notification-To: Jane <jane@example.com>, john@example.com\n",
+            "\n",
+            "Body\n"
+        ))
+        .unwrap();
+    let addr = message.read_receipt_to().unwrap();
+    assert_eq!(addr.name.as_deref(), Some("Jane"));
+    assert_eq!(addr.address.as_deref(), Some("jane@example.com"));
+    assert_eq!(
+        message
+            .header(HeaderName::DispositionNotificationTo)
+            .and_then(|value| value.as_address())
+            .map(|address| address.iter().count()),
+        Some(2)
+    );
+
+    let message = MessageParser::default()
+        .parse("return-receipt-to: <legacy@example.com>\n\nBody\n")
+        .unwrap();
+    assert_eq!(
+        message.read_receipt_to().unwrap().address.as_deref(),
+        Some("legacy@example.com")
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject: no receipt\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.read_receipt_to(), None);
+}
+
+#[test]
+fn user_agent_and_organization() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "X-Mailer: Microsoft Outlook 16.0\n",
+            "User-Agent: =?utf-8?q?Thunderbird_=E2=9C=93?=\n",
+            "Organization: =?iso-8859-1?q?Caf=E9?= Corp\n",
+            "\n",
+            "Body\n"
+        ))
+        .unwrap();
+    assert_eq!(message.user_agent(), Some("Thunderbird ✓"));
+    assert_eq!(message.organization(), Some("Café Corp"));
+
+    let message = MessageParser::default()
+        .parse("x-mailer: Apple Mail (2.3731)\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.user_agent(), Some("Apple Mail (2.3731)"));
+    assert_eq!(message.organization(), None);
+
+    let message = MessageParser::default()
+        .parse("Subject: test\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.user_agent(), None);
+}
+
+#[test]
+fn authors() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: Alice <a@x>, b@y\n",
+            "Sender: Alice <a@x>\n",
+            "\n",
+            "Body\n"
+        ))
+        .unwrap();
+    assert_eq!(
+        message
+            .authors()
+            .map(|addr| (addr.name(), addr.address()))
+            .collect::<Vec<_>>(),
+        [(Some("Alice"), Some("a@x")), (None, Some("b@y"))]
+    );
+    assert!(matches!(message.from(), Some(Address::List(list)) if list.len() == 2));
+
+    let message = MessageParser::default()
+        .parse("Subject: no authors\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.authors().count(), 0);
+}
+
+#[test]
+fn all_addresses() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: Alice <alice@example.com>\n",
+            "Sender: sender@example.com\n",
+            "Reply-To: reply@example.com\n",
+            "To: Team: bob@example.com, carol@example.com;, dave@example.com\n",
+            "Cc: cc@example.com\n",
+            "Bcc: bcc@example.com\n",
+            "Resent-From: resent-from@example.com\n",
+            "Resent-Sender: resent-sender@example.com\n",
+            "Resent-To: resent-to@example.com\n",
+            "Resent-Cc: resent-cc@example.com\n",
+            "Resent-Bcc: resent-bcc@example.com\n",
+            "List-Post: <mailto:list@example.com>\n",
+            "\n",
+            "Body\n"
+        ))
+        .unwrap();
+
+    assert_eq!(
+        message
+            .all_addresses()
+            .map(|(name, addr)| (name.as_str(), addr.address().unwrap()))
+            .collect::<Vec<_>>(),
+        [
+            ("From", "alice@example.com"),
+            ("Sender", "sender@example.com"),
+            ("Reply-To", "reply@example.com"),
+            ("To", "bob@example.com"),
+            ("To", "carol@example.com"),
+            ("To", "dave@example.com"),
+            ("Cc", "cc@example.com"),
+            ("Bcc", "bcc@example.com"),
+            ("Resent-From", "resent-from@example.com"),
+            ("Resent-Sender", "resent-sender@example.com"),
+            ("Resent-To", "resent-to@example.com"),
+            ("Resent-Cc", "resent-cc@example.com"),
+            ("Resent-Bcc", "resent-bcc@example.com"),
+        ]
+    );
+}
+
+#[test]
+fn comments() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Comments: first\n",
+            "Subject: test\n",
+            "Comments: =?utf-8?q?caf=C3=A9?=\n",
+            "Comments: ticket=1234;\n priority=low\n",
+            "\n",
+            "Body\n"
+        ))
+        .unwrap();
+    assert_eq!(
+        message.comments(),
+        ["first", "café", "ticket=1234; priority=low"]
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject: test\n\nBody\n")
+        .unwrap();
+    assert!(message.comments().is_empty());
+}
+
+#[test]
+fn origin_ip() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Received: from mx.example.com (mx.example.com [10.0.0.5])\n",
+            "\tby store.example.com; Tue, 1 Jun 2021 10:00:03 +0000\n",
+            "Received: from relay.sender.net (relay.sender.net [198.51.100.7])\n",
+            "\tby mx.example.com; Tue, 1 Jun 2021 10:00:02 +0000\n",
+            "Received: from laptop (dsl.isp.net [203.0.113.25])\n",
+            "\tby relay.sender.net; Tue, 1 Jun 2021 10:00:01 +0000\n",
+            "Received: from localhost (localhost [127.0.0.1])\n",
+            "\tby laptop; Tue, 1 Jun 2021 10:00:00 +0000\n",
+            "Received: by laptop (Postfix, from userid 1000)\n",
+            "\tid 1234; Tue, 1 Jun 2021 10:00:00 +0000\n",
+            "\n",
+            "Body\n"
+        ))
+        .unwrap();
+
+    let is_trusted = |ip: &IpAddr| match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private(),
+        IpAddr::V6(ip) => ip.is_loopback(),
+    };
+    assert_eq!(
+        message.origin_ip(is_trusted),
+        Some("203.0.113.25".parse().unwrap())
+    );
+    assert_eq!(
+        message.origin_ip(|_| false),
+        Some("127.0.0.1".parse().unwrap())
+    );
+    assert_eq!(message.origin_ip(|_| true), None);
+
+    let message = MessageParser::default()
+        .parse("Subject: no received\n\nBody\n")
+        .unwrap();
+    assert_eq!(message.origin_ip(|_| false), None);
+}
+
+#[test]
+fn delivered_to() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Delivered-To: jdoe@example.com\r\n",
+            "X-Original-To: sales@example.com\r\n",
+            "Delivered-To: sales@example.com\r\n",
+            "Delivered-To: \"Sales list\" <sales-list@example.com>\r\n",
+            "From: jane@example.org\r\n",
+            "\r\n",
+            "Hi\r\n"
+        ))
+        .unwrap();
+
+    assert_eq!(
+        message
+            .delivered_to()
+            .into_iter()
+            .map(|addr| addr.address().unwrap())
+            .collect::<Vec<_>>(),
+        [
+            "jdoe@example.com",
+            "sales@example.com",
+            "sales-list@example.com"
+        ]
+    );
+    assert_eq!(
+        message
+            .header(HeaderName::XOriginalTo)
+            .and_then(|value| value.as_address())
+            .and_then(|address| address.first())
+            .and_then(|addr| addr.address()),
+        Some("sales@example.com")
+    );
+    assert!(MessageParser::default()
+        .parse("Subject: test\r\n\r\nHi\r\n")
+        .unwrap()
+        .delivered_to()
+        .is_empty());
+}
+
+#[test]
+fn parts_by_type() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Content-Type: multipart/mixed; boundary=b\n",
+            "\n",
+            "--b\n",
+            "Content-Type: text/plain\n",
+            "\n",
+            "See attached.\n",
+            "--b\n",
+            "Content-Type: image/png; name=a.png\n",
+            "\n",
+            "PNG\n",
+            "--b\n",
+            "Content-Type: IMAGE/JPEG; name=b.jpg\n",
+            "\n",
+            "JPEG\n",
+            "--b\n",
+            "Content-Type: application/pdf; name=c.pdf\n",
+            "\n",
+            "PDF\n",
+            "--b--\n"
+        ))
+        .unwrap();
+
+    assert_eq!(
+        message
+            .parts_by_type("image", None)
+            .map(|part| part.attachment_name().unwrap())
+            .collect::<Vec<_>>(),
+        ["a.png", "b.jpg"]
+    );
+    assert_eq!(
+        message
+            .parts_by_type("image", Some("jpeg"))
+            .map(|part| part.attachment_name().unwrap())
+            .collect::<Vec<_>>(),
+        ["b.jpg"]
+    );
+    assert_eq!(
+        message
+            .parts_by_type("Application", Some("PDF"))
+            .map(|part| part.attachment_name().unwrap())
+            .collect::<Vec<_>>(),
+        ["c.pdf"]
+    );
+    assert_eq!(message.parts_by_type("multipart", None).count(), 1);
+    assert_eq!(message.parts_by_type("audio", None).count(), 0);
+}
+
+#[test]
+fn offsets_after_into_owned() {
+    let input = concat!(
+        "Subject: Offsets\r\n",
+        "Content-Type: multipart/mixed; boundary=b\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Hello\r\n",
+        "--b\r\n",
+        "Content-Type: message/rfc822\r\n",
+        "\r\n",
+        "X-Inner: value\r\n",
+        "\r\n",
+        "Inner body\r\n",
+        "--b--\r\n"
+    )
+    .to_string();
+    let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+    let header = |message: &Message<'_>, name: &str| {
+        let header = message.parts[0].headers.header(name).unwrap();
+        message.raw_bytes()[header.offset_start..header.offset_end].to_vec()
+    };
+    let subject = header(&message, "Subject");
+    let part = &message.parts[1];
+    let body = message.raw_bytes()[part.offset_body..part.offset_end].to_vec();
+    assert_eq!(subject, b" Offsets\r\n");
+    assert_eq!(message.raw_bytes(), input.as_bytes());
+
+    let message = message.into_owned();
+    drop(input);
+    assert_eq!(header(&message, "Subject"), subject);
+    let part = &message.parts[1];
+    assert_eq!(message.raw_bytes()[part.offset_body..part.offset_end], body);
+
+    let nested = message.attachment(0).unwrap().message().unwrap();
+    assert_eq!(header(nested, "X-Inner"), b" value\r\n");
+    assert_eq!(nested.raw_message(), b"X-Inner: value\r\n\r\nInner body");
+}
+
+#[test]
+fn in_reply_to_with_garbage() {
+    for (header, expected) in [
+        (
+            "In-Reply-To: \"On Mon, 1 Jan 2024, John <jdoe@example.com> wrote\" <id@x>",
+            vec!["id@x"],
+        ),
+        (
+            "In-Reply-To: Your message of Mon, 1 Jan 2024\r\n <a@x> (John) <b@y>",
+            vec!["a@x", "b@y"],
+        ),
+        ("In-Reply-To: Your message of Mon, 1 Jan 2024", vec![]),
+    ] {
+        let input = format!("{header}\r\nSubject: test\r\n\r\nHi\r\n");
+        let message = MessageParser::default().parse(input.as_bytes()).unwrap();
+        assert_eq!(message.in_reply_to(), expected, "failed for {header:?}");
+    }
+}
+
+#[test]
+fn raw_subject() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Subject: =?utf-8?q?Caf=C3=A9?= menu\r\n",
+            " for today\r\n",
+            "\r\n",
+            "Hi\r\n"
+        ))
+        .unwrap();
+    assert_eq!(message.subject(), Some("Café menu for today"));
+    assert_eq!(
+        message.raw_subject(),
+        Some(&b"=?utf-8?q?Caf=C3=A9?= menu\r\n for today"[..])
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject:\r\nFrom: a@b.com\r\n\r\nHi\r\n")
+        .unwrap();
+    assert_eq!(message.raw_subject(), Some(&b""[..]));
+    assert_eq!(
+        MessageParser::default()
+            .parse("From: a@b.com\r\n\r\nHi\r\n")
+            .unwrap()
+            .raw_subject(),
+        None
+    );
+}
+
+#[test]
+fn display_headers() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: \"Doe, John\" <jdoe@example.com>\r\n",
+            "To: Friends: jane@example.com, bob@example.com;\r\n",
+            "Subject: =?utf-8?q?Caf=C3=A9?=\r\n",
+            "Date: Tue, 1 Jul 2003 10:52:37 +0200\r\n",
+            "Message-ID: <1234@example.com>\r\n",
+            "References: <1@example.com>\r\n <2@example.com>\r\n",
+            "Keywords: one, two\r\n",
+            "Received: from mail.example.org ([192.0.2.1])\r\n",
+            "\tby mx.example.com with ESMTP; Tue, 1 Jul 2003 10:52:37 +0200\r\n",
+            "Content-Type: text/plain; charset=utf-8; format=flowed\r\n",
+            "X-Custom: Custom value\r\n",
+            "Subject: second\r\n",
+            "\r\n",
+            "Body\r\n"
+        ))
+        .unwrap();
+
+    assert_eq!(
+        message.display_headers().collect::<Vec<_>>(),
+        [
+            ("From", "\"Doe, John\" <jdoe@example.com>"),
+            ("To", "Friends: jane@example.com, bob@example.com;"),
+            ("Subject", "Café"),
+            ("Date", "2003-07-01T10:52:37+02:00"),
+            ("Message-ID", "<1234@example.com>"),
+            ("References", "<1@example.com> <2@example.com>"),
+            ("Keywords", "one, two"),
+            (
+                "Received",
+                concat!(
+                    "from mail.example.org ([192.0.2.1]) by mx.example.com with ESMTP;",
+                    " Tue, 1 Jul 2003 10:52:37 +0200"
+                )
+            ),
+            (
+                "Content-Type",
+                "text/plain; charset=\"utf-8\"; format=\"flowed\""
+            ),
+            ("X-Custom", "Custom value"),
+            ("Subject", "second"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn content_language() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Subject: test\r\n",
+            "Content-Language: en, fr\r\n",
+            "Content-Type: multipart/mixed; boundary=b\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Language: de\r\n",
+            "\r\n",
+            "Hallo\r\n",
+            "--b--\r\n"
+        ))
+        .unwrap();
+    assert_eq!(message.content_language_tags(), vec!["en", "fr"]);
+    assert_eq!(message.parts[1].content_language_tags(), vec!["de"]);
+    assert_eq!(
+        message.content_language(),
+        &HeaderValue::TextList(vec!["en".into(), "fr".into()])
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject: test\r\n\r\nHello\r\n")
+        .unwrap();
+    assert!(message.content_language_tags().is_empty());
+    assert_eq!(message.content_language(), &HeaderValue::Empty);
+}
+
+#[cfg(feature = "lang_detect")]
+#[test]
+fn detect_language() {
+    let message = MessageParser::default()
+        .parse("Content-Language: fr\r\n\r\nThe body is written in English.\r\n")
+        .unwrap();
+    assert_eq!(message.detect_language(), Some("fr"));
+
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Subject: test\r\n",
+            "\r\n",
+            "Le petit chat dort tranquillement sur le canap\u{e9} du salon pendant que ",
+            "les enfants jouent dans le jardin avec leurs amis.\r\n"
+        ))
+        .unwrap();
+    assert_eq!(message.detect_language(), Some("fr"));
+
+    let message = MessageParser::default()
+        .parse("Subject: test\r\n\r\n12345\r\n")
+        .unwrap();
+    assert_eq!(message.detect_language(), None);
+}
+
+#[test]
+fn inline_images() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Subject: Images\r\n",
+            "Content-Type: multipart/related; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p><img src=\"cid:logo@example.com\"> ",
+            "<img src='CID:photo%40example.com'></p>\r\n",
+            "--b\r\n",
+            "Content-Type: image/png\r\n",
+            "Content-ID: <logo@example.com>\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "iVBORw0K\r\n",
+            "--b\r\n",
+            "Content-Type: image/jpeg; name=photo.jpg\r\n",
+            "Content-ID: <photo@example.com>\r\n",
+            "Content-Disposition: attachment; filename=photo.jpg\r\n",
+            "\r\n",
+            "JPEG\r\n",
+            "--b\r\n",
+            "Content-Type: image/gif\r\n",
+            "Content-ID: <unused@example.com>\r\n",
+            "Content-Disposition: attachment\r\n",
+            "\r\n",
+            "GIF\r\n",
+            "--b--\r\n"
+        ))
+        .unwrap();
+
+    assert_eq!(
+        message.inline_images(),
+        vec![
+            InlineImage {
+                part_id: 2,
+                cid: "logo@example.com",
+                content_type: "image/png".to_string(),
+                contents: b"\x89PNG\r\n",
+            },
+            InlineImage {
+                part_id: 3,
+                cid: "photo@example.com",
+                content_type: "image/jpeg".to_string(),
+                contents: b"JPEG",
+            },
+        ]
+    );
+
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Hello\r\n",
+            "--b\r\n",
+            "Content-Type: image/png\r\n",
+            "Content-ID: <logo@example.com>\r\n",
+            "Content-Disposition: inline\r\n",
+            "\r\n",
+            "PNG\r\n",
+            "--b--\r\n"
+        ))
+        .unwrap();
+    assert_eq!(
+        message
+            .inline_images()
+            .iter()
+            .map(|image| image.cid)
+            .collect::<Vec<_>>(),
+        vec!["logo@example.com"]
+    );
+}
+
+#[test]
+fn resent_headers() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Resent-From: Mary Smith <mary@example.net>\r\n",
+            "Resent-To: Jane Brown <j-brown@other.example>\r\n",
+            "Resent-Date: Mon, 24 Nov 1997 14:22:01 -0800\r\n",
+            "Resent-Message-ID: <78910@example.net>\r\n",
+            "Received: from x.example by y.example; Mon, 24 Nov 1997 14:22:05 -0800\r\n",
+            "Resent-From: John Doe <jdoe@machine.example>\r\n",
+            "Resent-To: Mary Smith <mary@example.net>\r\n",
+            "Resent-Cc: Ann <ann@example.org>\r\n",
+            "Resent-Date: Fri, 21 Nov 1997 10:01:10 -0600\r\n",
+            "From: John Doe <jdoe@machine.example>\r\n",
+            "Subject: Saying Hello\r\n",
+            "\r\n",
+            "Hello\r\n"
+        ))
+        .unwrap();
+    assert_eq!(
+        message.resent_date().map(|date| date.to_rfc3339()),
+        Some("1997-11-24T14:22:01-08:00".to_string())
+    );
+    assert_eq!(
+        message
+            .resent_from()
+            .and_then(|from| from.first())
+            .and_then(|addr| addr.address()),
+        Some("mary@example.net")
+    );
+    assert_eq!(
+        message
+            .resent_to()
+            .and_then(|to| to.first())
+            .and_then(|addr| addr.address()),
+        Some("j-brown@other.example")
+    );
+    assert_eq!(message.resent_cc(), None);
+    assert_eq!(
+        message.resent_message_id().as_text(),
+        Some("78910@example.net")
+    );
+
+    // Adjacent blocks, the repeated Resent-Date starts an older block
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Resent-Date: Tue, 25 Nov 1997 09:00:00 +0000\r\n",
+            "Resent-From: new@example.org\r\n",
+            "Resent-Date: Mon, 24 Nov 1997 09:00:00 +0000\r\n",
+            "Resent-From: old@example.org\r\n",
+            "Resent-Cc: old-cc@example.org\r\n",
+            "Subject: test\r\n",
+            "\r\n",
+            "Hello\r\n"
+        ))
+        .unwrap();
+    assert_eq!(
+        message.resent_date().map(|date| date.to_rfc3339()),
+        Some("1997-11-25T09:00:00Z".to_string())
+    );
+    assert_eq!(
+        message
+            .resent_from()
+            .and_then(|from| from.first())
+            .and_then(|addr| addr.address()),
+        Some("new@example.org")
+    );
+    assert_eq!(message.resent_cc(), None);
+
+    let message = MessageParser::default()
+        .parse("Subject: test\r\n\r\nHello\r\n")
+        .unwrap();
+    assert_eq!(message.resent_date(), None);
+    assert_eq!(message.resent_from(), None);
+    assert_eq!(message.resent_to(), None);
+}
+
+#[test]
+fn tnef_part() {
+    for (content_type, name) in [
+        ("application/ms-tnef", None),
+        ("Application/VND.MS-TNEF", None),
+        ("application/octet-stream", Some("WINMAIL.DAT")),
+    ] {
+        let mut input = String::from(concat!(
+            "From: john@example.org\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See attached\r\n",
+            "--b\r\n",
+        ));
+        input.push_str(&format!("Content-Type: {content_type}"));
+        if let Some(name) = name {
+            input.push_str(&format!("; name=\"{name}\""));
+        }
+        input.push_str(concat!(
+            "\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "eJ8+IgAAAQaQCAAEAAAAAAABAAEAAQeQBgAIAAAA5AQAAAAAAADoAAEIgAcAGAAAAElQTS5N\r\n",
+            "--b--\r\n"
+        ));
+
+        let message = MessageParser::default().parse(&input).unwrap();
+        assert!(message.has_tnef(), "{content_type}");
+        let part = message.tnef_part().unwrap();
+        assert!(part.is_tnef());
+        assert_eq!(message.parts.iter().position(|p| p == part), Some(2));
+        assert!(part.contents().starts_with(&[0x78, 0x9f, 0x3e, 0x22]));
+    }
+
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: john@example.org\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "See attached\r\n",
+            "--b\r\n",
+            "Content-Type: application/pdf; name=\"winmail.pdf\"\r\n",
+            "\r\n",
+            "%PDF-1.4\r\n",
+            "--b--\r\n"
+        ))
+        .unwrap();
+    assert!(!message.has_tnef());
+    assert_eq!(message.tnef_part(), None);
+}
+
+#[test]
+fn from_domain() {
+    for (from, expected) in [
+        ("John Doe <john@Example.ORG>", Some("example.org")),
+        ("john@example.org", Some("example.org")),
+        ("\"a@b\" <john@mail.example.org>", Some("mail.example.org")),
+        ("first@one.example, second@two.example", Some("one.example")),
+        (
+            "Team: first@one.example, second@two.example;",
+            Some("one.example"),
+        ),
+        ("john", None),
+        ("<john@>", None),
+        ("<>", None),
+    ] {
+        let input = format!("From: {from}\r\nSubject: test\r\n\r\nHello\r\n");
+        let message = MessageParser::default().parse(&input).unwrap();
+        assert_eq!(message.from_domain().as_deref(), expected, "{from:?}");
+    }
+
+    let message = MessageParser::default()
+        .parse("From: john@example.org\r\n\r\nHello\r\n")
+        .unwrap();
+    assert!(matches!(
+        message.from_domain(),
+        Some(std::borrow::Cow::Borrowed("example.org"))
+    ));
+
+    let message = MessageParser::default()
+        .parse("Subject: test\r\n\r\nHello\r\n")
+        .unwrap();
+    assert_eq!(message.from_domain(), None);
+}
+
+#[test]
+fn list_id() {
+    for (value, expected) in [
+        (
+            "\"Stalwart Users\" <users.stalw.art>",
+            Some(ListId {
+                description: Some("Stalwart Users"),
+                id: "users.stalw.art",
+            }),
+        ),
+        (
+            "=?utf-8?q?Caf=C3=A9_Lovers?= <cafe.lists.example.org>",
+            Some(ListId {
+                description: Some("Café Lovers"),
+                id: "cafe.lists.example.org",
+            }),
+        ),
+        (
+            "<list-header.nisto.com>",
+            Some(ListId {
+                description: None,
+                id: "list-header.nisto.com",
+            }),
+        ),
+        ("<>", None),
+    ] {
+        let input = format!("List-ID: {value}\r\nSubject: test\r\n\r\nHello\r\n");
+        let message = MessageParser::default().parse(&input).unwrap();
+        assert_eq!(message.list_id(), expected, "{value:?}");
+    }
+
+    let message = MessageParser::default()
+        .parse("Subject: test\r\n\r\nHello\r\n")
+        .unwrap();
+    assert_eq!(message.list_id(), None);
+}
+
+#[test]
+fn header_text() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: john@example.org\r\n",
+            "Subject: =?utf-8?q?caf=C3=A9?=\r\n",
+            "X-Custom: first value\r\n",
+            "x-custom: second value\r\n",
+            "\r\n",
+            "Hello\r\n"
+        ))
+        .unwrap();
+    assert_eq!(message.header_text("Subject"), Some("café"));
+    assert_eq!(message.header_text("SUBJECT"), Some("café"));
+    assert_eq!(message.header_text("X-Custom"), Some("first value"));
+    assert_eq!(message.header_text("x-CUSTOM"), Some("first value"));
+    assert_eq!(message.header_text("From"), None);
+    assert_eq!(message.header_text("X-Missing"), None);
+    assert_eq!(message.header_text(""), None);
+}
+
+#[test]
+fn innermost_message() {
+    let input = concat!(
+        "From: alice@example.org\r\n",
+        "Subject: Fwd: Fwd: Fwd: Lunch\r\n",
+        "Content-Type: message/rfc822\r\n",
+        "\r\n",
+        "From: bob@example.org\r\n",
+        "Subject: Fwd: Fwd: Lunch\r\n",
+        "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: message/rfc822\r\n",
+        "\r\n",
+        "From: carol@example.org\r\n",
+        "Subject: Fwd: Lunch\r\n",
+        "Content-Type: message/rfc822\r\n",
+        "\r\n",
+        "From: dave@example.org\r\n",
+        "Subject: Lunch\r\n",
+        "\r\n",
+        "Noon?\r\n",
+        "--b--\r\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+    let innermost = message.innermost_message();
+    assert_eq!(innermost.subject(), Some("Lunch"));
+    assert_eq!(innermost.body_text(0).as_deref(), Some("Noon?"));
+    assert!(std::ptr::eq(innermost.innermost_message(), innermost));
+
+    // Forwards with a comment stop the descent
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: alice@example.org\r\n",
+            "Subject: Fwd: Lunch\r\n",
+            "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "FYI\r\n",
+            "--b\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "\r\n",
+            "From: dave@example.org\r\n",
+            "Subject: Lunch\r\n",
+            "\r\n",
+            "Noon?\r\n",
+            "--b--\r\n"
+        ))
+        .unwrap();
+    assert!(std::ptr::eq(message.innermost_message(), &message));
+
+    // As do several nested messages
+    let message = MessageParser::default()
+        .parse(concat!(
+            "Subject: Digest\r\n",
+            "Content-Type: multipart/digest; boundary=\"b\"\r\n",
+            "\r\n",
+            "--b\r\n",
+            "\r\n",
+            "Subject: One\r\n",
+            "\r\n",
+            "1\r\n",
+            "--b\r\n",
+            "\r\n",
+            "Subject: Two\r\n",
+            "\r\n",
+            "2\r\n",
+            "--b--\r\n"
+        ))
+        .unwrap();
+    assert!(std::ptr::eq(message.innermost_message(), &message));
+}
+
+#[test]
+fn face_headers() {
+    let message = MessageParser::default()
+        .parse(concat!(
+            "From: john@example.org\r\n",
+            "Face: iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR4\r\n",
+            " 2mNk+M9QDwADhgGAWjR9awAAAABJRU5ErkJggg==\r\n",
+            "X-Face: \"8,2%wbc!;^Ny{@(&J;h]cQ)e4[;k4L3K\r\n",
+            " ~dpCk_u~n3M\r\n",
+            "Subject: Avatar\r\n",
+            "\r\n",
+            "Hello\r\n"
+        ))
+        .unwrap();
+    assert_eq!(message.parts[0].headers[1].name, HeaderName::Face);
+    assert_eq!(message.parts[0].headers[2].name, HeaderName::XFace);
+    let png = message.face_png().unwrap();
+    assert_eq!(png.len(), 70);
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+    assert_eq!(
+        message.x_face(),
+        Some("\"8,2%wbc!;^Ny{@(&J;h]cQ)e4[;k4L3K\r\n ~dpCk_u~n3M")
+    );
+
+    // Not a PNG image
+    let message = MessageParser::default()
+        .parse("Face: SGVsbG8gd29ybGQ=\r\n\r\nHello\r\n")
+        .unwrap();
+    assert_eq!(message.face_png(), None);
+
+    let message = MessageParser::default()
+        .parse("Subject: test\r\n\r\nHello\r\n")
+        .unwrap();
+    assert_eq!(message.face_png(), None);
+    assert_eq!(message.x_face(), None);
+}
+
+#[test]
+fn attachment_sizes() {
+    let input = concat!(
+        "From: john@example.org\r\n",
+        "Content-Type: multipart/mixed; boundary=\"a\"\r\n",
+        "\r\n",
+        "--a\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Body\r\n",
+        "--a\r\n",
+        "Content-Type: application/octet-stream; name=\"one.bin\"\r\n",
+        "Content-Transfer-Encoding: base64\r\n",
+        "\r\n",
+        "SGVsbG8gd29ybGQ=\r\n",
+        "--a\r\n",
+        "Content-Type: application/octet-stream; name=\"two.bin\"\r\n",
+        "\r\n",
+        "0123456789ABCDEF\r\n",
+        "--a\r\n",
+        "Content-Type: message/rfc822\r\n",
+        "\r\n",
+        "Subject: Nested\r\n",
+        "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Nested body\r\n",
+        "--b\r\n",
+        "Content-Type: application/octet-stream; name=\"three.bin\"\r\n",
+        "\r\n",
+        "0123456789ABCDEFGHIJ\r\n",
+        "--b--\r\n",
+        "--a--\r\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+    let nested_len = message.attachment(2).unwrap().len();
+    assert!(nested_len > 20);
+
+    assert_eq!(message.attachments_total_size(), 11 + 16 + nested_len);
+    assert_eq!(
+        message
+            .largest_attachment()
+            .and_then(|part| part.message())
+            .and_then(|message| message.subject()),
+        Some("Nested")
+    );
+    assert_eq!(message.attachments_total_size_recursive(), 11 + 16 + 20);
+    assert_eq!(
+        message
+            .largest_attachment_recursive()
+            .and_then(|part| part.attachment_name()),
+        Some("three.bin")
+    );
+
+    // Ties keep the first attachment
+    let input = concat!(
+        "Content-Type: multipart/mixed; boundary=\"a\"\r\n",
+        "\r\n",
+        "--a\r\n",
+        "Content-Type: image/png; name=\"first.png\"\r\n",
+        "\r\n",
+        "1234\r\n",
+        "--a\r\n",
+        "Content-Type: image/png; name=\"second.png\"\r\n",
+        "\r\n",
+        "5678\r\n",
+        "--a--\r\n"
+    );
+    let message = MessageParser::default().parse(input).unwrap();
+    assert_eq!(
+        message
+            .largest_attachment()
+            .and_then(|part| part.attachment_name()),
+        Some("first.png")
+    );
+
+    let message = MessageParser::default()
+        .parse("Subject: No attachments\r\n\r\nBody\r\n")
+        .unwrap();
+    assert_eq!(message.attachments_total_size(), 0);
+    assert_eq!(message.attachments_total_size_recursive(), 0);
+    assert_eq!(message.largest_attachment(), None);
+    assert_eq!(message.largest_attachment_recursive(), None);
+}